@@ -9,6 +9,9 @@
 //!
 //! The code in this example file is released into the Public Domain.
 
+// fields of the types below are only read through their `Debug` implementations
+#![allow(dead_code)]
+
 use osmflat::{iter_tags, FileResourceStorage, Osm, RelationMembersRef, COORD_SCALE};
 
 use std::fmt;
@@ -87,7 +90,7 @@ impl<'ar> Member<'ar> {
         let strings = archive.stringtable();
         archive
             .relation_members()
-            .at(relation_idx as usize)
+            .at(relation_idx)
             .map(move |member| {
                 let res = match member {
                     RelationMembersRef::NodeMember(m) => Member {
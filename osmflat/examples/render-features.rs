@@ -119,7 +119,7 @@ impl Feature {
             Category::Road | Category::River(_) => {
                 Some(way_into_polyline(&archive.ways()[self.idx]))
             }
-            Category::Park | Category::Water => multipolygon_into_polyline(&archive, self.idx),
+            Category::Park | Category::Water => multipolygon_into_polyline(archive, self.idx),
         }
     }
 }
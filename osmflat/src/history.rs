@@ -0,0 +1,135 @@
+//! Access to history archives.
+//!
+//! A history archive contains all versions of each entity, ordered by id and
+//! version. References to entities point to the first version of the
+//! referenced entity; a [`Snapshot`] resolves them to the version valid at a
+//! given point in time.
+
+use crate::{Node, Osm, Relation, Validity, Way};
+
+/// State of a history archive at a point in time.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm, Snapshot};
+///
+/// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+/// let archive = Osm::open(storage).unwrap();
+/// let snapshot = Snapshot::new(&archive, 1_600_000_000).expect("not a history archive");
+///
+/// for (_, way) in snapshot.ways() {
+///     let refs = &archive.nodes_index()[way.refs().start as usize..way.refs().end as usize];
+///     for node_idx in refs.iter().filter_map(|idx| idx.value()) {
+///         if let Some(idx) = snapshot.resolve_node(node_idx) {
+///             println!("{:?}", archive.nodes()[idx as usize]);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Snapshot<'a> {
+    archive: &'a Osm,
+    timestamp: i64,
+}
+
+impl<'a> Snapshot<'a> {
+    /// Creates the snapshot of `archive` at `timestamp` (in seconds since the
+    /// epoch).
+    ///
+    /// Returns `None` if `archive` is not a history archive.
+    pub fn new(archive: &'a Osm, timestamp: i64) -> Option<Self> {
+        archive.node_validity()?;
+        Some(Self { archive, timestamp })
+    }
+
+    /// Timestamp of the snapshot in seconds since the epoch.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Iterates over the versions of nodes which are visible in the snapshot,
+    /// together with their index in the `nodes` vector.
+    pub fn nodes(&self) -> impl Iterator<Item = (usize, &'a Node)> + 'a {
+        let nodes = self.archive.nodes();
+        visible(self.archive.node_validity(), self.timestamp).map(move |idx| (idx, &nodes[idx]))
+    }
+
+    /// Iterates over the versions of ways which are visible in the snapshot,
+    /// together with their index in the `ways` vector.
+    pub fn ways(&self) -> impl Iterator<Item = (usize, &'a Way)> + 'a {
+        let ways = self.archive.ways();
+        visible(self.archive.way_validity(), self.timestamp).map(move |idx| (idx, &ways[idx]))
+    }
+
+    /// Iterates over the versions of relations which are visible in the
+    /// snapshot, together with their index in the `relations` vector.
+    pub fn relations(&self) -> impl Iterator<Item = (usize, &'a Relation)> + 'a {
+        let relations = self.archive.relations();
+        visible(self.archive.relation_validity(), self.timestamp)
+            .map(move |idx| (idx, &relations[idx]))
+    }
+
+    /// Resolves a reference to a node to the index of its version visible in
+    /// the snapshot.
+    ///
+    /// Returns `None` if the node does not exist at the time of the snapshot.
+    pub fn resolve_node(&self, idx: u64) -> Option<u64> {
+        let nodes = self.archive.nodes();
+        let validity = self.archive.node_validity().unwrap_or_default();
+        resolve(validity, self.timestamp, idx, |idx| nodes[idx].id())
+    }
+
+    /// Resolves a reference to a way to the index of its version visible in
+    /// the snapshot.
+    ///
+    /// Returns `None` if the way does not exist at the time of the snapshot.
+    pub fn resolve_way(&self, idx: u64) -> Option<u64> {
+        let ways = self.archive.ways();
+        let validity = self.archive.way_validity().unwrap_or_default();
+        resolve(validity, self.timestamp, idx, |idx| ways[idx].id())
+    }
+
+    /// Resolves a reference to a relation to the index of its version visible
+    /// in the snapshot.
+    ///
+    /// Returns `None` if the relation does not exist at the time of the
+    /// snapshot.
+    pub fn resolve_relation(&self, idx: u64) -> Option<u64> {
+        let relations = self.archive.relations();
+        let validity = self.archive.relation_validity().unwrap_or_default();
+        resolve(validity, self.timestamp, idx, |idx| relations[idx].id())
+    }
+}
+
+fn is_visible(validity: &Validity, timestamp: i64) -> bool {
+    validity.visible()
+        && validity.valid_from() <= timestamp
+        && validity
+            .valid_to()
+            .map_or(true, |valid_to| timestamp < valid_to)
+}
+
+fn visible(validity: Option<&[Validity]>, timestamp: i64) -> impl Iterator<Item = usize> + '_ {
+    validity
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter(move |(_, validity)| is_visible(validity, timestamp))
+        .map(|(idx, _)| idx)
+}
+
+/// Finds the visible version among the versions starting at `first_idx`.
+fn resolve(
+    validity: &[Validity],
+    timestamp: i64,
+    first_idx: u64,
+    id: impl Fn(usize) -> i64,
+) -> Option<u64> {
+    let first_idx = first_idx as usize;
+    let first_id = id(first_idx);
+    (first_idx..validity.len())
+        .take_while(|&idx| id(idx) == first_id)
+        .find(|&idx| is_visible(&validity[idx], timestamp))
+        .map(|idx| idx as u64)
+}
@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 #![allow(clippy::all)] // generated code is not clippy friendly
+#![allow(unknown_lints, mismatched_lifetime_syntaxes)]
 
 //! Flat OpenStreetMap (OSM) data format providing an efficient *random* data
 //! access through [memory mapped files].
@@ -41,8 +42,13 @@ extern crate flatdata;
 // generated osm module
 include!("osmflat_generated.rs");
 
+mod history;
+mod lookup;
+mod parents;
+mod spatial;
 mod tags;
 
+pub use crate::history::*;
 pub use crate::osm::*;
 pub use crate::tags::*;
 
@@ -0,0 +1,51 @@
+//! Lookup of entities by id.
+//!
+//! Requires an archive compiled with an id index (`osmflatc --with-id-index`).
+
+use crate::{IdIndex, Node, Osm, Relation, Way};
+
+impl Osm {
+    /// Finds the node with the id `id` in O(log n).
+    ///
+    /// In history archives, the first version of the node is returned. Returns
+    /// `None` if there is no such node or the archive has no id index.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+    /// let archive = Osm::open(storage).unwrap();
+    /// if let Some(node) = archive.node_by_id(240109189) {
+    ///     println!("{:?}", node);
+    /// }
+    /// ```
+    pub fn node_by_id(&self, id: i64) -> Option<&Node> {
+        find(self.node_id_index()?, id).map(|idx| &self.nodes()[idx])
+    }
+
+    /// Finds the way with the id `id` in O(log n).
+    ///
+    /// In history archives, the first version of the way is returned. Returns
+    /// `None` if there is no such way or the archive has no id index.
+    pub fn way_by_id(&self, id: i64) -> Option<&Way> {
+        find(self.way_id_index()?, id).map(|idx| &self.ways()[idx])
+    }
+
+    /// Finds the relation with the id `id` in O(log n).
+    ///
+    /// In history archives, the first version of the relation is returned.
+    /// Returns `None` if there is no such relation or the archive has no id
+    /// index.
+    pub fn relation_by_id(&self, id: i64) -> Option<&Relation> {
+        find(self.relation_id_index()?, id).map(|idx| &self.relations()[idx])
+    }
+}
+
+fn find(index: &[IdIndex], id: i64) -> Option<usize> {
+    index
+        .binary_search_by_key(&id, |entry| entry.id())
+        .ok()
+        .map(|pos| index[pos].idx() as usize)
+}
@@ -7,6 +7,12 @@ pub const INVALID_IDX: u64 = 1_099_511_627_775;
 
     /// All coordinate are scaled by this constant to convert them to integers.
 pub const COORD_SCALE: u64 = 1_000_000_000;
+
+    /// Special value which represents an unset timestamp.
+pub const INVALID_TIMESTAMP: i64 = 549_755_813_887;
+
+    /// Maximum number of children of a node of an R-tree.
+pub const RTREE_NODE_SIZE: u64 = 16;
 /// Metadata attached to the archive.
 #[repr(transparent)]
 #[derive(Clone)]
@@ -1450,537 +1456,3100 @@ impl Relation {
         self.set_tag_first_idx(other.tag_first_idx());
     }
 }
-
-
-/// Enum for read-only heterogeneous access to elements in a
-/// bucket of the [`relation_members`] resource.
+/// Metadata of a node, way or relation.
 ///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-#[derive(Clone, PartialEq)]
-pub enum RelationMembersRef<'a> {
-    #[allow(missing_docs)]
-    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
-    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
-    RelationMember(&'a super::osm::RelationMember),}
+/// See <https://wiki.openstreetmap.org/wiki/Elements#Common_attributes>.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Info {
+    data: [u8; 23],
+}
 
-impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        match *self {
-            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
-        }
+impl Info {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 23]}
     }
 }
 
-impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
-    #[inline]
-    fn size_in_bytes(&self) -> usize {
-        match *self {
-            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
-        }
+impl flatdata::Struct for Info {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 23]}
     }
-}
 
-/// Builder of buckets in the [`relation_members`] resource.
-///
-/// Refers to a single bucket in the [`relation_members`] multivector and
-/// provides methods for adding heterogeneous data to the bucket.
-///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-pub struct RelationMembersBuilder<'a> {
-    data: &'a mut Vec<u8>
+    const SIZE_IN_BYTES: usize = 23;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
 }
 
-impl<'a> RelationMembersBuilder<'a> {
-    /// Adds data of the type [`NodeMember`] to the bucket.
-    ///
-    /// [`NodeMember`]: struct.NodeMember.html
-    #[inline]
-    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 0;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
-    }
-    /// Adds data of the type [`WayMember`] to the bucket.
-    ///
-    /// [`WayMember`]: struct.WayMember.html
-    #[inline]
-    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 1;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
-    }
-    /// Adds data of the type [`RelationMember`] to the bucket.
-    ///
-    /// [`RelationMember`]: struct.RelationMember.html
-    #[inline]
-    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 2;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+impl Info {
+    pub fn new( ) -> Self {
+        Self{data : [0; 23]}
     }
-}
-
-/// Variadic struct attached to the [`relation_members`] archive resource.
-///
-/// It unifies the following data types:
-//
-/// * [`NodeMember`]
-/// * [`WayMember`]
-/// * [`RelationMember`]
-///
-/// ## Access pattern
-///
-/// This structure is used as a template parameter in [`relation_members`] multivector/
-/// multiarray view. It does not contain any data, instead it references
-///
-/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
-/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
-///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-/// [`RelationMembersRef`]: enum.RelationMembersRef.html
-/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
-/// [`NodeMember`]: struct.NodeMember.html
-/// [`WayMember`]: struct.WayMember.html
-/// [`RelationMember`]: struct.RelationMember.html
-#[derive(Clone)]
-pub struct RelationMembers {}
 
-impl flatdata::VariadicIndex for RelationMembers {
-    type Index = super::_builtin::multivector::IndexType40;
-}
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 23]) -> &Self {
+        // Safety: This is safe since Info is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
 
-impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
-    type Item = RelationMembersRef<'a>;
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 23]) -> &mut Self {
+        // Safety: This is safe since Info is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
 
-    #[inline]
-    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
-    {
-        match index {
-                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
-                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
-                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
-            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 23 {
+            assert_eq!(data.len(), 23);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
         }
+        let ptr = data.as_ptr() as *const [u8; 23];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
     }
 
-    type ItemMut = RelationMembersBuilder<'a>;
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 23 {
+            assert_eq!(data.len(), 23);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 23];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
 
-    #[inline]
-    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
-    {
-        Self::ItemMut { data }
+    pub fn as_bytes(&self) -> &[u8; 23] {
+        &self.data
     }
 }
 
-/// OSM data archive
-///
-/// Relations and relation members are indexed with the same index, i.e.
-/// a relation at index `i` in the vector `relations` has the members
-/// at index `i` in the multivector `relation_members`.
-///
-/// All 1:n relationships are modeled in-place by using an additional index. This is a
-/// common pattern in flatdata. For example, a node might have multiple tags attached
-/// to it. To model this, a node in `nodes` references the first tag attached to it
-/// by storing an index in the `tags_index` vector. The next node in `nodes` again
-/// references its first tag, which is the last tag (exclusive) of the previous node.
-///
-/// ```text
-/// nodes:      [ ..., n_1, n_2, ... ]
-///                     |    |
-///                     |    +-------+
-///                     v            v
-/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
-/// ```
-#[derive(Clone)]
-pub struct Osm {
-    _storage: flatdata::StorageHandle,
-    header : &'static super::osm::Header,
-    nodes : &'static [super::osm::Node],
-    ways : &'static [super::osm::Way],
-    relations : &'static [super::osm::Relation],
-    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
-    tags : &'static [super::osm::Tag],
-    tags_index : &'static [super::osm::TagIndex],
-    nodes_index : &'static [super::osm::NodeIndex],
-    stringtable : flatdata::RawData<'static>,
+impl Default for Info {
+    fn default( ) -> Self {
+        Self::new( )
+    }
 }
 
-impl Osm {
-    fn signature_name(archive_name: &str) -> String {
-        format!("{}.archive", archive_name)
+unsafe impl flatdata::NoOverlap for Info {}
+
+impl Info {
+    /// Version of the entity, or 0 if unknown.
+    #[inline]
+    pub fn version(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// Header which contains the metadata attached to the archive.
+    /// Timestamp of the last edit, expressed in seconds since the epoch.
     #[inline]
-    pub fn header(&self) -> &super::osm::Header {
-        self.header
+    pub fn timestamp(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 32, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// List of nodes.
-///
-/// A node references a range of tags in the `tags_index` vector.
+    /// Changeset of the last edit.
     #[inline]
-    pub fn nodes(&self) -> &[super::osm::Node] {
-        self.nodes
+    pub fn changeset(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 72, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// List of ways.
-///
-/// A way references
-///
-/// * a range of tags in the `tags_index` vector, and
-/// * a range of nodes in the `nodes_index` vector.
+    /// Id of the user who made the last edit.
     #[inline]
-    pub fn ways(&self) -> &[super::osm::Way] {
-        self.ways
+    pub fn uid(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 112, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// List of relations.
-///
-/// A relation references a range of tags in `tags_index` vectors.
-/// Members are attached to a relation implicitly: members that belong to a
-/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    /// Name of the user who made the last edit (reference to `stringtable`).
     #[inline]
-    pub fn relations(&self) -> &[super::osm::Relation] {
-        self.relations
+    pub fn user_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 144, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
     }
 
-    /// Members attached to relations.
-///
-/// An index in this multivector corresponds to an index in the `relations` vector.
-///
-/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
-/// Each type references its role in the `stringtable` raw data. Additionally,
-///
-/// * a node member references a node in the `nodes` vector,
-/// * a way member references a way in the `ways` vector,
-/// * a relation member references a relation in the `relations` vector.
+}
+
+impl std::fmt::Debug for Info {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Info")
+            .field("version", &self.version())
+            .field("timestamp", &self.timestamp())
+            .field("changeset", &self.changeset())
+            .field("uid", &self.uid())
+            .field("user_idx", &self.user_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Info {
     #[inline]
-    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
-        &self.relation_members
+    fn eq(&self, other: &Self) -> bool {
+        self.version() == other.version() &&        self.timestamp() == other.timestamp() &&        self.changeset() == other.changeset() &&        self.uid() == other.uid() &&        self.user_idx() == other.user_idx()     }
+}
+
+impl Info {
+    /// Version of the entity, or 0 if unknown.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_version(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
     }
 
-    /// List of tags.
-///
-/// A tag references its key and value in the `stringtable` raw data.
+    /// Timestamp of the last edit, expressed in seconds since the epoch.
     #[inline]
-    pub fn tags(&self) -> &[super::osm::Tag] {
-        self.tags
+    #[allow(missing_docs)]
+    pub fn set_timestamp(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 32, 40)
     }
 
-    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
-/// and tags.
+    /// Changeset of the last edit.
     #[inline]
-    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
-        self.tags_index
+    #[allow(missing_docs)]
+    pub fn set_changeset(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 72, 40)
     }
 
-    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    /// Id of the user who made the last edit.
     #[inline]
-    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
-        self.nodes_index
+    #[allow(missing_docs)]
+    pub fn set_uid(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 112, 32)
     }
 
-    /// List of strings separated by `\0`.
+    /// Name of the user who made the last edit (reference to `stringtable`).
     #[inline]
-    pub fn stringtable(&self) -> flatdata::RawData {
-        self.stringtable
+    #[allow(missing_docs)]
+    pub fn set_user_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 144, 40)
     }
 
-}
 
-impl ::std::fmt::Debug for Osm {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        f.debug_struct("Osm")
-            .field("header", &self.header())
-            .field("nodes", &self.nodes())
-            .field("ways", &self.ways())
-            .field("relations", &self.relations())
-            .field("relation_members", &self.relation_members())
-            .field("tags", &self.tags())
-            .field("tags_index", &self.tags_index())
-            .field("nodes_index", &self.nodes_index())
-            .field("stringtable", &self.stringtable())
-            .finish()
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Info) {
+        self.set_version(other.version());
+        self.set_timestamp(other.timestamp());
+        self.set_changeset(other.changeset());
+        self.set_uid(other.uid());
+        self.set_user_idx(other.user_idx());
     }
 }
+/// Validity of a version of an entity in a history archive.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Validity {
+    data: [u8; 11],
+}
 
-impl Osm {
-    pub fn open(storage: flatdata::StorageHandle)
-        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
-    {
-        #[allow(unused_imports)]
-        use flatdata::SliceExt;
-        // extend lifetime since Rust cannot know that we reference a cache here
-        #[allow(unused_variables)]
-        let extend = |x : Result<&[u8], flatdata::ResourceStorageError>| -> Result<&'static [u8], flatdata::ResourceStorageError> {x.map(|x| unsafe{std::mem::transmute(x)})};
+impl Validity {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 11]}
+    }
+}
 
-        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
+impl flatdata::Struct for Validity {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 11]}
+    }
 
-        let resource = extend(storage.read("header", schema::osm::resources::HEADER));
-        let header = resource.map(|x| super::osm::Header::from_bytes_slice(x))??;
-        let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
-        let nodes = resource.map(|x| <&[super::osm::Node]>::from_bytes(x))??;
-        let size = nodes.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "nodes", size}); }
-        let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
-        let ways = resource.map(|x| <&[super::osm::Way]>::from_bytes(x))??;
-        let size = ways.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "ways", size}); }
-        let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
-        let relations = resource.map(|x| <&[super::osm::Relation]>::from_bytes(x))??;
-        let size = relations.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "relations", size}); }
-        let relation_members = {
-            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
-            let index = extend(storage.read("relation_members_index", &index_schema));
-            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
-            let result = match (index, data) {
-                (Ok(index), Ok(data)) => {
-                    Ok(flatdata::MultiArrayView::new(
-                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
-                        data
-                    ))
-                }
-                (Ok(_), Err(x)) | (Err(x), Ok(_)) => {return Err(x);}
-                (Err(x), Err(_)) => Err(x),
-            };
-            result?
-        };
-        let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
-        let tags = resource.map(|x| <&[super::osm::Tag]>::from_bytes(x))??;
-        let size = tags.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "tags", size}); }
-        let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
-        let tags_index = resource.map(|x| <&[super::osm::TagIndex]>::from_bytes(x))??;
-        let size = tags_index.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "tags_index", size}); }
-        let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
-        let nodes_index = resource.map(|x| <&[super::osm::NodeIndex]>::from_bytes(x))??;
-        let size = nodes_index.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "nodes_index", size}); }
-        let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
-        let stringtable = resource.map(|x| flatdata::RawData::new(x))?;
-        let size = stringtable.len();
-        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "stringtable", size}); }
+    const SIZE_IN_BYTES: usize = 11;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
 
-        Ok(Self {
-            _storage: storage,
-            header,
-            nodes,
-            ways,
-            relations,
-            relation_members,
-            tags,
-            tags_index,
-            nodes_index,
-            stringtable,
-        })
+impl Validity {
+    pub fn new( ) -> Self {
+        Self{data : [0; 11]}
     }
-}
 
-/// Builder for creating [`Osm`] archives.
-///
-///[`Osm`]: struct.Osm.html
-#[derive(Clone, Debug)]
-pub struct OsmBuilder {
-    storage: flatdata::StorageHandle
-}
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 11]) -> &Self {
+        // Safety: This is safe since Validity is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
 
-impl OsmBuilder {
-    #[inline]
-    /// Stores [`header`] in the archive.
-    ///
-    /// [`header`]: struct.Osm.html#method.header
-    /// Stores [`header`] in the archive.
-    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
-        let data = resource.as_bytes();
-        self.storage.write("header", schema::osm::resources::HEADER, data)
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 11]) -> &mut Self {
+        // Safety: This is safe since Validity is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
     }
 
-    #[inline]
-    /// Stores [`nodes`] in the archive.
-    ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
-    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 11 {
+            assert_eq!(data.len(), 11);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 11];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
     }
 
-    /// Opens [`nodes`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
-        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 11 {
+            assert_eq!(data.len(), 11);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 11];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
     }
 
-    #[inline]
-    /// Stores [`ways`] in the archive.
-    ///
-    /// [`ways`]: struct.Osm.html#method.ways
-    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+    pub fn as_bytes(&self) -> &[u8; 11] {
+        &self.data
     }
+}
 
-    /// Opens [`ways`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`ways`]: struct.Osm.html#method.ways
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
-        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+impl Default for Validity {
+    fn default( ) -> Self {
+        Self::new( )
     }
+}
 
+unsafe impl flatdata::NoOverlap for Validity {}
+
+impl Validity {
+    /// Start of the validity interval (inclusive), in seconds since the epoch.
     #[inline]
-    /// Stores [`relations`] in the archive.
-    ///
-    /// [`relations`]: struct.Osm.html#method.relations
-    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+    pub fn valid_from(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// Opens [`relations`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relations`]: struct.Osm.html#method.relations
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// End of the validity interval (exclusive), in seconds since the epoch,
+/// or `None` if this is the latest version.
     #[inline]
-    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
-        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    pub fn valid_to(&self) -> Option<i64> {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 40, 40);
+        let x = unsafe { std::mem::transmute::<i64, i64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_TIMESTAMP)
     }
 
-    /// Opens [`relation_members`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the multivector until the [`MultiVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relation_members`]: struct.Osm.html#method.relation_members
-    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
+    /// Whether the entity exists in this version, i.e. `false` if the version
+/// deletes the entity.
     #[inline]
-    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
-        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+    pub fn visible(&self) -> bool {
+        let value = flatdata_read_bytes!(bool, self.data.as_ptr(), 80, 1);
+        unsafe { std::mem::transmute::<bool, bool>(value) }
     }
 
-    #[inline]
-    /// Stores [`tags`] in the archive.
-    ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+}
+
+impl std::fmt::Debug for Validity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Validity")
+            .field("valid_from", &self.valid_from())
+            .field("valid_to", &self.valid_to())
+            .field("visible", &self.visible())
+            .finish()
     }
+}
 
-    /// Opens [`tags`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+impl std::cmp::PartialEq for Validity {
     #[inline]
-    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
-        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
-    }
+    fn eq(&self, other: &Self) -> bool {
+        self.valid_from() == other.valid_from() &&        self.valid_to() == other.valid_to() &&        self.visible() == other.visible()     }
+}
 
+impl Validity {
+    /// Start of the validity interval (inclusive), in seconds since the epoch.
     #[inline]
-    /// Stores [`tags_index`] in the archive.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    #[allow(missing_docs)]
+    pub fn set_valid_from(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 0, 40)
     }
 
-    /// Opens [`tags_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// End of the validity interval (exclusive), in seconds since the epoch,
+/// or `None` if this is the latest version.
     #[inline]
-    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
-        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    #[allow(missing_docs)]
+    pub fn set_valid_to(&mut self, value: Option<i64>) {
+let value = value.unwrap_or(super::osm::INVALID_TIMESTAMP);        flatdata_write_bytes!(i64; value, self.data, 40, 40)
     }
 
+    /// Whether the entity exists in this version, i.e. `false` if the version
+/// deletes the entity.
     #[inline]
-    /// Stores [`nodes_index`] in the archive.
-    ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
-    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    #[allow(missing_docs)]
+    pub fn set_visible(&mut self, value: bool) {
+        flatdata_write_bytes!(bool; value, self.data, 80, 1)
     }
 
-    /// Opens [`nodes_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Validity) {
+        self.set_valid_from(other.valid_from());
+        self.set_valid_to(other.valid_to());
+        self.set_visible(other.visible());
+    }
+}
+/// Maps the id of an entity to its index.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct IdIndex {
+    data: [u8; 13],
+}
+
+impl IdIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 13]}
+    }
+}
+
+impl flatdata::Struct for IdIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 13]}
+    }
+
+    const SIZE_IN_BYTES: usize = 13;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl IdIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 13]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 13]) -> &Self {
+        // Safety: This is safe since IdIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 13]) -> &mut Self {
+        // Safety: This is safe since IdIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 13 {
+            assert_eq!(data.len(), 13);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 13];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 13 {
+            assert_eq!(data.len(), 13);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 13];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 13] {
+        &self.data
+    }
+}
+
+impl Default for IdIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for IdIndex {}
+
+impl IdIndex {
+    /// Id of the entity.
+    #[inline]
+    pub fn id(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 0, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Index of the entity in its vector.
+    #[inline]
+    pub fn idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 64, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for IdIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IdIndex")
+            .field("id", &self.id())
+            .field("idx", &self.idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for IdIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id() &&        self.idx() == other.idx()     }
+}
+
+impl IdIndex {
+    /// Id of the entity.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_id(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 0, 64)
+    }
+
+    /// Index of the entity in its vector.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 64, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &IdIndex) {
+        self.set_id(other.id());
+        self.set_idx(other.idx());
+    }
+}
+/// Range of ways using a node.
+///
+/// The node at index `i` in the `nodes` vector is used by the ways in the range
+/// of the element at index `i` in the `node_ways` vector.
+#[repr(transparent)]
+pub struct NodeWays {
+    data: [u8; 5],
+}
+
+impl NodeWays {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for NodeWays {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for NodeWays {}
+
+impl NodeWays {
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: #method.ways
+    #[inline]
+    pub fn way_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of ways using the node.
+///
+/// The values of the range are indexes in the `node_ways_index` vector.
+    #[inline]
+    pub fn ways(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for NodeWays {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NodeWays")
+            .field("way_first_idx", &self.way_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for NodeWays {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.way_first_idx() == other.way_first_idx()     }
+}
+
+impl NodeWays {
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: struct.NodeWaysRef.html#method.ways
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_way_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &NodeWays) {
+        self.set_way_first_idx(other.way_first_idx());
+    }
+}
+/// Index of a way.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct WayIndex {
+    data: [u8; 5],
+}
+
+impl WayIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for WayIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl WayIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since WayIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since WayIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for WayIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for WayIndex {}
+
+impl WayIndex {
+    /// Index in the `ways` vector.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for WayIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WayIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for WayIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl WayIndex {
+    /// Index in the `ways` vector.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &WayIndex) {
+        self.set_value(other.value());
+    }
+}
+/// Range of relations having an entity as member.
+///
+/// The entity at index `i` in its vector is a member of the relations in the range
+/// of the element at index `i` in the corresponding parent relations vector.
+#[repr(transparent)]
+pub struct ParentRelations {
+    data: [u8; 5],
+}
+
+impl ParentRelations {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for ParentRelations {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for ParentRelations {}
+
+impl ParentRelations {
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: #method.relations
+    #[inline]
+    pub fn relation_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of relations having the entity as member.
+///
+/// The values of the range are indexes in the corresponding parent relations index vector.
+    #[inline]
+    pub fn relations(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for ParentRelations {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ParentRelations")
+            .field("relation_first_idx", &self.relation_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ParentRelations {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.relation_first_idx() == other.relation_first_idx()     }
+}
+
+impl ParentRelations {
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: struct.ParentRelationsRef.html#method.relations
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ParentRelations) {
+        self.set_relation_first_idx(other.relation_first_idx());
+    }
+}
+/// Relation having an entity as member.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct ParentRelation {
+    data: [u8; 10],
+}
+
+impl ParentRelation {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
+}
+
+impl flatdata::Struct for ParentRelation {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
+
+    const SIZE_IN_BYTES: usize = 10;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl ParentRelation {
+    pub fn new( ) -> Self {
+        Self{data : [0; 10]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 10]) -> &Self {
+        // Safety: This is safe since ParentRelation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 10]) -> &mut Self {
+        // Safety: This is safe since ParentRelation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 10] {
+        &self.data
+    }
+}
+
+impl Default for ParentRelation {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for ParentRelation {}
+
+impl ParentRelation {
+    /// Index in the `relations` vector.
+    #[inline]
+    pub fn relation_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Role of the member (index in `stringtable`).
+    #[inline]
+    pub fn role_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for ParentRelation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ParentRelation")
+            .field("relation_idx", &self.relation_idx())
+            .field("role_idx", &self.role_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ParentRelation {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.relation_idx() == other.relation_idx() &&        self.role_idx() == other.role_idx()     }
+}
+
+impl ParentRelation {
+    /// Index in the `relations` vector.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Role of the member (index in `stringtable`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_role_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ParentRelation) {
+        self.set_relation_idx(other.relation_idx());
+        self.set_role_idx(other.role_idx());
+    }
+}
+/// Entities carrying a tag or a key.
+///
+/// Entries are sorted by the key and value strings; the entry of a key without
+/// value precedes the entries of the values of the key.
+#[repr(transparent)]
+pub struct TagEntities {
+    data: [u8; 25],
+}
+
+impl TagEntities {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 25]}
+    }
+}
+
+impl flatdata::Struct for TagEntities {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 25]}
+    }
+
+    const SIZE_IN_BYTES: usize = 25;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for TagEntities {}
+
+impl TagEntities {
+    /// Key of the tag (index in `stringtable`).
+    #[inline]
+    pub fn key_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Value of the tag (index in `stringtable`), or `None` for the entry of
+/// all tags with the key.
+    #[inline]
+    pub fn value_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
+    }
+
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: #method.nodes
+    #[inline]
+    pub fn node_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of nodes carrying the tag.
+///
+/// The values of the range are indexes in the `tag_entities_nodes` vector.
+    #[inline]
+    pub fn nodes(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 80 + 25 * 8, 40);
+        start..end
+    }
+
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: #method.ways
+    #[inline]
+    pub fn way_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 120, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of ways carrying the tag.
+///
+/// The values of the range are indexes in the `tag_entities_ways` vector.
+    #[inline]
+    pub fn ways(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 120, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 120 + 25 * 8, 40);
+        start..end
+    }
+
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: #method.relations
+    #[inline]
+    pub fn relation_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 160, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of relations carrying the tag.
+///
+/// The values of the range are indexes in the `tag_entities_relations` vector.
+    #[inline]
+    pub fn relations(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 160, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 160 + 25 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for TagEntities {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TagEntities")
+            .field("key_idx", &self.key_idx())
+            .field("value_idx", &self.value_idx())
+            .field("node_first_idx", &self.node_first_idx())
+            .field("way_first_idx", &self.way_first_idx())
+            .field("relation_first_idx", &self.relation_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for TagEntities {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key_idx() == other.key_idx() &&        self.value_idx() == other.value_idx() &&        self.node_first_idx() == other.node_first_idx() &&        self.way_first_idx() == other.way_first_idx() &&        self.relation_first_idx() == other.relation_first_idx()     }
+}
+
+impl TagEntities {
+    /// Key of the tag (index in `stringtable`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_key_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Value of the tag (index in `stringtable`), or `None` for the entry of
+/// all tags with the key.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: struct.TagEntitiesRef.html#method.nodes
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_node_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 80, 40)
+    }
+
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: struct.TagEntitiesRef.html#method.ways
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_way_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 120, 40)
+    }
+
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: struct.TagEntitiesRef.html#method.relations
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 160, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TagEntities) {
+        self.set_key_idx(other.key_idx());
+        self.set_value_idx(other.value_idx());
+        self.set_node_first_idx(other.node_first_idx());
+        self.set_way_first_idx(other.way_first_idx());
+        self.set_relation_first_idx(other.relation_first_idx());
+    }
+}
+/// Index of a relation.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct RelationIndex {
+    data: [u8; 5],
+}
+
+impl RelationIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for RelationIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl RelationIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since RelationIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since RelationIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for RelationIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for RelationIndex {}
+
+impl RelationIndex {
+    /// Index in the `relations` vector.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for RelationIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RelationIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for RelationIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl RelationIndex {
+    /// Index in the `relations` vector.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &RelationIndex) {
+        self.set_value(other.value());
+    }
+}
+/// Bounding box of an entity or of a node of an R-tree.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct BBox {
+    data: [u8; 20],
+}
+
+impl BBox {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 20]}
+    }
+}
+
+impl flatdata::Struct for BBox {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 20]}
+    }
+
+    const SIZE_IN_BYTES: usize = 20;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl BBox {
+    pub fn new( ) -> Self {
+        Self{data : [0; 20]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 20]) -> &Self {
+        // Safety: This is safe since BBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 20]) -> &mut Self {
+        // Safety: This is safe since BBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 20 {
+            assert_eq!(data.len(), 20);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 20];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 20 {
+            assert_eq!(data.len(), 20);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 20];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.data
+    }
+}
+
+impl Default for BBox {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for BBox {}
+
+impl BBox {
+    /// Min longitude (scaled with `COORD_SCALE`).
+    #[inline]
+    pub fn min_lon(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Min latitude (scaled with `COORD_SCALE`).
+    #[inline]
+    pub fn min_lat(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 40, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Max longitude (scaled with `COORD_SCALE`).
+    #[inline]
+    pub fn max_lon(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 80, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Max latitude (scaled with `COORD_SCALE`).
+    #[inline]
+    pub fn max_lat(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 120, 40);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for BBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BBox")
+            .field("min_lon", &self.min_lon())
+            .field("min_lat", &self.min_lat())
+            .field("max_lon", &self.max_lon())
+            .field("max_lat", &self.max_lat())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for BBox {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.min_lon() == other.min_lon() &&        self.min_lat() == other.min_lat() &&        self.max_lon() == other.max_lon() &&        self.max_lat() == other.max_lat()     }
+}
+
+impl BBox {
+    /// Min longitude (scaled with `COORD_SCALE`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_min_lon(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 0, 40)
+    }
+
+    /// Min latitude (scaled with `COORD_SCALE`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_min_lat(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 40, 40)
+    }
+
+    /// Max longitude (scaled with `COORD_SCALE`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_max_lon(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 80, 40)
+    }
+
+    /// Max latitude (scaled with `COORD_SCALE`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_max_lat(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 120, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &BBox) {
+        self.set_min_lon(other.min_lon());
+        self.set_min_lat(other.min_lat());
+        self.set_max_lon(other.max_lon());
+        self.set_max_lat(other.max_lat());
+    }
+}
+
+
+/// Enum for read-only heterogeneous access to elements in a
+/// bucket of the [`relation_members`] resource.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+#[derive(Clone, PartialEq)]
+pub enum RelationMembersRef<'a> {
+    #[allow(missing_docs)]
+    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
+    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
+    RelationMember(&'a super::osm::RelationMember),}
+
+impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
+        }
+    }
+}
+
+impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
+    #[inline]
+    fn size_in_bytes(&self) -> usize {
+        match *self {
+            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
+        }
+    }
+}
+
+/// Builder of buckets in the [`relation_members`] resource.
+///
+/// Refers to a single bucket in the [`relation_members`] multivector and
+/// provides methods for adding heterogeneous data to the bucket.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+pub struct RelationMembersBuilder<'a> {
+    data: &'a mut Vec<u8>
+}
+
+impl<'a> RelationMembersBuilder<'a> {
+    /// Adds data of the type [`NodeMember`] to the bucket.
+    ///
+    /// [`NodeMember`]: struct.NodeMember.html
+    #[inline]
+    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 0;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
+    }
+    /// Adds data of the type [`WayMember`] to the bucket.
+    ///
+    /// [`WayMember`]: struct.WayMember.html
+    #[inline]
+    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 1;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
+    }
+    /// Adds data of the type [`RelationMember`] to the bucket.
+    ///
+    /// [`RelationMember`]: struct.RelationMember.html
+    #[inline]
+    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 2;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+    }
+}
+
+/// Variadic struct attached to the [`relation_members`] archive resource.
+///
+/// It unifies the following data types:
+//
+/// * [`NodeMember`]
+/// * [`WayMember`]
+/// * [`RelationMember`]
+///
+/// ## Access pattern
+///
+/// This structure is used as a template parameter in [`relation_members`] multivector/
+/// multiarray view. It does not contain any data, instead it references
+///
+/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
+/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+/// [`RelationMembersRef`]: enum.RelationMembersRef.html
+/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
+/// [`NodeMember`]: struct.NodeMember.html
+/// [`WayMember`]: struct.WayMember.html
+/// [`RelationMember`]: struct.RelationMember.html
+#[derive(Clone)]
+pub struct RelationMembers {}
+
+impl flatdata::VariadicIndex for RelationMembers {
+    type Index = super::_builtin::multivector::IndexType40;
+}
+
+impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
+    type Item = RelationMembersRef<'a>;
+
+    #[inline]
+    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
+    {
+        match index {
+                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
+                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
+                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
+            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+        }
+    }
+
+    type ItemMut = RelationMembersBuilder<'a>;
+
+    #[inline]
+    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
+    {
+        Self::ItemMut { data }
+    }
+}
+
+/// OSM data archive
+///
+/// Relations and relation members are indexed with the same index, i.e.
+/// a relation at index `i` in the vector `relations` has the members
+/// at index `i` in the multivector `relation_members`.
+///
+/// All 1:n relationships are modeled in-place by using an additional index. This is a
+/// common pattern in flatdata. For example, a node might have multiple tags attached
+/// to it. To model this, a node in `nodes` references the first tag attached to it
+/// by storing an index in the `tags_index` vector. The next node in `nodes` again
+/// references its first tag, which is the last tag (exclusive) of the previous node.
+///
+/// ```text
+/// nodes:      [ ..., n_1, n_2, ... ]
+///                     |    |
+///                     |    +-------+
+///                     v            v
+/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
+/// ```
+///
+/// A history archive contains all versions of each entity, ordered by id and
+/// version, together with their validity in the `*_validity` vectors. In such an
+/// archive, references to entities (e.g. the nodes of a way) point to the first
+/// version of the referenced entity.
+#[derive(Clone)]
+pub struct Osm {
+    _storage: flatdata::StorageHandle,
+    header : &'static super::osm::Header,
+    nodes : &'static [super::osm::Node],
+    ways : &'static [super::osm::Way],
+    relations : &'static [super::osm::Relation],
+    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
+    tags : &'static [super::osm::Tag],
+    tags_index : &'static [super::osm::TagIndex],
+    nodes_index : &'static [super::osm::NodeIndex],
+    stringtable : flatdata::RawData<'static>,
+    node_info : Option<&'static [super::osm::Info]>,
+    way_info : Option<&'static [super::osm::Info]>,
+    relation_info : Option<&'static [super::osm::Info]>,
+    node_validity : Option<&'static [super::osm::Validity]>,
+    way_validity : Option<&'static [super::osm::Validity]>,
+    relation_validity : Option<&'static [super::osm::Validity]>,
+    node_id_index : Option<&'static [super::osm::IdIndex]>,
+    way_id_index : Option<&'static [super::osm::IdIndex]>,
+    relation_id_index : Option<&'static [super::osm::IdIndex]>,
+    node_ways : Option<&'static [super::osm::NodeWays]>,
+    node_ways_index : Option<&'static [super::osm::WayIndex]>,
+    node_parent_relations : Option<&'static [super::osm::ParentRelations]>,
+    node_parent_relations_index : Option<&'static [super::osm::ParentRelation]>,
+    way_parent_relations : Option<&'static [super::osm::ParentRelations]>,
+    way_parent_relations_index : Option<&'static [super::osm::ParentRelation]>,
+    relation_parent_relations : Option<&'static [super::osm::ParentRelations]>,
+    relation_parent_relations_index : Option<&'static [super::osm::ParentRelation]>,
+    tag_entities : Option<&'static [super::osm::TagEntities]>,
+    tag_entities_nodes : Option<&'static [super::osm::NodeIndex]>,
+    tag_entities_ways : Option<&'static [super::osm::WayIndex]>,
+    tag_entities_relations : Option<&'static [super::osm::RelationIndex]>,
+    node_rtree : Option<&'static [super::osm::BBox]>,
+    node_rtree_index : Option<&'static [super::osm::NodeIndex]>,
+    way_rtree : Option<&'static [super::osm::BBox]>,
+    way_rtree_index : Option<&'static [super::osm::WayIndex]>,
+}
+
+impl Osm {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Header which contains the metadata attached to the archive.
+    #[inline]
+    pub fn header(&self) -> &super::osm::Header {
+        self.header
+    }
+
+    /// List of nodes.
+///
+/// A node references a range of tags in the `tags_index` vector.
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Node] {
+        self.nodes
+    }
+
+    /// List of ways.
+///
+/// A way references
+///
+/// * a range of tags in the `tags_index` vector, and
+/// * a range of nodes in the `nodes_index` vector.
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Way] {
+        self.ways
+    }
+
+    /// List of relations.
+///
+/// A relation references a range of tags in `tags_index` vectors.
+/// Members are attached to a relation implicitly: members that belong to a
+/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Relation] {
+        self.relations
+    }
+
+    /// Members attached to relations.
+///
+/// An index in this multivector corresponds to an index in the `relations` vector.
+///
+/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
+/// Each type references its role in the `stringtable` raw data. Additionally,
+///
+/// * a node member references a node in the `nodes` vector,
+/// * a way member references a way in the `ways` vector,
+/// * a relation member references a relation in the `relations` vector.
+    #[inline]
+    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
+        &self.relation_members
+    }
+
+    /// List of tags.
+///
+/// A tag references its key and value in the `stringtable` raw data.
+    #[inline]
+    pub fn tags(&self) -> &[super::osm::Tag] {
+        self.tags
+    }
+
+    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
+/// and tags.
+    #[inline]
+    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
+        self.tags_index
+    }
+
+    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    #[inline]
+    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
+        self.nodes_index
+    }
+
+    /// List of strings separated by `\0`.
+    #[inline]
+    pub fn stringtable(&self) -> flatdata::RawData {
+        self.stringtable
+    }
+
+    /// Metadata of nodes.
+///
+/// The node at index `i` in the `nodes` vector has its metadata at index `i`
+/// in this vector. Only present if the archive was compiled with metadata.
+    #[inline]
+    pub fn node_info(&self) -> Option<&[super::osm::Info]> {
+        self.node_info
+    }
+
+    /// Metadata of ways.
+///
+/// The way at index `i` in the `ways` vector has its metadata at index `i`
+/// in this vector. Only present if the archive was compiled with metadata.
+    #[inline]
+    pub fn way_info(&self) -> Option<&[super::osm::Info]> {
+        self.way_info
+    }
+
+    /// Metadata of relations.
+///
+/// The relation at index `i` in the `relations` vector has its metadata at index `i`
+/// in this vector. Only present if the archive was compiled with metadata.
+    #[inline]
+    pub fn relation_info(&self) -> Option<&[super::osm::Info]> {
+        self.relation_info
+    }
+
+    /// Validity of the versions of nodes.
+///
+/// The node at index `i` in the `nodes` vector has its validity at index `i`
+/// in this vector. Only present in history archives.
+    #[inline]
+    pub fn node_validity(&self) -> Option<&[super::osm::Validity]> {
+        self.node_validity
+    }
+
+    /// Validity of the versions of ways.
+///
+/// The way at index `i` in the `ways` vector has its validity at index `i`
+/// in this vector. Only present in history archives.
+    #[inline]
+    pub fn way_validity(&self) -> Option<&[super::osm::Validity]> {
+        self.way_validity
+    }
+
+    /// Validity of the versions of relations.
+///
+/// The relation at index `i` in the `relations` vector has its validity at index `i`
+/// in this vector. Only present in history archives.
+    #[inline]
+    pub fn relation_validity(&self) -> Option<&[super::osm::Validity]> {
+        self.relation_validity
+    }
+
+    /// Index of nodes by id.
+///
+/// Maps the id of each node to its index in the `nodes` vector, sorted by id.
+/// In history archives, the id is mapped to the first version. Only present
+/// if the archive was compiled with an id index.
+    #[inline]
+    pub fn node_id_index(&self) -> Option<&[super::osm::IdIndex]> {
+        self.node_id_index
+    }
+
+    /// Index of ways by id.
+///
+/// Maps the id of each way to its index in the `ways` vector, sorted by id.
+/// In history archives, the id is mapped to the first version. Only present
+/// if the archive was compiled with an id index.
+    #[inline]
+    pub fn way_id_index(&self) -> Option<&[super::osm::IdIndex]> {
+        self.way_id_index
+    }
+
+    /// Index of relations by id.
+///
+/// Maps the id of each relation to its index in the `relations` vector, sorted by id.
+/// In history archives, the id is mapped to the first version. Only present
+/// if the archive was compiled with an id index.
+    #[inline]
+    pub fn relation_id_index(&self) -> Option<&[super::osm::IdIndex]> {
+        self.relation_id_index
+    }
+
+    /// Ranges of ways using each node.
+///
+/// The node at index `i` in the `nodes` vector is used by the ways in the range
+/// of the element at index `i` in this vector. Only present if the archive was
+/// compiled with a node ways index.
+    #[inline]
+    pub fn node_ways(&self) -> Option<&[super::osm::NodeWays]> {
+        self.node_ways
+    }
+
+    /// Indexes of the ways using a node, in the order of the ways.
+///
+/// Referenced by the ranges in `node_ways`.
+    #[inline]
+    pub fn node_ways_index(&self) -> Option<&[super::osm::WayIndex]> {
+        self.node_ways_index
+    }
+
+    /// Ranges of relations having each node as member.
+///
+/// The node at index `i` in the `nodes` vector is a member of the relations in
+/// the range of the element at index `i` in this vector. Only present if the
+/// archive was compiled with parent relations.
+    #[inline]
+    pub fn node_parent_relations(&self) -> Option<&[super::osm::ParentRelations]> {
+        self.node_parent_relations
+    }
+
+    /// Relations having a node as member, together with the role of the node.
+///
+/// Referenced by the ranges in `node_parent_relations`.
+    #[inline]
+    pub fn node_parent_relations_index(&self) -> Option<&[super::osm::ParentRelation]> {
+        self.node_parent_relations_index
+    }
+
+    /// Ranges of relations having each way as member.
+///
+/// The way at index `i` in the `ways` vector is a member of the relations in
+/// the range of the element at index `i` in this vector. Only present if the
+/// archive was compiled with parent relations.
+    #[inline]
+    pub fn way_parent_relations(&self) -> Option<&[super::osm::ParentRelations]> {
+        self.way_parent_relations
+    }
+
+    /// Relations having a way as member, together with the role of the way.
+///
+/// Referenced by the ranges in `way_parent_relations`.
+    #[inline]
+    pub fn way_parent_relations_index(&self) -> Option<&[super::osm::ParentRelation]> {
+        self.way_parent_relations_index
+    }
+
+    /// Ranges of relations having each relation as member.
+///
+/// The relation at index `i` in the `relations` vector is a member of the relations in
+/// the range of the element at index `i` in this vector. Only present if the
+/// archive was compiled with parent relations.
+    #[inline]
+    pub fn relation_parent_relations(&self) -> Option<&[super::osm::ParentRelations]> {
+        self.relation_parent_relations
+    }
+
+    /// Relations having a relation as member, together with the role of the relation.
+///
+/// Referenced by the ranges in `relation_parent_relations`.
+    #[inline]
+    pub fn relation_parent_relations_index(&self) -> Option<&[super::osm::ParentRelation]> {
+        self.relation_parent_relations_index
+    }
+
+    /// Inverted index of tags.
+///
+/// Contains an entry for each distinct tag and each distinct key, sorted by the
+/// key and value strings, with the ranges of the entities carrying it. Only
+/// present if the archive was compiled with a tag index.
+    #[inline]
+    pub fn tag_entities(&self) -> Option<&[super::osm::TagEntities]> {
+        self.tag_entities
+    }
+
+    /// Sorted indexes of the nodes carrying a tag.
+///
+/// Referenced by the ranges in `tag_entities`.
+    #[inline]
+    pub fn tag_entities_nodes(&self) -> Option<&[super::osm::NodeIndex]> {
+        self.tag_entities_nodes
+    }
+
+    /// Sorted indexes of the ways carrying a tag.
+///
+/// Referenced by the ranges in `tag_entities`.
+    #[inline]
+    pub fn tag_entities_ways(&self) -> Option<&[super::osm::WayIndex]> {
+        self.tag_entities_ways
+    }
+
+    /// Sorted indexes of the relations carrying a tag.
+///
+/// Referenced by the ranges in `tag_entities`.
+    #[inline]
+    pub fn tag_entities_relations(&self) -> Option<&[super::osm::RelationIndex]> {
+        self.tag_entities_relations
+    }
+
+    /// Packed Hilbert R-tree over the nodes.
+///
+/// Contains the bounding boxes of the levels of the tree, starting with the
+/// leaves and ending with the root. Each level is sorted by the Hilbert value of
+/// the centers of the boxes, and the children of the element at index `i` of
+/// a level are the elements at indexes `i * RTREE_NODE_SIZE` to
+/// `(i + 1) * RTREE_NODE_SIZE` of the previous level. Only present if the archive
+/// was compiled with a spatial index.
+    #[inline]
+    pub fn node_rtree(&self) -> Option<&[super::osm::BBox]> {
+        self.node_rtree
+    }
+
+    /// Indexes of the nodes of the leaves of `node_rtree`.
+    #[inline]
+    pub fn node_rtree_index(&self) -> Option<&[super::osm::NodeIndex]> {
+        self.node_rtree_index
+    }
+
+    /// Packed Hilbert R-tree over the ways.
+///
+/// Contains the bounding boxes of the levels of the tree, starting with the
+/// leaves and ending with the root. Each level is sorted by the Hilbert value of
+/// the centers of the boxes, and the children of the element at index `i` of
+/// a level are the elements at indexes `i * RTREE_NODE_SIZE` to
+/// `(i + 1) * RTREE_NODE_SIZE` of the previous level. Only present if the archive
+/// was compiled with a spatial index.
+    #[inline]
+    pub fn way_rtree(&self) -> Option<&[super::osm::BBox]> {
+        self.way_rtree
+    }
+
+    /// Indexes of the ways of the leaves of `way_rtree`.
+    #[inline]
+    pub fn way_rtree_index(&self) -> Option<&[super::osm::WayIndex]> {
+        self.way_rtree_index
+    }
+
+}
+
+impl ::std::fmt::Debug for Osm {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Osm")
+            .field("header", &self.header())
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .field("relation_members", &self.relation_members())
+            .field("tags", &self.tags())
+            .field("tags_index", &self.tags_index())
+            .field("nodes_index", &self.nodes_index())
+            .field("stringtable", &self.stringtable())
+            .field("node_info", &self.node_info())
+            .field("way_info", &self.way_info())
+            .field("relation_info", &self.relation_info())
+            .field("node_validity", &self.node_validity())
+            .field("way_validity", &self.way_validity())
+            .field("relation_validity", &self.relation_validity())
+            .field("node_id_index", &self.node_id_index())
+            .field("way_id_index", &self.way_id_index())
+            .field("relation_id_index", &self.relation_id_index())
+            .field("node_ways", &self.node_ways())
+            .field("node_ways_index", &self.node_ways_index())
+            .field("node_parent_relations", &self.node_parent_relations())
+            .field("node_parent_relations_index", &self.node_parent_relations_index())
+            .field("way_parent_relations", &self.way_parent_relations())
+            .field("way_parent_relations_index", &self.way_parent_relations_index())
+            .field("relation_parent_relations", &self.relation_parent_relations())
+            .field("relation_parent_relations_index", &self.relation_parent_relations_index())
+            .field("tag_entities", &self.tag_entities())
+            .field("tag_entities_nodes", &self.tag_entities_nodes())
+            .field("tag_entities_ways", &self.tag_entities_ways())
+            .field("tag_entities_relations", &self.tag_entities_relations())
+            .field("node_rtree", &self.node_rtree())
+            .field("node_rtree_index", &self.node_rtree_index())
+            .field("way_rtree", &self.way_rtree())
+            .field("way_rtree_index", &self.way_rtree_index())
+            .finish()
+    }
+}
+
+impl Osm {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], flatdata::ResourceStorageError>| -> Result<&'static [u8], flatdata::ResourceStorageError> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
+
+        let resource = extend(storage.read("header", schema::osm::resources::HEADER));
+        let header = resource.map(|x| super::osm::Header::from_bytes_slice(x))??;
+        let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
+        let nodes = resource.map(|x| <&[super::osm::Node]>::from_bytes(x))??;
+        let size = nodes.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "nodes", size}); }
+        let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
+        let ways = resource.map(|x| <&[super::osm::Way]>::from_bytes(x))??;
+        let size = ways.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "ways", size}); }
+        let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
+        let relations = resource.map(|x| <&[super::osm::Relation]>::from_bytes(x))??;
+        let size = relations.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "relations", size}); }
+        let relation_members = {
+            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
+            let index = extend(storage.read("relation_members_index", &index_schema));
+            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
+            let result = match (index, data) {
+                (Ok(index), Ok(data)) => {
+                    Ok(flatdata::MultiArrayView::new(
+                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
+                        data
+                    ))
+                }
+                (Ok(_), Err(x)) | (Err(x), Ok(_)) => {return Err(x);}
+                (Err(x), Err(_)) => Err(x),
+            };
+            result?
+        };
+        let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
+        let tags = resource.map(|x| <&[super::osm::Tag]>::from_bytes(x))??;
+        let size = tags.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "tags", size}); }
+        let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
+        let tags_index = resource.map(|x| <&[super::osm::TagIndex]>::from_bytes(x))??;
+        let size = tags_index.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "tags_index", size}); }
+        let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
+        let nodes_index = resource.map(|x| <&[super::osm::NodeIndex]>::from_bytes(x))??;
+        let size = nodes_index.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "nodes_index", size}); }
+        let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
+        let stringtable = resource.map(|x| flatdata::RawData::new(x))?;
+        let size = stringtable.len();
+        if size > 1099511627776 { return Err(flatdata::ResourceStorageError::TooBig{resource_name: "stringtable", size}); }
+        let resource = extend(storage.read("node_info", schema::osm::resources::NODE_INFO));
+        let node_info = flatdata::check_optional_resource("node_info", |r: &&[super::osm::Info]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_info", schema::osm::resources::WAY_INFO));
+        let way_info = flatdata::check_optional_resource("way_info", |r: &&[super::osm::Info]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?;
+        let resource = extend(storage.read("relation_info", schema::osm::resources::RELATION_INFO));
+        let relation_info = flatdata::check_optional_resource("relation_info", |r: &&[super::osm::Info]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_validity", schema::osm::resources::NODE_VALIDITY));
+        let node_validity = flatdata::check_optional_resource("node_validity", |r: &&[super::osm::Validity]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Validity]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_validity", schema::osm::resources::WAY_VALIDITY));
+        let way_validity = flatdata::check_optional_resource("way_validity", |r: &&[super::osm::Validity]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Validity]>::from_bytes(x)))?;
+        let resource = extend(storage.read("relation_validity", schema::osm::resources::RELATION_VALIDITY));
+        let relation_validity = flatdata::check_optional_resource("relation_validity", |r: &&[super::osm::Validity]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::Validity]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_id_index", schema::osm::resources::NODE_ID_INDEX));
+        let node_id_index = flatdata::check_optional_resource("node_id_index", |r: &&[super::osm::IdIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::IdIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_id_index", schema::osm::resources::WAY_ID_INDEX));
+        let way_id_index = flatdata::check_optional_resource("way_id_index", |r: &&[super::osm::IdIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::IdIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("relation_id_index", schema::osm::resources::RELATION_ID_INDEX));
+        let relation_id_index = flatdata::check_optional_resource("relation_id_index", |r: &&[super::osm::IdIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::IdIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_ways", schema::osm::resources::NODE_WAYS));
+        let node_ways = flatdata::check_optional_resource("node_ways", |r: &&[super::osm::NodeWays]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::NodeWays]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_ways_index", schema::osm::resources::NODE_WAYS_INDEX));
+        let node_ways_index = flatdata::check_optional_resource("node_ways_index", |r: &&[super::osm::WayIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::WayIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_parent_relations", schema::osm::resources::NODE_PARENT_RELATIONS));
+        let node_parent_relations = flatdata::check_optional_resource("node_parent_relations", |r: &&[super::osm::ParentRelations]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelations]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_parent_relations_index", schema::osm::resources::NODE_PARENT_RELATIONS_INDEX));
+        let node_parent_relations_index = flatdata::check_optional_resource("node_parent_relations_index", |r: &&[super::osm::ParentRelation]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_parent_relations", schema::osm::resources::WAY_PARENT_RELATIONS));
+        let way_parent_relations = flatdata::check_optional_resource("way_parent_relations", |r: &&[super::osm::ParentRelations]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelations]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_parent_relations_index", schema::osm::resources::WAY_PARENT_RELATIONS_INDEX));
+        let way_parent_relations_index = flatdata::check_optional_resource("way_parent_relations_index", |r: &&[super::osm::ParentRelation]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?;
+        let resource = extend(storage.read("relation_parent_relations", schema::osm::resources::RELATION_PARENT_RELATIONS));
+        let relation_parent_relations = flatdata::check_optional_resource("relation_parent_relations", |r: &&[super::osm::ParentRelations]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelations]>::from_bytes(x)))?;
+        let resource = extend(storage.read("relation_parent_relations_index", schema::osm::resources::RELATION_PARENT_RELATIONS_INDEX));
+        let relation_parent_relations_index = flatdata::check_optional_resource("relation_parent_relations_index", |r: &&[super::osm::ParentRelation]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?;
+        let resource = extend(storage.read("tag_entities", schema::osm::resources::TAG_ENTITIES));
+        let tag_entities = flatdata::check_optional_resource("tag_entities", |r: &&[super::osm::TagEntities]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::TagEntities]>::from_bytes(x)))?;
+        let resource = extend(storage.read("tag_entities_nodes", schema::osm::resources::TAG_ENTITIES_NODES));
+        let tag_entities_nodes = flatdata::check_optional_resource("tag_entities_nodes", |r: &&[super::osm::NodeIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("tag_entities_ways", schema::osm::resources::TAG_ENTITIES_WAYS));
+        let tag_entities_ways = flatdata::check_optional_resource("tag_entities_ways", |r: &&[super::osm::WayIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::WayIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("tag_entities_relations", schema::osm::resources::TAG_ENTITIES_RELATIONS));
+        let tag_entities_relations = flatdata::check_optional_resource("tag_entities_relations", |r: &&[super::osm::RelationIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::RelationIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_rtree", schema::osm::resources::NODE_RTREE));
+        let node_rtree = flatdata::check_optional_resource("node_rtree", |r: &&[super::osm::BBox]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::BBox]>::from_bytes(x)))?;
+        let resource = extend(storage.read("node_rtree_index", schema::osm::resources::NODE_RTREE_INDEX));
+        let node_rtree_index = flatdata::check_optional_resource("node_rtree_index", |r: &&[super::osm::NodeIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_rtree", schema::osm::resources::WAY_RTREE));
+        let way_rtree = flatdata::check_optional_resource("way_rtree", |r: &&[super::osm::BBox]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::BBox]>::from_bytes(x)))?;
+        let resource = extend(storage.read("way_rtree_index", schema::osm::resources::WAY_RTREE_INDEX));
+        let way_rtree_index = flatdata::check_optional_resource("way_rtree_index", |r: &&[super::osm::WayIndex]| r.len(), Some(1099511627776), resource.and_then(|x| <&[super::osm::WayIndex]>::from_bytes(x)))?;
+
+        Ok(Self {
+            _storage: storage,
+            header,
+            nodes,
+            ways,
+            relations,
+            relation_members,
+            tags,
+            tags_index,
+            nodes_index,
+            stringtable,
+            node_info,
+            way_info,
+            relation_info,
+            node_validity,
+            way_validity,
+            relation_validity,
+            node_id_index,
+            way_id_index,
+            relation_id_index,
+            node_ways,
+            node_ways_index,
+            node_parent_relations,
+            node_parent_relations_index,
+            way_parent_relations,
+            way_parent_relations_index,
+            relation_parent_relations,
+            relation_parent_relations_index,
+            tag_entities,
+            tag_entities_nodes,
+            tag_entities_ways,
+            tag_entities_relations,
+            node_rtree,
+            node_rtree_index,
+            way_rtree,
+            way_rtree_index,
+        })
+    }
+}
+
+/// Builder for creating [`Osm`] archives.
+///
+///[`Osm`]: struct.Osm.html
+#[derive(Clone, Debug)]
+pub struct OsmBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl OsmBuilder {
+    #[inline]
+    /// Stores [`header`] in the archive.
+    ///
+    /// [`header`]: struct.Osm.html#method.header
+    /// Stores [`header`] in the archive.
+    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
+        let data = resource.as_bytes();
+        self.storage.write("header", schema::osm::resources::HEADER, data)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    }
+
+    /// Opens [`relation_members`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the multivector until the [`MultiVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_members`]: struct.Osm.html#method.relation_members
+    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
+    #[inline]
+    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
+        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+    }
+
+    #[inline]
+    /// Stores [`tags`] in the archive.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
+        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
+    }
+
+    #[inline]
+    /// Stores [`tags_index`] in the archive.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`tags_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`nodes_index`] in the archive.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`nodes_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    }
+
+    /// Stores [`stringtable`] in the archive.
+    ///
+    /// [`stringtable`]: struct.Osm.html#method.stringtable
+    #[inline]
+    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
+        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    }
+
+    #[inline]
+    /// Stores [`node_info`] in the archive.
+    ///
+    /// [`node_info`]: struct.Osm.html#method.node_info
+    pub fn set_node_info(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_info", schema::osm::resources::NODE_INFO, vector.as_bytes())
+    }
+
+    /// Opens [`node_info`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_info`]: struct.Osm.html#method.node_info
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_info(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "node_info", schema::osm::resources::NODE_INFO)
+    }
+
+    #[inline]
+    /// Stores [`way_info`] in the archive.
+    ///
+    /// [`way_info`]: struct.Osm.html#method.way_info
+    pub fn set_way_info(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_info", schema::osm::resources::WAY_INFO, vector.as_bytes())
+    }
+
+    /// Opens [`way_info`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_info`]: struct.Osm.html#method.way_info
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_info(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "way_info", schema::osm::resources::WAY_INFO)
+    }
+
+    #[inline]
+    /// Stores [`relation_info`] in the archive.
+    ///
+    /// [`relation_info`]: struct.Osm.html#method.relation_info
+    pub fn set_relation_info(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_info", schema::osm::resources::RELATION_INFO, vector.as_bytes())
+    }
+
+    /// Opens [`relation_info`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_info`]: struct.Osm.html#method.relation_info
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_info(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "relation_info", schema::osm::resources::RELATION_INFO)
+    }
+
+    #[inline]
+    /// Stores [`node_validity`] in the archive.
+    ///
+    /// [`node_validity`]: struct.Osm.html#method.node_validity
+    pub fn set_node_validity(&self, vector: &[super::osm::Validity]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_validity", schema::osm::resources::NODE_VALIDITY, vector.as_bytes())
+    }
+
+    /// Opens [`node_validity`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_validity`]: struct.Osm.html#method.node_validity
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_validity(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Validity>> {
+        flatdata::create_external_vector(&*self.storage, "node_validity", schema::osm::resources::NODE_VALIDITY)
+    }
+
+    #[inline]
+    /// Stores [`way_validity`] in the archive.
+    ///
+    /// [`way_validity`]: struct.Osm.html#method.way_validity
+    pub fn set_way_validity(&self, vector: &[super::osm::Validity]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_validity", schema::osm::resources::WAY_VALIDITY, vector.as_bytes())
+    }
+
+    /// Opens [`way_validity`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_validity`]: struct.Osm.html#method.way_validity
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_validity(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Validity>> {
+        flatdata::create_external_vector(&*self.storage, "way_validity", schema::osm::resources::WAY_VALIDITY)
+    }
+
+    #[inline]
+    /// Stores [`relation_validity`] in the archive.
+    ///
+    /// [`relation_validity`]: struct.Osm.html#method.relation_validity
+    pub fn set_relation_validity(&self, vector: &[super::osm::Validity]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_validity", schema::osm::resources::RELATION_VALIDITY, vector.as_bytes())
+    }
+
+    /// Opens [`relation_validity`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_validity`]: struct.Osm.html#method.relation_validity
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_validity(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Validity>> {
+        flatdata::create_external_vector(&*self.storage, "relation_validity", schema::osm::resources::RELATION_VALIDITY)
+    }
+
+    #[inline]
+    /// Stores [`node_id_index`] in the archive.
+    ///
+    /// [`node_id_index`]: struct.Osm.html#method.node_id_index
+    pub fn set_node_id_index(&self, vector: &[super::osm::IdIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_id_index", schema::osm::resources::NODE_ID_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`node_id_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_id_index`]: struct.Osm.html#method.node_id_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_id_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdIndex>> {
+        flatdata::create_external_vector(&*self.storage, "node_id_index", schema::osm::resources::NODE_ID_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`way_id_index`] in the archive.
+    ///
+    /// [`way_id_index`]: struct.Osm.html#method.way_id_index
+    pub fn set_way_id_index(&self, vector: &[super::osm::IdIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_id_index", schema::osm::resources::WAY_ID_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`way_id_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_id_index`]: struct.Osm.html#method.way_id_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_id_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdIndex>> {
+        flatdata::create_external_vector(&*self.storage, "way_id_index", schema::osm::resources::WAY_ID_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`relation_id_index`] in the archive.
+    ///
+    /// [`relation_id_index`]: struct.Osm.html#method.relation_id_index
+    pub fn set_relation_id_index(&self, vector: &[super::osm::IdIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_id_index", schema::osm::resources::RELATION_ID_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`relation_id_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_id_index`]: struct.Osm.html#method.relation_id_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_id_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdIndex>> {
+        flatdata::create_external_vector(&*self.storage, "relation_id_index", schema::osm::resources::RELATION_ID_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`node_ways`] in the archive.
+    ///
+    /// [`node_ways`]: struct.Osm.html#method.node_ways
+    pub fn set_node_ways(&self, vector: &[super::osm::NodeWays]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_ways", schema::osm::resources::NODE_WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`node_ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_ways`]: struct.Osm.html#method.node_ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeWays>> {
+        flatdata::create_external_vector(&*self.storage, "node_ways", schema::osm::resources::NODE_WAYS)
+    }
+
+    #[inline]
+    /// Stores [`node_ways_index`] in the archive.
+    ///
+    /// [`node_ways_index`]: struct.Osm.html#method.node_ways_index
+    pub fn set_node_ways_index(&self, vector: &[super::osm::WayIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_ways_index", schema::osm::resources::NODE_WAYS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`node_ways_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_ways_index`]: struct.Osm.html#method.node_ways_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_ways_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::WayIndex>> {
+        flatdata::create_external_vector(&*self.storage, "node_ways_index", schema::osm::resources::NODE_WAYS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`node_parent_relations`] in the archive.
+    ///
+    /// [`node_parent_relations`]: struct.Osm.html#method.node_parent_relations
+    pub fn set_node_parent_relations(&self, vector: &[super::osm::ParentRelations]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_parent_relations", schema::osm::resources::NODE_PARENT_RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`node_parent_relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_parent_relations`]: struct.Osm.html#method.node_parent_relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_parent_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelations>> {
+        flatdata::create_external_vector(&*self.storage, "node_parent_relations", schema::osm::resources::NODE_PARENT_RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`node_parent_relations_index`] in the archive.
+    ///
+    /// [`node_parent_relations_index`]: struct.Osm.html#method.node_parent_relations_index
+    pub fn set_node_parent_relations_index(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_parent_relations_index", schema::osm::resources::NODE_PARENT_RELATIONS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`node_parent_relations_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_parent_relations_index`]: struct.Osm.html#method.node_parent_relations_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_parent_relations_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "node_parent_relations_index", schema::osm::resources::NODE_PARENT_RELATIONS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`way_parent_relations`] in the archive.
+    ///
+    /// [`way_parent_relations`]: struct.Osm.html#method.way_parent_relations
+    pub fn set_way_parent_relations(&self, vector: &[super::osm::ParentRelations]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_parent_relations", schema::osm::resources::WAY_PARENT_RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`way_parent_relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_parent_relations`]: struct.Osm.html#method.way_parent_relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_parent_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelations>> {
+        flatdata::create_external_vector(&*self.storage, "way_parent_relations", schema::osm::resources::WAY_PARENT_RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`way_parent_relations_index`] in the archive.
+    ///
+    /// [`way_parent_relations_index`]: struct.Osm.html#method.way_parent_relations_index
+    pub fn set_way_parent_relations_index(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_parent_relations_index", schema::osm::resources::WAY_PARENT_RELATIONS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`way_parent_relations_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_parent_relations_index`]: struct.Osm.html#method.way_parent_relations_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_parent_relations_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "way_parent_relations_index", schema::osm::resources::WAY_PARENT_RELATIONS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`relation_parent_relations`] in the archive.
+    ///
+    /// [`relation_parent_relations`]: struct.Osm.html#method.relation_parent_relations
+    pub fn set_relation_parent_relations(&self, vector: &[super::osm::ParentRelations]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_parent_relations", schema::osm::resources::RELATION_PARENT_RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_parent_relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_parent_relations`]: struct.Osm.html#method.relation_parent_relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_parent_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelations>> {
+        flatdata::create_external_vector(&*self.storage, "relation_parent_relations", schema::osm::resources::RELATION_PARENT_RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`relation_parent_relations_index`] in the archive.
+    ///
+    /// [`relation_parent_relations_index`]: struct.Osm.html#method.relation_parent_relations_index
+    pub fn set_relation_parent_relations_index(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_parent_relations_index", schema::osm::resources::RELATION_PARENT_RELATIONS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`relation_parent_relations_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_parent_relations_index`]: struct.Osm.html#method.relation_parent_relations_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_parent_relations_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "relation_parent_relations_index", schema::osm::resources::RELATION_PARENT_RELATIONS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`tag_entities`] in the archive.
+    ///
+    /// [`tag_entities`]: struct.Osm.html#method.tag_entities
+    pub fn set_tag_entities(&self, vector: &[super::osm::TagEntities]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_entities", schema::osm::resources::TAG_ENTITIES, vector.as_bytes())
+    }
+
+    /// Opens [`tag_entities`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_entities`]: struct.Osm.html#method.tag_entities
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_entities(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagEntities>> {
+        flatdata::create_external_vector(&*self.storage, "tag_entities", schema::osm::resources::TAG_ENTITIES)
+    }
+
+    #[inline]
+    /// Stores [`tag_entities_nodes`] in the archive.
+    ///
+    /// [`tag_entities_nodes`]: struct.Osm.html#method.tag_entities_nodes
+    pub fn set_tag_entities_nodes(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_entities_nodes", schema::osm::resources::TAG_ENTITIES_NODES, vector.as_bytes())
+    }
+
+    /// Opens [`tag_entities_nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_entities_nodes`]: struct.Osm.html#method.tag_entities_nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_entities_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tag_entities_nodes", schema::osm::resources::TAG_ENTITIES_NODES)
+    }
+
+    #[inline]
+    /// Stores [`tag_entities_ways`] in the archive.
+    ///
+    /// [`tag_entities_ways`]: struct.Osm.html#method.tag_entities_ways
+    pub fn set_tag_entities_ways(&self, vector: &[super::osm::WayIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_entities_ways", schema::osm::resources::TAG_ENTITIES_WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`tag_entities_ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_entities_ways`]: struct.Osm.html#method.tag_entities_ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_entities_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::WayIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tag_entities_ways", schema::osm::resources::TAG_ENTITIES_WAYS)
+    }
+
+    #[inline]
+    /// Stores [`tag_entities_relations`] in the archive.
+    ///
+    /// [`tag_entities_relations`]: struct.Osm.html#method.tag_entities_relations
+    pub fn set_tag_entities_relations(&self, vector: &[super::osm::RelationIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_entities_relations", schema::osm::resources::TAG_ENTITIES_RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`tag_entities_relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_entities_relations`]: struct.Osm.html#method.tag_entities_relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_entities_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::RelationIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tag_entities_relations", schema::osm::resources::TAG_ENTITIES_RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`node_rtree`] in the archive.
+    ///
+    /// [`node_rtree`]: struct.Osm.html#method.node_rtree
+    pub fn set_node_rtree(&self, vector: &[super::osm::BBox]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_rtree", schema::osm::resources::NODE_RTREE, vector.as_bytes())
+    }
+
+    /// Opens [`node_rtree`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
     /// is called. To flush the data fully into the archive, this method must be called
     /// in the end.
     ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    /// [`node_rtree`]: struct.Osm.html#method.node_rtree
     /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
-        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    pub fn start_node_rtree(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BBox>> {
+        flatdata::create_external_vector(&*self.storage, "node_rtree", schema::osm::resources::NODE_RTREE)
     }
 
-    /// Stores [`stringtable`] in the archive.
+    #[inline]
+    /// Stores [`node_rtree_index`] in the archive.
     ///
-    /// [`stringtable`]: struct.Osm.html#method.stringtable
+    /// [`node_rtree_index`]: struct.Osm.html#method.node_rtree_index
+    pub fn set_node_rtree_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_rtree_index", schema::osm::resources::NODE_RTREE_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`node_rtree_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_rtree_index`]: struct.Osm.html#method.node_rtree_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
-        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    pub fn start_node_rtree_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
+        flatdata::create_external_vector(&*self.storage, "node_rtree_index", schema::osm::resources::NODE_RTREE_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`way_rtree`] in the archive.
+    ///
+    /// [`way_rtree`]: struct.Osm.html#method.way_rtree
+    pub fn set_way_rtree(&self, vector: &[super::osm::BBox]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_rtree", schema::osm::resources::WAY_RTREE, vector.as_bytes())
+    }
+
+    /// Opens [`way_rtree`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_rtree`]: struct.Osm.html#method.way_rtree
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_rtree(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BBox>> {
+        flatdata::create_external_vector(&*self.storage, "way_rtree", schema::osm::resources::WAY_RTREE)
+    }
+
+    #[inline]
+    /// Stores [`way_rtree_index`] in the archive.
+    ///
+    /// [`way_rtree_index`]: struct.Osm.html#method.way_rtree_index
+    pub fn set_way_rtree_index(&self, vector: &[super::osm::WayIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_rtree_index", schema::osm::resources::WAY_RTREE_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`way_rtree_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_rtree_index`]: struct.Osm.html#method.way_rtree_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_rtree_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::WayIndex>> {
+        flatdata::create_external_vector(&*self.storage, "way_rtree_index", schema::osm::resources::WAY_RTREE_INDEX)
+    }
+
+}
+
+impl OsmBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
+        Ok(Self { storage })
     }
+}
+
+
+#[doc(hidden)]
+pub mod schema {
+pub mod osm {
+
+pub const OSM: &str = r#"namespace osm {
+struct Header
+{
+    bbox_left : i64 : 40;
+    bbox_right : i64 : 40;
+    bbox_top : i64 : 40;
+    bbox_bottom : i64 : 40;
+    required_feature_first_idx : u64 : 40;
+    required_features_size : u32 : 4;
+    optional_feature_first_idx : u64 : 40;
+    optional_features_size : u32 : 4;
+    writingprogram_idx : u64 : 40;
+    source_idx : u64 : 40;
+    osmosis_replication_timestamp : i64 : 64;
+    osmosis_replication_sequence_number : i64 : 64;
+    osmosis_replication_base_url_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Node
+{
+    id : i64 : 40;
+    lat : i64 : 40;
+    lon : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Way
+{
+    id : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+    @range( refs )
+    ref_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Relation
+{
+    id : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct NodeMember
+{
+    @optional( .osm.INVALID_IDX )
+    node_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayMember
+{
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationMember
+{
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Tag
+{
+    key_idx : u64 : 40;
+    value_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct NodeIndex
+{
+    @optional( .osm.INVALID_IDX )
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 40;
+    changeset : i64 : 40;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+const i64 INVALID_TIMESTAMP = 549755813887;
+}
+
+namespace osm {
+struct Validity
+{
+    valid_from : i64 : 40;
+    @optional( .osm.INVALID_TIMESTAMP )
+    valid_to : i64 : 40;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+struct IdIndex
+{
+    id : i64 : 64;
+    idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct NodeWays
+{
+    @range( ways )
+    way_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct ParentRelations
+{
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagEntities
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct BBox
+{
+    min_lon : i64 : 40;
+    min_lat : i64 : 40;
+    max_lon : i64 : 40;
+    max_lat : i64 : 40;
+}
+}
+
+namespace osm {
+const u64 COORD_SCALE = 1000000000;
+}
+
+namespace osm {
+const u64 RTREE_NODE_SIZE = 16;
+}
 
+namespace osm {
+@bound_implicitly( Relations : .osm.Osm.relations, .osm.Osm.relation_members )
+archive Osm
+{
+    @explicit_reference( .osm.Header.required_feature_first_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.optional_feature_first_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.osmosis_replication_base_url_idx, .osm.Osm.stringtable )
+    header : .osm.Header;
+    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
+    nodes : vector< .osm.Node >;
+    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
+    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
+    ways : vector< .osm.Way >;
+    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
+    relations : vector< .osm.Relation >;
+    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
+    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
+    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
+    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
+    tags : vector< .osm.Tag >;
+    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
+    tags_index : vector< .osm.TagIndex >;
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    nodes_index : vector< .osm.NodeIndex >;
+    stringtable : raw_data;
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    node_info : vector< .osm.Info >;
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    way_info : vector< .osm.Info >;
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    relation_info : vector< .osm.Info >;
+    @optional
+    node_validity : vector< .osm.Validity >;
+    @optional
+    way_validity : vector< .osm.Validity >;
+    @optional
+    relation_validity : vector< .osm.Validity >;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.nodes )
+    node_id_index : vector< .osm.IdIndex >;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.ways )
+    way_id_index : vector< .osm.IdIndex >;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.relations )
+    relation_id_index : vector< .osm.IdIndex >;
+    @optional
+    @explicit_reference( .osm.NodeWays.way_first_idx, .osm.Osm.node_ways_index )
+    node_ways : vector< .osm.NodeWays >;
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    node_ways_index : vector< .osm.WayIndex >;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.node_parent_relations_index )
+    node_parent_relations : vector< .osm.ParentRelations >;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    node_parent_relations_index : vector< .osm.ParentRelation >;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.way_parent_relations_index )
+    way_parent_relations : vector< .osm.ParentRelations >;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    way_parent_relations_index : vector< .osm.ParentRelation >;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.relation_parent_relations_index )
+    relation_parent_relations : vector< .osm.ParentRelations >;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    relation_parent_relations_index : vector< .osm.ParentRelation >;
+    @optional
+    @explicit_reference( .osm.TagEntities.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.TagEntities.value_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.TagEntities.node_first_idx, .osm.Osm.tag_entities_nodes )
+    @explicit_reference( .osm.TagEntities.way_first_idx, .osm.Osm.tag_entities_ways )
+    @explicit_reference( .osm.TagEntities.relation_first_idx, .osm.Osm.tag_entities_relations )
+    tag_entities : vector< .osm.TagEntities >;
+    @optional
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    tag_entities_nodes : vector< .osm.NodeIndex >;
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    tag_entities_ways : vector< .osm.WayIndex >;
+    @optional
+    @explicit_reference( .osm.RelationIndex.value, .osm.Osm.relations )
+    tag_entities_relations : vector< .osm.RelationIndex >;
+    @optional
+    node_rtree : vector< .osm.BBox >;
+    @optional
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    node_rtree_index : vector< .osm.NodeIndex >;
+    @optional
+    way_rtree : vector< .osm.BBox >;
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    way_rtree_index : vector< .osm.WayIndex >;
 }
-
-impl OsmBuilder {
-    pub fn new(
-        storage: flatdata::StorageHandle,
-    ) -> Result<Self, flatdata::ResourceStorageError> {
-        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
-        Ok(Self { storage })
-    }
 }
 
+"#;
 
-#[doc(hidden)]
-pub mod schema {
-pub mod osm {
-
-pub const OSM: &str = r#"namespace osm {
+pub mod resources {
+pub const HEADER: &str = r#"namespace osm {
 struct Header
 {
     bbox_left : i64 : 40;
@@ -2000,288 +4569,606 @@ struct Header
 }
 
 namespace osm {
-struct Node
+archive Osm
+{
+    @explicit_reference( .osm.Header.required_feature_first_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.optional_feature_first_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.osmosis_replication_base_url_idx, .osm.Osm.stringtable )
+    header : .osm.Header;
+}
+}
+
+"#;
+pub const NODES: &str = r#"namespace osm {
+struct Node
+{
+    id : i64 : 40;
+    lat : i64 : 40;
+    lon : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
+    nodes : vector< .osm.Node >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct Way
+{
+    id : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+    @range( refs )
+    ref_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
+    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
+    ways : vector< .osm.Way >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct Relation
+{
+    id : i64 : 40;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
+    relations : vector< .osm.Relation >;
+}
+}
+
+"#;
+pub const RELATION_MEMBERS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct NodeMember
+{
+    @optional( .osm.INVALID_IDX )
+    node_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayMember
+{
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationMember
+{
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
+    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
+    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
+    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+}
+}
+
+"#;
+pub const TAGS: &str = r#"namespace osm {
+struct Tag
+{
+    key_idx : u64 : 40;
+    value_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
+    tags : vector< .osm.Tag >;
+}
+}
+
+"#;
+pub const TAGS_INDEX: &str = r#"namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+pub const NODES_INDEX: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct NodeIndex
+{
+    @optional( .osm.INVALID_IDX )
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    nodes_index : vector< .osm.NodeIndex >;
+}
+}
+
+"#;
+pub const STRINGTABLE: &str = r#"namespace osm {
+archive Osm
+{
+    stringtable : raw_data;
+}
+}
+
+"#;
+pub const NODE_INFO: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 40;
+    changeset : i64 : 40;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    node_info : vector< .osm.Info >;
+}
+}
+
+"#;
+pub const WAY_INFO: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 40;
+    changeset : i64 : 40;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    way_info : vector< .osm.Info >;
+}
+}
+
+"#;
+pub const RELATION_INFO: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 40;
+    changeset : i64 : 40;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.Info.user_idx, .osm.Osm.stringtable )
+    relation_info : vector< .osm.Info >;
+}
+}
+
+"#;
+pub const NODE_VALIDITY: &str = r#"namespace osm {
+const i64 INVALID_TIMESTAMP = 549755813887;
+}
+
+namespace osm {
+struct Validity
 {
-    id : i64 : 40;
-    lat : i64 : 40;
-    lon : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    valid_from : i64 : 40;
+    @optional( .osm.INVALID_TIMESTAMP )
+    valid_to : i64 : 40;
+    visible : bool : 1;
 }
 }
 
 namespace osm {
-struct Way
+archive Osm
 {
-    id : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
-    @range( refs )
-    ref_first_idx : u64 : 40;
+    @optional
+    node_validity : vector< .osm.Validity >;
+}
 }
+
+"#;
+pub const WAY_VALIDITY: &str = r#"namespace osm {
+const i64 INVALID_TIMESTAMP = 549755813887;
 }
 
 namespace osm {
-struct Relation
+struct Validity
 {
-    id : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    valid_from : i64 : 40;
+    @optional( .osm.INVALID_TIMESTAMP )
+    valid_to : i64 : 40;
+    visible : bool : 1;
 }
 }
 
 namespace osm {
-const u64 INVALID_IDX = 1099511627775;
+archive Osm
+{
+    @optional
+    way_validity : vector< .osm.Validity >;
+}
+}
+
+"#;
+pub const RELATION_VALIDITY: &str = r#"namespace osm {
+const i64 INVALID_TIMESTAMP = 549755813887;
 }
 
 namespace osm {
-struct NodeMember
+struct Validity
 {
-    @optional( .osm.INVALID_IDX )
-    node_idx : u64 : 40;
-    role_idx : u64 : 40;
+    valid_from : i64 : 40;
+    @optional( .osm.INVALID_TIMESTAMP )
+    valid_to : i64 : 40;
+    visible : bool : 1;
 }
 }
 
 namespace osm {
-struct WayMember
+archive Osm
 {
-    @optional( .osm.INVALID_IDX )
-    way_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @optional
+    relation_validity : vector< .osm.Validity >;
+}
+}
+
+"#;
+pub const NODE_ID_INDEX: &str = r#"namespace osm {
+struct IdIndex
+{
+    id : i64 : 64;
+    idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct RelationMember
+archive Osm
 {
-    @optional( .osm.INVALID_IDX )
-    relation_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.nodes )
+    node_id_index : vector< .osm.IdIndex >;
+}
+}
+
+"#;
+pub const WAY_ID_INDEX: &str = r#"namespace osm {
+struct IdIndex
+{
+    id : i64 : 64;
+    idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct Tag
+archive Osm
 {
-    key_idx : u64 : 40;
-    value_idx : u64 : 40;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.ways )
+    way_id_index : vector< .osm.IdIndex >;
+}
+}
+
+"#;
+pub const RELATION_ID_INDEX: &str = r#"namespace osm {
+struct IdIndex
+{
+    id : i64 : 64;
+    idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct TagIndex
+archive Osm
 {
-    value : u64 : 40;
+    @optional
+    @explicit_reference( .osm.IdIndex.idx, .osm.Osm.relations )
+    relation_id_index : vector< .osm.IdIndex >;
+}
+}
+
+"#;
+pub const NODE_WAYS: &str = r#"namespace osm {
+struct NodeWays
+{
+    @range( ways )
+    way_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct NodeIndex
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.NodeWays.way_first_idx, .osm.Osm.node_ways_index )
+    node_ways : vector< .osm.NodeWays >;
+}
+}
+
+"#;
+pub const NODE_WAYS_INDEX: &str = r#"namespace osm {
+struct WayIndex
 {
-    @optional( .osm.INVALID_IDX )
     value : u64 : 40;
 }
 }
 
 namespace osm {
-const u64 COORD_SCALE = 1000000000;
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    node_ways_index : vector< .osm.WayIndex >;
+}
+}
+
+"#;
+pub const NODE_PARENT_RELATIONS: &str = r#"namespace osm {
+struct ParentRelations
+{
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
 }
 
 namespace osm {
-@bound_implicitly( Relations : .osm.Osm.relations, .osm.Osm.relation_members )
 archive Osm
 {
-    @explicit_reference( .osm.Header.required_feature_first_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.optional_feature_first_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.osmosis_replication_base_url_idx, .osm.Osm.stringtable )
-    header : .osm.Header;
-    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
-    nodes : vector< .osm.Node >;
-    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
-    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
-    ways : vector< .osm.Way >;
-    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
-    relations : vector< .osm.Relation >;
-    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
-    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
-    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
-    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
-    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
-    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
-    tags : vector< .osm.Tag >;
-    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
-    tags_index : vector< .osm.TagIndex >;
-    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
-    nodes_index : vector< .osm.NodeIndex >;
-    stringtable : raw_data;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.node_parent_relations_index )
+    node_parent_relations : vector< .osm.ParentRelations >;
 }
 }
 
 "#;
+pub const NODE_PARENT_RELATIONS_INDEX: &str = r#"namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
 
-pub mod resources {
-pub const HEADER: &str = r#"namespace osm {
-struct Header
+namespace osm {
+archive Osm
 {
-    bbox_left : i64 : 40;
-    bbox_right : i64 : 40;
-    bbox_top : i64 : 40;
-    bbox_bottom : i64 : 40;
-    required_feature_first_idx : u64 : 40;
-    required_features_size : u32 : 4;
-    optional_feature_first_idx : u64 : 40;
-    optional_features_size : u32 : 4;
-    writingprogram_idx : u64 : 40;
-    source_idx : u64 : 40;
-    osmosis_replication_timestamp : i64 : 64;
-    osmosis_replication_sequence_number : i64 : 64;
-    osmosis_replication_base_url_idx : u64 : 40;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    node_parent_relations_index : vector< .osm.ParentRelation >;
+}
+}
+
+"#;
+pub const WAY_PARENT_RELATIONS: &str = r#"namespace osm {
+struct ParentRelations
+{
+    @range( relations )
+    relation_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Header.required_feature_first_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.optional_feature_first_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.osmosis_replication_base_url_idx, .osm.Osm.stringtable )
-    header : .osm.Header;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.way_parent_relations_index )
+    way_parent_relations : vector< .osm.ParentRelations >;
 }
 }
 
 "#;
-pub const NODES: &str = r#"namespace osm {
-struct Node
+pub const WAY_PARENT_RELATIONS_INDEX: &str = r#"namespace osm {
+struct ParentRelation
 {
-    id : i64 : 40;
-    lat : i64 : 40;
-    lon : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
-    nodes : vector< .osm.Node >;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    way_parent_relations_index : vector< .osm.ParentRelation >;
 }
 }
 
 "#;
-pub const WAYS: &str = r#"namespace osm {
-struct Way
+pub const RELATION_PARENT_RELATIONS: &str = r#"namespace osm {
+struct ParentRelations
 {
-    id : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
-    @range( refs )
-    ref_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
-    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
-    ways : vector< .osm.Way >;
+    @optional
+    @explicit_reference( .osm.ParentRelations.relation_first_idx, .osm.Osm.relation_parent_relations_index )
+    relation_parent_relations : vector< .osm.ParentRelations >;
 }
 }
 
 "#;
-pub const RELATIONS: &str = r#"namespace osm {
-struct Relation
+pub const RELATION_PARENT_RELATIONS_INDEX: &str = r#"namespace osm {
+struct ParentRelation
 {
-    id : i64 : 40;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
-    relations : vector< .osm.Relation >;
+    @optional
+    @explicit_reference( .osm.ParentRelation.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.ParentRelation.role_idx, .osm.Osm.stringtable )
+    relation_parent_relations_index : vector< .osm.ParentRelation >;
 }
 }
 
 "#;
-pub const RELATION_MEMBERS: &str = r#"namespace osm {
+pub const TAG_ENTITIES: &str = r#"namespace osm {
 const u64 INVALID_IDX = 1099511627775;
 }
 
 namespace osm {
-struct NodeMember
+struct TagEntities
 {
+    key_idx : u64 : 40;
     @optional( .osm.INVALID_IDX )
-    node_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value_idx : u64 : 40;
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct WayMember
+archive Osm
 {
-    @optional( .osm.INVALID_IDX )
-    way_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @optional
+    @explicit_reference( .osm.TagEntities.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.TagEntities.value_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.TagEntities.node_first_idx, .osm.Osm.tag_entities_nodes )
+    @explicit_reference( .osm.TagEntities.way_first_idx, .osm.Osm.tag_entities_ways )
+    @explicit_reference( .osm.TagEntities.relation_first_idx, .osm.Osm.tag_entities_relations )
+    tag_entities : vector< .osm.TagEntities >;
+}
 }
+
+"#;
+pub const TAG_ENTITIES_NODES: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
 }
 
 namespace osm {
-struct RelationMember
+struct NodeIndex
 {
     @optional( .osm.INVALID_IDX )
-    relation_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
-    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
-    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
-    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
-    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+    @optional
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    tag_entities_nodes : vector< .osm.NodeIndex >;
 }
 }
 
 "#;
-pub const TAGS: &str = r#"namespace osm {
-struct Tag
+pub const TAG_ENTITIES_WAYS: &str = r#"namespace osm {
+struct WayIndex
 {
-    key_idx : u64 : 40;
-    value_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
-    tags : vector< .osm.Tag >;
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    tag_entities_ways : vector< .osm.WayIndex >;
 }
 }
 
 "#;
-pub const TAGS_INDEX: &str = r#"namespace osm {
-struct TagIndex
+pub const TAG_ENTITIES_RELATIONS: &str = r#"namespace osm {
+struct RelationIndex
 {
     value : u64 : 40;
 }
@@ -2290,13 +5177,33 @@ struct TagIndex
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
-    tags_index : vector< .osm.TagIndex >;
+    @optional
+    @explicit_reference( .osm.RelationIndex.value, .osm.Osm.relations )
+    tag_entities_relations : vector< .osm.RelationIndex >;
 }
 }
 
 "#;
-pub const NODES_INDEX: &str = r#"namespace osm {
+pub const NODE_RTREE: &str = r#"namespace osm {
+struct BBox
+{
+    min_lon : i64 : 40;
+    min_lat : i64 : 40;
+    max_lon : i64 : 40;
+    max_lat : i64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    node_rtree : vector< .osm.BBox >;
+}
+}
+
+"#;
+pub const NODE_RTREE_INDEX: &str = r#"namespace osm {
 const u64 INVALID_IDX = 1099511627775;
 }
 
@@ -2311,16 +5218,45 @@ struct NodeIndex
 namespace osm {
 archive Osm
 {
+    @optional
     @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
-    nodes_index : vector< .osm.NodeIndex >;
+    node_rtree_index : vector< .osm.NodeIndex >;
 }
 }
 
 "#;
-pub const STRINGTABLE: &str = r#"namespace osm {
+pub const WAY_RTREE: &str = r#"namespace osm {
+struct BBox
+{
+    min_lon : i64 : 40;
+    min_lat : i64 : 40;
+    max_lon : i64 : 40;
+    max_lat : i64 : 40;
+}
+}
+
+namespace osm {
 archive Osm
 {
-    stringtable : raw_data;
+    @optional
+    way_rtree : vector< .osm.BBox >;
+}
+}
+
+"#;
+pub const WAY_RTREE_INDEX: &str = r#"namespace osm {
+struct WayIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    @explicit_reference( .osm.WayIndex.value, .osm.Osm.ways )
+    way_rtree_index : vector< .osm.WayIndex >;
 }
 }
 
@@ -0,0 +1,112 @@
+//! Lookup of the entities using an entity.
+//!
+//! Requires an archive compiled with the corresponding reverse index
+//! (`osmflatc --with-node-ways` resp. `--with-parent-relations`).
+
+use crate::{Osm, ParentRelation, ParentRelations};
+
+impl Osm {
+    /// Iterates over the indexes of the ways using the node at index
+    /// `node_idx` in the `nodes` vector.
+    ///
+    /// Each way is returned once, in the order of the `ways` vector. Returns
+    /// `None` if the archive has no node ways index.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+    /// let archive = Osm::open(storage).unwrap();
+    /// for (node_idx, node) in archive.nodes().iter().enumerate() {
+    ///     let num_ways = archive.ways_of_node(node_idx as u64).unwrap().count();
+    ///     if num_ways > 1 {
+    ///         println!("{} is an intersection of {} ways", node.id(), num_ways);
+    ///     }
+    /// }
+    /// ```
+    pub fn ways_of_node(&self, node_idx: u64) -> Option<impl Iterator<Item = u64> + '_> {
+        let range = self.node_ways()?[node_idx as usize].ways();
+        let index = &self.node_ways_index()?[range.start as usize..range.end as usize];
+        Some(index.iter().map(|idx| idx.value()))
+    }
+
+    /// Iterates over the relations having the node at index `node_idx` in the
+    /// `nodes` vector as member.
+    ///
+    /// Yields the index of each relation in the `relations` vector together
+    /// with the role of the node. A relation having the node as member several
+    /// times is yielded for each membership. Returns `None` if the archive has
+    /// no parent relations.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{find_tag, FileResourceStorage, Osm};
+    ///
+    /// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+    /// let archive = Osm::open(storage).unwrap();
+    /// for (relation_idx, role) in archive.parent_relations_of_node(42).unwrap() {
+    ///     let relation = &archive.relations()[relation_idx as usize];
+    ///     if find_tag(&archive, relation.tags(), b"type") == Some(b"route") {
+    ///         println!("{} ({})", relation.id(), String::from_utf8_lossy(role));
+    ///     }
+    /// }
+    /// ```
+    pub fn parent_relations_of_node(
+        &self,
+        node_idx: u64,
+    ) -> Option<impl Iterator<Item = (u64, &[u8])> + '_> {
+        Some(self.parent_relations(
+            self.node_parent_relations()?,
+            self.node_parent_relations_index()?,
+            node_idx,
+        ))
+    }
+
+    /// Iterates over the relations having the way at index `way_idx` in the
+    /// `ways` vector as member.
+    ///
+    /// See [`parent_relations_of_node`](#method.parent_relations_of_node).
+    pub fn parent_relations_of_way(
+        &self,
+        way_idx: u64,
+    ) -> Option<impl Iterator<Item = (u64, &[u8])> + '_> {
+        Some(self.parent_relations(
+            self.way_parent_relations()?,
+            self.way_parent_relations_index()?,
+            way_idx,
+        ))
+    }
+
+    /// Iterates over the relations having the relation at index
+    /// `relation_idx` in the `relations` vector as member.
+    ///
+    /// See [`parent_relations_of_node`](#method.parent_relations_of_node).
+    pub fn parent_relations_of_relation(
+        &self,
+        relation_idx: u64,
+    ) -> Option<impl Iterator<Item = (u64, &[u8])> + '_> {
+        Some(self.parent_relations(
+            self.relation_parent_relations()?,
+            self.relation_parent_relations_index()?,
+            relation_idx,
+        ))
+    }
+
+    fn parent_relations<'a>(
+        &'a self,
+        ranges: &'a [ParentRelations],
+        index: &'a [ParentRelation],
+        idx: u64,
+    ) -> impl Iterator<Item = (u64, &'a [u8])> + 'a {
+        let range = ranges[idx as usize].relations();
+        let strings = self.stringtable();
+        let parents = &index[range.start as usize..range.end as usize];
+        parents.iter().map(move |parent| {
+            let role = strings.substring_raw(parent.role_idx() as usize);
+            (parent.relation_idx(), role)
+        })
+    }
+}
@@ -0,0 +1,124 @@
+//! Spatial queries.
+//!
+//! Requires an archive compiled with a spatial index
+//! (`osmflatc --with-spatial-index`). The queries traverse the R-trees of the
+//! archive in place, so that only the visited parts of the trees are read.
+
+use crate::{BBox, Osm, RTREE_NODE_SIZE};
+
+impl BBox {
+    /// Creates a bounding box from coordinates scaled with `COORD_SCALE`.
+    pub fn from_coords(min_lon: i64, min_lat: i64, max_lon: i64, max_lat: i64) -> Self {
+        let mut bbox = Self::new();
+        bbox.set_min_lon(min_lon);
+        bbox.set_min_lat(min_lat);
+        bbox.set_max_lon(max_lon);
+        bbox.set_max_lat(max_lat);
+        bbox
+    }
+
+    /// Checks if the bounding box intersects `other`, including their borders.
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.min_lon() <= other.max_lon()
+            && other.min_lon() <= self.max_lon()
+            && self.min_lat() <= other.max_lat()
+            && other.min_lat() <= self.max_lat()
+    }
+}
+
+impl Osm {
+    /// Iterates over the indexes of the nodes inside `bbox`.
+    ///
+    /// The nodes are returned in the order of the spatial index. Returns
+    /// `None` if the archive has no spatial index.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{BBox, FileResourceStorage, Osm, COORD_SCALE};
+    ///
+    /// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+    /// let archive = Osm::open(storage).unwrap();
+    /// let scale = |degrees: f64| (degrees * COORD_SCALE as f64) as i64;
+    /// let bbox = BBox::from_coords(scale(13.37), scale(52.50), scale(13.40), scale(52.52));
+    /// for idx in archive.nodes_in_bbox(&bbox).expect("no spatial index") {
+    ///     println!("{:?}", archive.nodes()[idx as usize]);
+    /// }
+    /// ```
+    pub fn nodes_in_bbox(&self, bbox: &BBox) -> Option<impl Iterator<Item = u64> + '_> {
+        let index = self.node_rtree_index()?;
+        let leaves = Search::new(self.node_rtree()?, index.len(), bbox.clone());
+        Some(leaves.filter_map(move |pos| index[pos].value()))
+    }
+
+    /// Iterates over the indexes of the ways whose bounding box intersects
+    /// `bbox`.
+    ///
+    /// The bounding box of a way is spanned by its nodes, so a returned way
+    /// does not necessarily cross `bbox`. Ways are returned in the order of the
+    /// spatial index. Returns `None` if the archive has no spatial index.
+    pub fn ways_in_bbox(&self, bbox: &BBox) -> Option<impl Iterator<Item = u64> + '_> {
+        let index = self.way_rtree_index()?;
+        let leaves = Search::new(self.way_rtree()?, index.len(), bbox.clone());
+        Some(leaves.map(move |pos| index[pos].value()))
+    }
+}
+
+/// Depth-first search of the leaves of a packed R-tree intersecting a bounding
+/// box.
+struct Search<'a> {
+    tree: &'a [BBox],
+    /// Start and size of each level in `tree`, starting with the leaves
+    levels: Vec<(usize, usize)>,
+    bbox: BBox,
+    /// Level and position in the level of the nodes to visit
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'a> Search<'a> {
+    fn new(tree: &'a [BBox], num_leaves: usize, bbox: BBox) -> Self {
+        let node_size = RTREE_NODE_SIZE as usize;
+        let mut levels = Vec::new();
+        let mut size = num_leaves;
+        let mut start = 0;
+        while size > 0 {
+            levels.push((start, size));
+            if size == 1 {
+                break;
+            }
+            start += size;
+            size = (size + node_size - 1) / node_size;
+        }
+        let stack = if levels.is_empty() {
+            Vec::new()
+        } else {
+            vec![(levels.len() - 1, 0)]
+        };
+        Self {
+            tree,
+            levels,
+            bbox,
+            stack,
+        }
+    }
+}
+
+impl<'a> Iterator for Search<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let node_size = RTREE_NODE_SIZE as usize;
+        while let Some((level, pos)) = self.stack.pop() {
+            if !self.tree[self.levels[level].0 + pos].intersects(&self.bbox) {
+                continue;
+            }
+            if level == 0 {
+                return Some(pos);
+            }
+            let children = pos * node_size..((pos + 1) * node_size).min(self.levels[level - 1].1);
+            self.stack
+                .extend(children.rev().map(|child| (level - 1, child)));
+        }
+        None
+    }
+}
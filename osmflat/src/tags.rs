@@ -2,7 +2,7 @@
 //! It is easy to combine these with `std::str::from_utf8` family of functions,
 //! to lift them to operate on `str`.
 
-use crate::Osm;
+use crate::{NodeIndex, Osm, RelationIndex, WayIndex};
 use std::ops::Range;
 
 /// Returns an iterator over tags specified by `range`.
@@ -82,3 +82,95 @@ pub fn has_tag(archive: &Osm, range: Range<u64>, key: &[u8], value: &[u8]) -> bo
     }
     false
 }
+
+/// Indexes of the entities carrying a tag, as returned by
+/// [`entities_with_tag`].
+#[derive(Debug, Clone, Copy)]
+pub struct TaggedEntities<'a> {
+    nodes: &'a [NodeIndex],
+    ways: &'a [WayIndex],
+    relations: &'a [RelationIndex],
+}
+
+impl<'a> TaggedEntities<'a> {
+    /// Iterates over the sorted indexes of nodes in the `nodes` vector.
+    pub fn nodes(&self) -> impl Iterator<Item = u64> + 'a {
+        self.nodes.iter().filter_map(|idx| idx.value())
+    }
+
+    /// Iterates over the sorted indexes of ways in the `ways` vector.
+    pub fn ways(&self) -> impl Iterator<Item = u64> + 'a {
+        self.ways.iter().map(|idx| idx.value())
+    }
+
+    /// Iterates over the sorted indexes of relations in the `relations`
+    /// vector.
+    pub fn relations(&self) -> impl Iterator<Item = u64> + 'a {
+        self.relations.iter().map(|idx| idx.value())
+    }
+
+    /// Returns `true` if no entity carries the tag.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.ways.is_empty() && self.relations.is_empty()
+    }
+}
+
+/// Finds the entities carrying a tag with the given `key` and `value`, or any
+/// tag with the given `key` if `value` is `None`.
+///
+/// Uses the inverted tag index of the archive (compiled with
+/// `osmflatc --with-tag-index`) and performs a binary search over its entries.
+/// Returns `None` if the archive has no tag index.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use osmflat::{entities_with_tag, FileResourceStorage, Osm};
+///
+/// let storage = FileResourceStorage::new("path/to/archive.osm.flatdata");
+/// let archive = Osm::open(storage).unwrap();
+/// let pubs = entities_with_tag(&archive, b"amenity", Some(b"pub")).expect("no tag index");
+/// for idx in pubs.nodes() {
+///     println!("{:?}", archive.nodes()[idx as usize]);
+/// }
+/// ```
+pub fn entities_with_tag<'a>(
+    archive: &'a Osm,
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> Option<TaggedEntities<'a>> {
+    let tag_entities = archive.tag_entities()?;
+    let nodes = archive.tag_entities_nodes()?;
+    let ways = archive.tag_entities_ways()?;
+    let relations = archive.tag_entities_relations()?;
+    let strings = archive.stringtable();
+
+    // entries are sorted by key and value, with the entry of the key first
+    let pos = tag_entities.partition_point(|entry| {
+        let entry_key = strings.substring_raw(entry.key_idx() as usize);
+        let entry_value = entry
+            .value_idx()
+            .map(|idx| strings.substring_raw(idx as usize));
+        (entry_key, entry_value) < (key, value)
+    });
+    let entry = tag_entities.get(pos).filter(|entry| {
+        strings.substring_raw(entry.key_idx() as usize) == key
+            && entry
+                .value_idx()
+                .map(|idx| strings.substring_raw(idx as usize))
+                == value
+    });
+    let slice = |range: Range<u64>| range.start as usize..range.end as usize;
+    Some(match entry {
+        Some(entry) => TaggedEntities {
+            nodes: &nodes[slice(entry.nodes())],
+            ways: &ways[slice(entry.ways())],
+            relations: &relations[slice(entry.relations())],
+        },
+        None => TaggedEntities {
+            nodes: &[],
+            ways: &[],
+            relations: &[],
+        },
+    })
+}
@@ -1,7 +1,90 @@
+use crate::filter::{BBox, TagExpression};
+
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
-/// Compiler of Open Street Data from osm.pbf format to osm.flatdata format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Pbf,
+    Xml,
+    O5m,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pbf" => Ok(InputFormat::Pbf),
+            "xml" => Ok(InputFormat::Xml),
+            "o5m" => Ok(InputFormat::O5m),
+            _ => Err(format!("unknown input format: {}", s)),
+        }
+    }
+}
+
+/// Policy for deduplicating tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDedup {
+    /// Deduplicate all tags
+    All,
+    /// Deduplicate only the most recently used tags
+    Lru,
+    /// Write every tag
+    None,
+}
+
+impl FromStr for TagDedup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(TagDedup::All),
+            "lru" => Ok(TagDedup::Lru),
+            "none" => Ok(TagDedup::None),
+            _ => Err(format!("unknown tag deduplication policy: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for TagDedup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TagDedup::All => write!(f, "all"),
+            TagDedup::Lru => write!(f, "lru"),
+            TagDedup::None => write!(f, "none"),
+        }
+    }
+}
+
+/// Policy for references to entities missing in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dangling {
+    /// Store the reference as invalid index
+    Keep,
+    /// Remove the reference from the way or relation
+    Drop,
+    /// Drop the whole way or relation
+    DropEntity,
+}
+
+impl FromStr for Dangling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Dangling::Keep),
+            "drop" => Ok(Dangling::Drop),
+            "drop-entity" => Ok(Dangling::DropEntity),
+            _ => Err(format!("unknown policy for dangling references: {}", s)),
+        }
+    }
+}
+
+/// Compiler of Open Street Data from osm.pbf, o5m or OSM XML format to osm.flatdata format
 #[derive(Debug, StructOpt)]
 #[structopt(name = "osmflatc")]
 pub struct Args {
@@ -9,11 +92,263 @@ pub struct Args {
     #[structopt(short, long, parse(from_occurrences))]
     pub verbose: u8,
 
-    /// Input OSM pbf file
+    /// Input OSM file (pbf, o5m, or XML optionally compressed with gzip or bzip2)
     #[structopt(name = "input", parse(from_os_str))]
-    pub input: PathBuf,
+    pub input: Option<PathBuf>,
+
+    /// Format of the input file
+    ///
+    /// By default, the format is determined from the extension of the input
+    /// file: `.osm`, `.osh` and `.xml` files (optionally followed by `.gz` or
+    /// `.bz2`) are read as XML, `.o5m` files as o5m, all other files as pbf.
+    #[structopt(long, possible_values = &["pbf", "xml", "o5m"])]
+    pub input_format: Option<InputFormat>,
 
     /// Output directory for OSM flatdata archive
     #[structopt(name = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub options: CompileOptions,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// Parses the command line arguments.
+    ///
+    /// `input` and `output` are required, unless a subcommand is given.
+    pub fn parse() -> Self {
+        let args = Self::from_args();
+        if args.command.is_none() && (args.input.is_none() || args.output.is_none()) {
+            Error::with_description(
+                "The arguments <input> and <output> are required",
+                ErrorKind::MissingRequiredArgument,
+            )
+            .exit();
+        }
+        args
+    }
+}
+
+// Options controlling the compilation of an archive (doc comments here would
+// override the description of the command)
+#[derive(Debug, Clone, StructOpt)]
+pub struct CompileOptions {
+    /// Skip corrupt blocks of the input instead of failing
+    ///
+    /// Skipped blocks are reported at the end of the compilation.
+    #[structopt(long)]
+    pub skip_corrupt_blocks: bool,
+
+    /// Memory budget in MiB for deduplicating strings [default: unbounded]
+    ///
+    /// The strings of the archive are streamed to disk while compiling. Only
+    /// the index for deduplicating them is held in memory. If the budget is
+    /// exceeded, rarely used strings are evicted from the index and are not
+    /// deduplicated anymore, which results in a larger stringtable.
+    #[structopt(long)]
+    pub stringtable_memory: Option<usize>,
+
+    /// Policy for deduplicating tags
+    ///
+    /// `all` deduplicates all tags, which needs memory proportional to the
+    /// number of distinct tags. `lru` only deduplicates the most recently used
+    /// tags, which bounds the memory by `--tag-dedup-capacity` and makes the
+    /// archive slightly larger. `none` writes every tag.
+    #[structopt(long, default_value = "all", possible_values = &["all", "lru", "none"])]
+    pub tag_dedup: TagDedup,
+
+    /// Number of tags kept for deduplication by the `lru` policy
+    #[structopt(long, default_value = "1000000")]
+    pub tag_dedup_capacity: usize,
+
+    /// Store the metadata of entities (version, timestamp, changeset, user)
+    ///
+    /// The metadata is stored in the optional `node_info`, `way_info` and
+    /// `relation_info` vectors, parallel to the corresponding entities.
+    #[structopt(long)]
+    pub with_metadata: bool,
+
+    /// Compile a history file into a history archive (implies `--with-metadata`)
+    ///
+    /// All versions of the entities are stored together with their validity
+    /// in the optional `node_validity`, `way_validity` and `relation_validity`
+    /// vectors. The versions of an entity must be ordered consecutively by
+    /// version, as in history files. References to entities point to their
+    /// first version.
+    #[structopt(long)]
+    pub history: bool,
+
+    /// Store indexes from the ids of entities to their indexes in the archive
+    ///
+    /// The indexes are stored in the optional `node_id_index`, `way_id_index`
+    /// and `relation_id_index` vectors, sorted by id, and allow looking up
+    /// entities by id.
+    #[structopt(long)]
+    pub with_id_index: bool,
+
+    /// Store an index from nodes to the ways using them
+    ///
+    /// For each node, the optional `node_ways` vector contains a range of
+    /// indexes of ways in the `node_ways_index` vector. The pairs of node and
+    /// way are sorted in chunks of bounded size, which are spilled to a
+    /// temporary file in the output directory.
+    #[structopt(long)]
+    pub with_node_ways: bool,
+
+    /// Store indexes from nodes, ways and relations to the relations having
+    /// them as members
+    ///
+    /// For each entity, the optional `node_parent_relations`,
+    /// `way_parent_relations` and `relation_parent_relations` vectors contain a
+    /// range of parent relations and roles in the corresponding `*_index`
+    /// vector. Like for `--with-node-ways`, the members are sorted in chunks
+    /// spilled to a temporary file in the output directory.
+    #[structopt(long)]
+    pub with_parent_relations: bool,
+
+    /// Store an inverted index from tags and keys to the entities carrying them
+    ///
+    /// The optional `tag_entities` vector contains an entry for each distinct
+    /// tag and key, sorted by their strings, with ranges of the indexes of
+    /// nodes, ways and relations in the `tag_entities_*` vectors. Building
+    /// the index sorts two 16 byte entries per tag of an entity in memory.
+    #[structopt(long)]
+    pub with_tag_index: bool,
+
+    /// Store R-trees over the locations of nodes and the bounding boxes of ways
+    ///
+    /// The optional `node_rtree` and `way_rtree` vectors contain the levels of
+    /// packed Hilbert R-trees, whose leaves refer to nodes resp. ways via the
+    /// `node_rtree_index` and `way_rtree_index` vectors. Building the trees
+    /// sorts the bounding boxes of all nodes resp. ways in memory, which needs
+    /// 40 bytes per node and way.
+    #[structopt(long)]
+    pub with_spatial_index: bool,
+
+    /// Order nodes and ways along a Hilbert curve instead of the input order
+    ///
+    /// Nodes are ordered by their location and ways by the center of their
+    /// bounding box, so that entities close to each other are stored close to
+    /// each other. Relations keep the input order. While reordering, the
+    /// Hilbert values and input positions of nodes and ways are kept in
+    /// memory, which needs about 24 bytes per node and 12 bytes per way. The
+    /// reordered input is written to a temporary file next to the output.
+    #[structopt(long, conflicts_with = "history")]
+    pub hilbert_order: bool,
+
+    /// Keep only the data inside a bounding box `min_lon,min_lat,max_lon,max_lat`
+    ///
+    /// Kept are the nodes inside the box, the ways with at least one node
+    /// inside the box together with all their nodes, and the relations
+    /// referencing kept entities. The node and way members of relations
+    /// referencing kept nodes or ways are kept as well, together with their
+    /// nodes. The bounding box of the archive header is set to the box.
+    #[structopt(long, allow_hyphen_values = true, conflicts_with = "polygon")]
+    pub bbox: Option<BBox>,
+
+    /// Keep only the data inside a polygon (Osmosis `.poly` file or GeoJSON)
+    ///
+    /// Files with the extension `.geojson` or `.json` are read as GeoJSON
+    /// containing (multi)polygons. The data is selected as with `--bbox`. The
+    /// bounding box of the archive header is set to the bounding box of the
+    /// polygon.
+    #[structopt(long, parse(from_os_str))]
+    pub polygon: Option<PathBuf>,
+
+    /// Keep only entities with matching tags, e.g. `w/highway`,
+    /// `n/amenity=pub,bar` or `r/type=multipolygon` (can be repeated)
+    ///
+    /// The prefix restricts the expression to nodes (`n`), ways (`w`) and/or
+    /// relations (`r`), without prefix it applies to all entities. Values
+    /// after `!=` match any other value of the key. Entities matching any
+    /// expression are kept together with the entities they reference: the
+    /// nodes of kept ways and the members of kept relations. Combined with a
+    /// region, only entities inside the region are kept.
+    #[structopt(long, number_of_values = 1)]
+    pub keep: Vec<TagExpression>,
+
+    /// Drop untagged nodes, which are neither used by a way nor a member of a
+    /// relation
+    ///
+    /// Applied after the other filters, i.e. nodes only used by dropped ways
+    /// and relations are dropped as well.
+    #[structopt(long)]
+    pub drop_orphan_nodes: bool,
+
+    /// Write the references to entities missing in the input to a file
+    ///
+    /// Every line of the file contains the tab-separated type and id of the
+    /// referencing way or relation, the type and id of the missing entity,
+    /// and the role of the member. References to ways and relations dropped
+    /// with `--dangling drop-entity` are not reported.
+    #[structopt(long, parse(from_os_str))]
+    pub unresolved_report: Option<PathBuf>,
+
+    /// Policy for references to entities missing in the input
+    ///
+    /// `keep` stores them as invalid indexes. `drop` removes them from ways
+    /// and relations. `drop-entity` drops the whole way or relation; entities
+    /// referencing dropped ones are dropped as well. When updating an
+    /// archive, `keep` behaves like `drop` for the references unresolved in
+    /// the archive, since their ids are not stored in it.
+    #[structopt(long, default_value = "keep", possible_values = &["keep", "drop", "drop-entity"])]
+    pub dangling: Dangling,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Applies an OsmChange file to an existing archive and compiles a new
+    /// archive
+    ApplyChanges(ApplyChangesArgs),
+    /// Applies all diffs of a local replication directory, which are newer
+    /// than an existing archive, and compiles a new archive
+    Update(UpdateArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ApplyChangesArgs {
+    /// Existing OSM flatdata archive
+    #[structopt(name = "archive", parse(from_os_str))]
+    pub archive: PathBuf,
+
+    /// OsmChange file (.osc), optionally compressed with gzip or bzip2
+    #[structopt(name = "changes", parse(from_os_str))]
+    pub changes: PathBuf,
+
+    /// Output directory for the new OSM flatdata archive
+    #[structopt(name = "output", parse(from_os_str))]
     pub output: PathBuf,
+
+    /// Replication sequence number of the new archive [default: sequence
+    /// number of the existing archive incremented by 1]
+    #[structopt(long)]
+    pub sequence_number: Option<i64>,
+
+    /// Replication timestamp of the new archive in seconds since epoch
+    /// [default: latest timestamp of the changes]
+    #[structopt(long)]
+    pub timestamp: Option<i64>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UpdateArgs {
+    /// Existing OSM flatdata archive with a replication sequence number
+    #[structopt(name = "archive", parse(from_os_str))]
+    pub archive: PathBuf,
+
+    /// Replication directory containing `state.txt` and the diffs as
+    /// `AAA/BBB/CCC.osc.gz`
+    #[structopt(name = "replication-dir", parse(from_os_str))]
+    pub replication_dir: PathBuf,
+
+    /// Output directory for the new OSM flatdata archive
+    #[structopt(name = "output", parse(from_os_str))]
+    pub output: PathBuf,
+
+    /// Maximum number of diffs to apply [default: all missing diffs]
+    #[structopt(long)]
+    pub max_diffs: Option<u32>,
 }
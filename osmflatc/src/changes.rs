@@ -0,0 +1,537 @@
+use crate::args::{ApplyChangesArgs, CompileOptions, Dangling};
+use crate::entities::{Entity, Info, Member, Node, Relation, Tags, Way};
+use crate::osmpbf::{self, relation::MemberType};
+use crate::osmxml::{self, Action};
+use crate::pbfwriter::PbfWriter;
+use crate::reorder::HILBERT_ORDER_FEATURE;
+use crate::{compile, input, temp_file_next_to, Error};
+
+use flatdata::FileResourceStorage;
+use log::{info, warn};
+use memmap::Mmap;
+use osmflat::{iter_tags, Osm, RelationMembersRef};
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Changed entities by id; deleted entities are stored as `None`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    nodes: HashMap<i64, Option<Node>>,
+    ways: HashMap<i64, Option<Way>>,
+    relations: HashMap<i64, Option<Relation>>,
+    latest_timestamp: Option<i64>,
+}
+
+impl Changes {
+    /// Reads an OsmChange file.
+    ///
+    /// If an entity is changed several times, the last change wins.
+    pub fn read<R: BufRead>(input: R) -> Result<Self, osmxml::Error> {
+        let mut reader = osmxml::Reader::new(input);
+        let mut changes = Self::default();
+        while let Some((action, entity)) = reader.next_change()? {
+            let keep = action != Action::Delete;
+            match entity {
+                Entity::Node(node) => {
+                    changes.nodes.insert(node.id, Some(node).filter(|_| keep));
+                }
+                Entity::Way(way) => {
+                    changes.ways.insert(way.id, Some(way).filter(|_| keep));
+                }
+                Entity::Relation(relation) => {
+                    changes
+                        .relations
+                        .insert(relation.id, Some(relation).filter(|_| keep));
+                }
+            }
+        }
+        changes.latest_timestamp = reader.latest_timestamp();
+        Ok(changes)
+    }
+
+    /// Appends changes which are newer than the changes of `self`.
+    ///
+    /// If an entity is changed in both, the change of `other` wins.
+    pub fn append(&mut self, other: Changes) {
+        self.nodes.extend(other.nodes);
+        self.ways.extend(other.ways);
+        self.relations.extend(other.relations);
+        self.latest_timestamp = self.latest_timestamp.max(other.latest_timestamp);
+    }
+
+    /// Latest timestamp (in seconds since epoch) of the changed entities.
+    pub fn latest_timestamp(&self) -> Option<i64> {
+        self.latest_timestamp
+    }
+}
+
+#[derive(Debug, Default)]
+struct ApplyStats {
+    num_created: usize,
+    num_modified: usize,
+    num_deleted: usize,
+    num_dropped_refs: usize,
+    num_dropped_entities: usize,
+}
+
+/// Applies an OsmChange file to an archive and compiles a new archive.
+pub fn run(args: &ApplyChangesArgs, options: &CompileOptions) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.archive.clone()))?;
+
+    info!("Reading changes...");
+    let changes = Changes::read(input::open_decompressed(&args.changes)?)?;
+
+    let mut header = read_header(&archive);
+    header.osmosis_replication_sequence_number = Some(
+        args.sequence_number
+            .unwrap_or_else(|| header.osmosis_replication_sequence_number.unwrap_or(0) + 1),
+    );
+    if let Some(timestamp) = args.timestamp.or_else(|| changes.latest_timestamp()) {
+        header.osmosis_replication_timestamp = Some(timestamp);
+    }
+
+    compile_with_changes(&archive, changes, &header, &args.output, options)
+}
+
+/// Writes the entities of `archive` with `changes` applied to a temporary PBF
+/// file and compiles it into a new archive at `output`.
+pub fn compile_with_changes(
+    archive: &Osm,
+    changes: Changes,
+    header: &osmpbf::HeaderBlock,
+    output: &Path,
+    options: &CompileOptions,
+) -> Result<(), Error> {
+    if archive.node_validity().is_some() {
+        return Err("Applying changes to a history archive is not supported".into());
+    }
+
+    // the temporary file has roughly the size of the input archive
+    let file = temp_file_next_to(output)?;
+
+    info!("Applying changes...");
+    let mut writer = PbfWriter::new(BufWriter::new(file), header)?;
+    let stats = apply(archive, changes, options.dangling, &mut writer)?;
+    let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+    info!(
+        "Changes applied: {} created, {} modified, {} deleted",
+        stats.num_created, stats.num_modified, stats.num_deleted
+    );
+    if stats.num_dropped_refs > 0 {
+        warn!(
+            "Dropped {} references, which were unresolved in the archive and whose ids are \
+             not stored in it; use --dangling drop-entity to drop the referencing entities",
+            stats.num_dropped_refs
+        );
+    }
+    if stats.num_dropped_entities > 0 {
+        warn!(
+            "Dropped {} ways and relations with references, which were unresolved in the archive",
+            stats.num_dropped_entities
+        );
+    }
+
+    // metadata, indexes and order of the archive are kept
+    let mut options = options.clone();
+    options.with_metadata |= archive.node_info().is_some();
+    options.with_id_index |= archive.node_id_index().is_some();
+    options.with_node_ways |= archive.node_ways().is_some();
+    options.with_parent_relations |= archive.node_parent_relations().is_some();
+    options.with_tag_index |= archive.tag_entities().is_some();
+    options.with_spatial_index |= archive.node_rtree().is_some();
+    options.hilbert_order |= header
+        .optional_features
+        .iter()
+        .any(|feature| feature == HILBERT_ORDER_FEATURE);
+    let data = unsafe { Mmap::map(&file)? };
+    compile(&data, output, &options)
+}
+
+fn apply<W: Write>(
+    archive: &Osm,
+    changes: Changes,
+    dangling: Dangling,
+    writer: &mut PbfWriter<W>,
+) -> io::Result<ApplyStats> {
+    let mut stats = ApplyStats::default();
+    let (mut num_dropped_refs, mut num_dropped_entities) = (0, 0);
+    // The ids of unresolved references are not stored in the archive, so they
+    // cannot be kept and are dropped unless the referencing entity is dropped.
+    let mut is_kept = |num_unresolved: usize| match dangling {
+        _ if num_unresolved == 0 => true,
+        Dangling::Keep | Dangling::Drop => {
+            num_dropped_refs += num_unresolved;
+            true
+        }
+        Dangling::DropEntity => {
+            num_dropped_entities += 1;
+            false
+        }
+    };
+    merge(
+        archive.nodes().iter().map(|node| node.id()),
+        changes.nodes,
+        |idx| Ok(Some(read_node(archive, idx))),
+        |node| writer.write(Entity::Node(node)),
+        &mut stats,
+    )?;
+    merge(
+        archive.ways().iter().map(|way| way.id()),
+        changes.ways,
+        |idx| {
+            let (way, num_unresolved) = read_way(archive, idx);
+            Ok(Some(way).filter(|_| is_kept(num_unresolved)))
+        },
+        |way| writer.write(Entity::Way(way)),
+        &mut stats,
+    )?;
+    merge(
+        archive.relations().iter().map(|relation| relation.id()),
+        changes.relations,
+        |idx| {
+            let (relation, num_unresolved) = read_relation(archive, idx);
+            Ok(Some(relation).filter(|_| is_kept(num_unresolved)))
+        },
+        |relation| writer.write(Entity::Relation(relation)),
+        &mut stats,
+    )?;
+    stats.num_dropped_refs = num_dropped_refs;
+    stats.num_dropped_entities = num_dropped_entities;
+    Ok(stats)
+}
+
+/// Merges the entities of an archive with changed entities.
+///
+/// Entities of the archive keep their order; `read` returns `None` for
+/// entities of the archive, which are dropped. Created entities are inserted
+/// in the order of their ids before the first entity of the archive with a
+/// larger id, which keeps sorted inputs sorted.
+fn merge<T>(
+    ids: impl Iterator<Item = i64> + Clone,
+    mut changes: HashMap<i64, Option<T>>,
+    mut read: impl FnMut(usize) -> io::Result<Option<T>>,
+    mut write: impl FnMut(T) -> io::Result<()>,
+    stats: &mut ApplyStats,
+) -> io::Result<()> {
+    let mut created_ids: HashSet<i64> = changes.keys().cloned().collect();
+    for id in ids.clone() {
+        created_ids.remove(&id);
+    }
+    let mut created: Vec<(i64, T)> = created_ids
+        .into_iter()
+        .filter_map(|id| changes.remove(&id).flatten().map(|entity| (id, entity)))
+        .collect();
+    // sorted descending, so that the entity with the smallest id is at the end
+    created.sort_unstable_by_key(|(id, _)| std::cmp::Reverse(*id));
+    stats.num_created += created.len();
+
+    for (idx, id) in ids.enumerate() {
+        while matches!(created.last(), Some((created_id, _)) if *created_id < id) {
+            write(created.pop().unwrap().1)?;
+        }
+        match changes.remove(&id) {
+            Some(Some(entity)) => {
+                stats.num_modified += 1;
+                write(entity)?;
+            }
+            Some(None) => stats.num_deleted += 1,
+            None => {
+                if let Some(entity) = read(idx)? {
+                    write(entity)?;
+                }
+            }
+        }
+    }
+    for (_, entity) in created.into_iter().rev() {
+        write(entity)?;
+    }
+    Ok(())
+}
+
+fn read_tags(archive: &Osm, range: Range<u64>) -> Tags {
+    iter_tags(archive, range)
+        .map(|(key, value)| {
+            (
+                String::from_utf8_lossy(key).into_owned(),
+                String::from_utf8_lossy(value).into_owned(),
+            )
+        })
+        .collect()
+}
+
+fn read_info(archive: &Osm, infos: Option<&[osmflat::Info]>, idx: usize) -> Option<Info> {
+    let info = infos?.get(idx)?;
+    let user = info.user_idx().map_or_else(String::new, |idx| {
+        String::from_utf8_lossy(archive.stringtable().substring_raw(idx as usize)).into_owned()
+    });
+    Some(Info {
+        version: info.version(),
+        timestamp: info.timestamp(),
+        changeset: info.changeset(),
+        uid: info.uid(),
+        user,
+        visible: None,
+    })
+}
+
+pub fn read_node(archive: &Osm, idx: usize) -> Node {
+    let node = &archive.nodes()[idx];
+    Node {
+        id: node.id(),
+        lat: node.lat(),
+        lon: node.lon(),
+        tags: read_tags(archive, node.tags()),
+        info: read_info(archive, archive.node_info(), idx),
+    }
+}
+
+/// Reads a way of the archive without its unresolved references, and returns
+/// it together with the number of unresolved references.
+pub fn read_way(archive: &Osm, idx: usize) -> (Way, usize) {
+    let way = &archive.ways()[idx];
+    let nodes = archive.nodes();
+    let node_indices = &archive.nodes_index()[way.refs().start as usize..way.refs().end as usize];
+    let refs: Vec<_> = node_indices
+        .iter()
+        .filter_map(|node_idx| node_idx.value())
+        .map(|node_idx| nodes[node_idx as usize].id())
+        .collect();
+    let num_unresolved = node_indices.len() - refs.len();
+    let way = Way {
+        id: way.id(),
+        refs,
+        tags: read_tags(archive, way.tags()),
+        info: read_info(archive, archive.way_info(), idx),
+    };
+    (way, num_unresolved)
+}
+
+/// Reads a relation of the archive without its unresolved members, and
+/// returns it together with the number of unresolved members.
+pub fn read_relation(archive: &Osm, idx: usize) -> (Relation, usize) {
+    let relation = &archive.relations()[idx];
+    let strings = archive.stringtable();
+    let role = |idx: u64| String::from_utf8_lossy(strings.substring_raw(idx as usize)).into_owned();
+    let mut members = Vec::new();
+    let mut num_unresolved = 0;
+    for member in archive.relation_members().at(idx) {
+        let (member_type, id, role_idx) = match member {
+            RelationMembersRef::NodeMember(m) => (
+                MemberType::Node,
+                m.node_idx().map(|idx| archive.nodes()[idx as usize].id()),
+                m.role_idx(),
+            ),
+            RelationMembersRef::WayMember(m) => (
+                MemberType::Way,
+                m.way_idx().map(|idx| archive.ways()[idx as usize].id()),
+                m.role_idx(),
+            ),
+            RelationMembersRef::RelationMember(m) => (
+                MemberType::Relation,
+                m.relation_idx()
+                    .map(|idx| archive.relations()[idx as usize].id()),
+                m.role_idx(),
+            ),
+        };
+        match id {
+            Some(id) => members.push(Member {
+                member_type,
+                id,
+                role: role(role_idx),
+            }),
+            None => num_unresolved += 1,
+        }
+    }
+    let relation = Relation {
+        id: relation.id(),
+        members,
+        tags: read_tags(archive, relation.tags()),
+        info: read_info(archive, archive.relation_info(), idx),
+    };
+    (relation, num_unresolved)
+}
+
+/// Reads the header of an archive back into a PBF header block.
+pub fn read_header(archive: &Osm) -> osmpbf::HeaderBlock {
+    let header = archive.header();
+    let strings = archive.stringtable();
+    let string =
+        |idx: u64| String::from_utf8_lossy(strings.substring_raw(idx as usize)).into_owned();
+    // features are stored consecutively in the stringtable
+    let features = |first_idx: u64, size: u32| {
+        let mut idx = first_idx;
+        (0..size)
+            .map(|_| {
+                let feature = string(idx);
+                idx += feature.len() as u64 + 1;
+                feature
+            })
+            .collect()
+    };
+
+    // Unset strings of the header are stored as index 0, which is also the
+    // index of the first string inserted into the stringtable. Therefore index
+    // 0 is only considered to be set for the first string of the header.
+    let mut is_first_string_taken =
+        header.required_features_size() + header.optional_features_size() > 0;
+    let mut optional_string = |idx: u64| {
+        if idx == 0 {
+            if is_first_string_taken || strings.as_bytes().is_empty() {
+                return None;
+            }
+            is_first_string_taken = true;
+        }
+        Some(string(idx))
+    };
+
+    let bbox = osmpbf::HeaderBBox {
+        left: header.bbox_left(),
+        right: header.bbox_right(),
+        top: header.bbox_top(),
+        bottom: header.bbox_bottom(),
+    };
+    osmpbf::HeaderBlock {
+        bbox: Some(bbox).filter(|bbox| *bbox != osmpbf::HeaderBBox::default()),
+        required_features: features(
+            header.required_feature_first_idx(),
+            header.required_features_size(),
+        ),
+        optional_features: features(
+            header.optional_feature_first_idx(),
+            header.optional_features_size(),
+        ),
+        writingprogram: optional_string(header.writingprogram_idx()),
+        source: optional_string(header.source_idx()),
+        osmosis_replication_timestamp: Some(header.osmosis_replication_timestamp())
+            .filter(|&x| x != 0),
+        osmosis_replication_sequence_number: Some(header.osmosis_replication_sequence_number())
+            .filter(|&x| x != 0),
+        osmosis_replication_base_url: optional_string(header.osmosis_replication_base_url_idx()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::*;
+
+    use structopt::StructOpt;
+
+    /// Compiles `archive` without changes using the given command line
+    /// options.
+    fn recompile(archive: &Osm, args: &[&str]) -> Result<(tempfile::TempDir, Osm), Error> {
+        let options = CompileOptions::from_iter_safe(Some("osmflatc").iter().chain(args))?;
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("archive");
+        let header = read_header(archive);
+        compile_with_changes(archive, Changes::default(), &header, &output, &options)?;
+        let archive = Osm::open(FileResourceStorage::new(output))?;
+        Ok((dir, archive))
+    }
+
+    #[test]
+    fn test_unresolved_references_of_archive() {
+        let entities = vec![
+            node(1, 0, 0),
+            way(10, &[1, 99]),
+            way(11, &[1]),
+            relation(
+                20,
+                &[(MemberType::Node, 77, "label"), (MemberType::Way, 11, "")],
+            ),
+            relation(21, &[(MemberType::Way, 10, "")]),
+        ];
+        let (_dir, archive) = compile_entities(entities, &[]);
+
+        // the ids of the references are not stored, so they cannot be kept
+        for args in &[&[][..], &["--dangling", "drop"]] {
+            let (_dir, dropped) = recompile(&archive, args).unwrap();
+            assert_eq!(way_ids(&dropped), vec![10, 11]);
+            assert_eq!(way_refs(&dropped, 0), vec![Some(1)]);
+            assert_eq!(relation_ids(&dropped), vec![20, 21]);
+            assert_eq!(
+                relation_members(&dropped, 0),
+                vec![(MemberType::Way, Some(11), String::new())]
+            );
+        }
+
+        // relation 21 is dropped, since its member way 10 is dropped
+        let (_dir, dropped) = recompile(&archive, &["--dangling", "drop-entity"]).unwrap();
+        assert_eq!(way_ids(&dropped), vec![11]);
+        assert_eq!(relation_ids(&dropped), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_keep_resources_of_archive() {
+        let entities = vec![
+            node(1, 0, 0),
+            node(2, 1000, 1000),
+            way(10, &[2, 1]),
+            relation(20, &[(MemberType::Way, 10, "")]),
+        ];
+        let args = [
+            "--with-metadata",
+            "--with-id-index",
+            "--with-node-ways",
+            "--with-parent-relations",
+            "--with-tag-index",
+            "--with-spatial-index",
+            "--hilbert-order",
+        ];
+        let (_dir, archive) = compile_entities(entities, &args);
+        let (_dir, updated) = recompile(&archive, &[]).unwrap();
+
+        assert!(updated.node_info().is_some());
+        assert!(updated.node_id_index().is_some());
+        assert!(updated.node_ways().is_some());
+        assert!(updated.node_parent_relations().is_some());
+        assert!(updated.tag_entities().is_some());
+        assert!(updated.node_rtree().is_some());
+        let features = read_header(&updated).optional_features;
+        assert_eq!(features, vec![HILBERT_ORDER_FEATURE.to_string()]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let archive = [(1, "a"), (3, "b"), (5, "c"), (7, "d")];
+        let mut changes = HashMap::new();
+        changes.insert(0, Some("created 0"));
+        changes.insert(3, None);
+        changes.insert(4, Some("created 4"));
+        changes.insert(5, Some("modified 5"));
+        changes.insert(6, None);
+        changes.insert(9, Some("created 9"));
+
+        let mut result = Vec::new();
+        let mut stats = ApplyStats::default();
+        merge(
+            archive.iter().map(|(id, _)| *id),
+            changes,
+            |idx| Ok(Some(archive[idx].1)),
+            |entity| {
+                result.push(entity);
+                Ok(())
+            },
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                "created 0",
+                "a",
+                "created 4",
+                "modified 5",
+                "d",
+                "created 9"
+            ]
+        );
+        assert_eq!(stats.num_created, 3);
+        assert_eq!(stats.num_modified, 1);
+        assert_eq!(stats.num_deleted, 1);
+    }
+}
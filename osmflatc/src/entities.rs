@@ -0,0 +1,65 @@
+//! Plain OSM entities used by readers of input formats other than PBF.
+//!
+//! The entities are converted to PBF by `pbfwriter`, so that all input
+//! formats are compiled by the same pipeline.
+
+use crate::osmpbf::relation::MemberType;
+
+/// List of `(key, value)` pairs of an entity.
+pub type Tags = Vec<(String, String)>;
+
+/// Metadata of an entity.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Info {
+    pub version: i32,
+    /// Timestamp in seconds since epoch
+    pub timestamp: i64,
+    pub changeset: i64,
+    pub uid: i32,
+    /// User name, empty if unknown
+    pub user: String,
+    /// Whether the entity exists in this version, only set in history files
+    pub visible: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Node {
+    pub id: i64,
+    /// Latitude in nanodegrees
+    pub lat: i64,
+    /// Longitude in nanodegrees
+    pub lon: i64,
+    pub tags: Tags,
+    pub info: Option<Info>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Way {
+    pub id: i64,
+    /// Ids of the referenced nodes
+    pub refs: Vec<i64>,
+    pub tags: Tags,
+    pub info: Option<Info>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub member_type: MemberType,
+    pub id: i64,
+    pub role: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Relation {
+    pub id: i64,
+    pub members: Vec<Member>,
+    pub tags: Tags,
+    pub info: Option<Info>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entity {
+    Node(Node),
+    Way(Way),
+    Relation(Relation),
+}
@@ -0,0 +1,236 @@
+//! Sorting of more items than fit into memory.
+
+use memmap::Mmap;
+use rayon::prelude::*;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Memory used by the items of a chunk, which is sorted in memory
+const CHUNK_MEMORY: usize = 256 * 1024 * 1024;
+
+/// Item of fixed size, which is written to a file when sorted externally
+pub trait Item: Copy + Send {
+    /// Number of bytes of a written item
+    const SIZE: usize;
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Reads an item from the first `SIZE` bytes of `bytes`.
+    fn read(bytes: &[u8]) -> Self;
+}
+
+fn read_u64(bytes: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(bytes[pos * 8..(pos + 1) * 8].try_into().unwrap())
+}
+
+impl Item for (u64, u64) {
+    const SIZE: usize = 16;
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.0.to_le_bytes())?;
+        writer.write_all(&self.1.to_le_bytes())
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        (read_u64(bytes, 0), read_u64(bytes, 1))
+    }
+}
+
+impl Item for (u64, u64, u64) {
+    const SIZE: usize = 24;
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.0.to_le_bytes())?;
+        writer.write_all(&self.1.to_le_bytes())?;
+        writer.write_all(&self.2.to_le_bytes())
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        (read_u64(bytes, 0), read_u64(bytes, 1), read_u64(bytes, 2))
+    }
+}
+
+/// Sorts items by a key with bounded memory.
+///
+/// Pushed items are collected in a chunk of bounded size. A full chunk is
+/// sorted in memory and written to a temporary file; the sorted chunks are
+/// merged when the items are read. If all items fit into a single chunk, no
+/// file is created.
+///
+/// The sort is stable: items with equal keys are read in the order in which
+/// they were pushed.
+pub struct ExternalSorter<T, K> {
+    key: fn(&T) -> K,
+    chunk: Vec<T>,
+    chunk_size: usize,
+    temp_dir: PathBuf,
+    /// File with the sorted chunks written so far
+    file: Option<BufWriter<File>>,
+    /// Number of items of each chunk in `file`
+    chunk_lens: Vec<usize>,
+}
+
+impl<T: Item, K: Ord + Send> ExternalSorter<T, K> {
+    /// Creates a sorter which writes its temporary file to `temp_dir`.
+    pub fn new(temp_dir: &Path, key: fn(&T) -> K) -> Self {
+        let chunk_size = (CHUNK_MEMORY / mem::size_of::<T>()).max(1);
+        Self::with_chunk_size(temp_dir, key, chunk_size)
+    }
+
+    /// Creates a sorter which sorts at most `chunk_size` items in memory.
+    pub fn with_chunk_size(temp_dir: &Path, key: fn(&T) -> K, chunk_size: usize) -> Self {
+        Self {
+            key,
+            chunk: Vec::new(),
+            chunk_size,
+            temp_dir: temp_dir.to_path_buf(),
+            file: None,
+            chunk_lens: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: T) -> io::Result<()> {
+        self.chunk.push(item);
+        if self.chunk.len() >= self.chunk_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Sorts the current chunk and appends it to the temporary file.
+    fn spill(&mut self) -> io::Result<()> {
+        if self.file.is_none() {
+            let file = tempfile::tempfile_in(&self.temp_dir)?;
+            self.file = Some(BufWriter::new(file));
+        }
+        let file = self.file.as_mut().unwrap();
+        self.chunk.par_sort_by_key(self.key);
+        for item in &self.chunk {
+            item.write(file)?;
+        }
+        self.chunk_lens.push(self.chunk.len());
+        self.chunk.clear();
+        Ok(())
+    }
+
+    /// Finishes pushing and returns the items in sorted order.
+    pub fn sort(mut self) -> io::Result<Sorted<T, K>> {
+        if self.file.is_none() {
+            self.chunk.par_sort_by_key(self.key);
+            return Ok(Sorted {
+                key: self.key,
+                items: self.chunk.into_iter(),
+                mmap: None,
+                chunks: Vec::new(),
+                heap: BinaryHeap::new(),
+            });
+        }
+        if !self.chunk.is_empty() {
+            self.spill()?;
+        }
+        let file = self
+            .file
+            .take()
+            .unwrap()
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let mut start = 0;
+        let chunks: Vec<_> = self
+            .chunk_lens
+            .iter()
+            .map(|len| {
+                let range = start..start + len * T::SIZE;
+                start = range.end;
+                range
+            })
+            .collect();
+        let heap = chunks
+            .iter()
+            .enumerate()
+            .map(|(chunk_idx, range)| {
+                Reverse(((self.key)(&T::read(&mmap[range.start..])), chunk_idx))
+            })
+            .collect();
+        Ok(Sorted {
+            key: self.key,
+            items: Vec::new().into_iter(),
+            mmap: Some(mmap),
+            chunks,
+            heap,
+        })
+    }
+}
+
+/// Iterator over the items of an [`ExternalSorter`] in sorted order.
+pub struct Sorted<T, K> {
+    key: fn(&T) -> K,
+    /// Items sorted in memory, if no chunk was written to disk
+    items: std::vec::IntoIter<T>,
+    /// Sorted chunks written to disk
+    mmap: Option<Mmap>,
+    /// Byte ranges of the remaining items of each chunk in `mmap`
+    chunks: Vec<Range<usize>>,
+    /// Key of the next item of each non-empty chunk; equal keys are ordered
+    /// by chunk, which keeps the merge stable.
+    heap: BinaryHeap<Reverse<(K, usize)>>,
+}
+
+impl<T: Item, K: Ord> Iterator for Sorted<T, K> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mmap = match &self.mmap {
+            Some(mmap) => mmap,
+            None => return self.items.next(),
+        };
+        let Reverse((_, chunk_idx)) = self.heap.pop()?;
+        let range = &mut self.chunks[chunk_idx];
+        let item = T::read(&mmap[range.start..]);
+        range.start += T::SIZE;
+        if range.start < range.end {
+            let next = T::read(&mmap[range.start..]);
+            self.heap.push(Reverse(((self.key)(&next), chunk_idx)));
+        }
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sort(items: &[(u64, u64)], chunk_size: usize) -> Vec<(u64, u64)> {
+        let mut sorter =
+            ExternalSorter::with_chunk_size(&std::env::temp_dir(), |&(key, _)| key, chunk_size);
+        for &item in items {
+            sorter.push(item).unwrap();
+        }
+        sorter.sort().unwrap().collect()
+    }
+
+    #[test]
+    fn test_sort_in_chunks_is_stable() {
+        let items: Vec<(u64, u64)> = (0..1000).map(|i| ((i * 7919) % 13, i)).collect();
+        let mut expected = items.clone();
+        expected.sort_by_key(|&(key, _)| key);
+
+        for chunk_size in &[1, 7, 100, 999, 1000, 5000] {
+            assert_eq!(sort(&items, *chunk_size), expected);
+        }
+    }
+
+    #[test]
+    fn test_sort_empty() {
+        assert_eq!(sort(&[], 1), vec![]);
+        assert_eq!(sort(&[], 10), vec![]);
+    }
+}
@@ -0,0 +1,1047 @@
+//! Selection of the entities which are compiled into an archive.
+
+use crate::osmpbf::{self, read_block, relation::MemberType, BlockIndex};
+use crate::osmxml::parse_coordinate;
+use crate::parallel;
+use crate::polygon::Polygon;
+
+use log::info;
+
+use std::collections::HashMap;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+/// Typed ids of the members of a relation.
+type Members = Vec<(MemberType, i64)>;
+
+/// Bounding box with coordinates in nanodegrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BBox {
+    pub min_lon: i64,
+    pub min_lat: i64,
+    pub max_lon: i64,
+    pub max_lat: i64,
+}
+
+impl BBox {
+    pub fn contains(&self, lat: i64, lon: i64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+
+    pub fn to_header_bbox(self) -> osmpbf::HeaderBBox {
+        osmpbf::HeaderBBox {
+            left: self.min_lon,
+            right: self.max_lon,
+            top: self.max_lat,
+            bottom: self.min_lat,
+        }
+    }
+}
+
+impl FromStr for BBox {
+    type Err = String;
+
+    /// Parses a bounding box `min_lon,min_lat,max_lon,max_lat` in degrees.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid bounding box: {}", s);
+        let coordinates = s
+            .split(',')
+            .map(|value| parse_coordinate(value.trim()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let bbox = match coordinates[..] {
+            [min_lon, min_lat, max_lon, max_lat] => BBox {
+                min_lon,
+                min_lat,
+                max_lon,
+                max_lat,
+            },
+            _ => return Err(invalid()),
+        };
+        let max_lat = 90 * osmflat::COORD_SCALE as i64;
+        let max_lon = 180 * osmflat::COORD_SCALE as i64;
+        if bbox.min_lon > bbox.max_lon
+            || bbox.min_lat > bbox.max_lat
+            || bbox.min_lat < -max_lat
+            || bbox.max_lat > max_lat
+            || bbox.min_lon < -max_lon
+            || bbox.max_lon > max_lon
+        {
+            return Err(invalid());
+        }
+        Ok(bbox)
+    }
+}
+
+/// Region to which the compiled data is clipped.
+#[derive(Debug, Clone)]
+pub enum Region {
+    BBox(BBox),
+    Polygon(Polygon),
+}
+
+impl Region {
+    pub fn contains(&self, lat: i64, lon: i64) -> bool {
+        match self {
+            Region::BBox(bbox) => bbox.contains(lat, lon),
+            Region::Polygon(polygon) => polygon.contains(lat, lon),
+        }
+    }
+
+    /// Bounding box of the region.
+    pub fn bbox(&self) -> BBox {
+        match self {
+            Region::BBox(bbox) => *bbox,
+            Region::Polygon(polygon) => polygon.bbox(),
+        }
+    }
+}
+
+/// Ids of the entities which are compiled into an archive.
+#[derive(Debug, Default)]
+pub struct Selection {
+    nodes: IdSet,
+    ways: IdSet,
+    relations: IdSet,
+}
+
+impl Selection {
+    /// Selects the entities inside `region`.
+    ///
+    /// These are the nodes inside the region, the ways with at least one node
+    /// inside the region together with all of their nodes, and the relations
+    /// referencing any selected entity, also indirectly via other relations.
+    /// The node and way members of relations referencing a selected node or
+    /// way are selected as well, together with the nodes of these ways.
+    /// Relations which are only selected as parents of selected relations are
+    /// not completed, so that e.g. a route master does not pull in all of its
+    /// routes.
+    pub fn clip(
+        reader: &BlockReader,
+        node_blocks: &[BlockIndex],
+        way_blocks: &[BlockIndex],
+        relation_blocks: &[BlockIndex],
+        region: &Region,
+    ) -> Result<Self, osmpbf::Error> {
+        info!("Selecting nodes inside the region...");
+        let mut nodes_inside = IdSet::default();
+        parallel::parallel_process(
+            node_blocks.iter(),
+            |idx| -> Result<Vec<i64>, osmpbf::Error> {
+                let mut ids = Vec::new();
+                for_each_node(&reader.read(idx)?, |id, lat, lon| {
+                    if region.contains(lat, lon) {
+                        ids.push(id);
+                    }
+                });
+                Ok(ids)
+            },
+            |ids| -> Result<(), osmpbf::Error> {
+                nodes_inside.extend(ids?);
+                Ok(())
+            },
+        )?;
+
+        info!("Selecting ways...");
+        let mut selection = Selection::default();
+        parallel::parallel_process(
+            way_blocks.iter(),
+            |idx| -> Result<Vec<(i64, Vec<i64>)>, osmpbf::Error> {
+                let block = reader.read(idx)?;
+                let ways = block.primitivegroup.iter().flat_map(|group| &group.ways);
+                Ok(ways
+                    .map(|way| (way.id, decode_deltas(&way.refs)))
+                    .filter(|(_, refs)| refs.iter().any(|&id| nodes_inside.contains(id)))
+                    .collect())
+            },
+            |ways| -> Result<(), osmpbf::Error> {
+                for (id, refs) in ways? {
+                    selection.ways.insert(id);
+                    selection.nodes.extend(refs);
+                }
+                Ok(())
+            },
+        )?;
+        selection.nodes.union_with(&nodes_inside);
+
+        info!("Selecting relations...");
+        let mut relations = Vec::new();
+        parallel::parallel_process(
+            relation_blocks.iter(),
+            |idx| -> Result<Vec<(i64, Members)>, osmpbf::Error> {
+                let block = reader.read(idx)?;
+                let relations = block
+                    .primitivegroup
+                    .iter()
+                    .flat_map(|group| &group.relations);
+                Ok(relations
+                    .map(|relation| (relation.id, members(relation)))
+                    .collect())
+            },
+            |block_relations| -> Result<(), osmpbf::Error> {
+                relations.extend(block_relations?);
+                Ok(())
+            },
+        )?;
+        // relations referencing selected nodes or ways are completed below
+        let mut direct_relations = IdSet::default();
+        for (id, members) in &relations {
+            let is_direct = members
+                .iter()
+                .any(|&(t, id)| t != MemberType::Relation && selection.contains(t, id));
+            if is_direct {
+                direct_relations.insert(*id);
+                selection.relations.insert(*id);
+            }
+        }
+        // relations referencing selected relations are selected as well, which
+        // is repeated until no more relations are selected
+        loop {
+            let num_relations = selection.relations.len();
+            for (id, members) in &relations {
+                if !selection.relations.contains(*id)
+                    && members.iter().any(|&(t, id)| selection.contains(t, id))
+                {
+                    selection.relations.insert(*id);
+                }
+            }
+            if selection.relations.len() == num_relations {
+                break;
+            }
+        }
+
+        let mut member_ways = IdSet::default();
+        let direct_members = relations
+            .iter()
+            .filter(|(id, _)| direct_relations.contains(*id))
+            .flat_map(|(_, members)| members);
+        for &(member_type, member_id) in direct_members {
+            match member_type {
+                MemberType::Node => {
+                    selection.nodes.insert(member_id);
+                }
+                MemberType::Way => {
+                    if selection.ways.insert(member_id) {
+                        member_ways.insert(member_id);
+                    }
+                }
+                MemberType::Relation => (),
+            }
+        }
+        drop(relations);
+
+        if member_ways.len() != 0 {
+            info!("Selecting nodes of member ways...");
+            parallel::parallel_process(
+                way_blocks.iter(),
+                |idx| -> Result<Vec<i64>, osmpbf::Error> {
+                    let block = reader.read(idx)?;
+                    let ways = block.primitivegroup.iter().flat_map(|group| &group.ways);
+                    Ok(ways
+                        .filter(|way| member_ways.contains(way.id))
+                        .flat_map(|way| decode_deltas(&way.refs))
+                        .collect())
+                },
+                |refs| -> Result<(), osmpbf::Error> {
+                    selection.nodes.extend(refs?);
+                    Ok(())
+                },
+            )?;
+        }
+
+        info!(
+            "Selected {} nodes, {} ways and {} relations.",
+            selection.nodes.len(),
+            selection.ways.len(),
+            selection.relations.len()
+        );
+        Ok(selection)
+    }
+
+    /// Selects the entities matching any of `expressions` by their tags.
+    ///
+    /// Additionally, all members of selected relations, also indirectly via
+    /// other relations, and all nodes of selected ways are selected, so that
+    /// the selection contains every entity referenced by a selected entity.
+    pub fn filter_tags(
+        reader: &BlockReader,
+        node_blocks: &[BlockIndex],
+        way_blocks: &[BlockIndex],
+        relation_blocks: &[BlockIndex],
+        expressions: &[TagExpression],
+    ) -> Result<Self, osmpbf::Error> {
+        info!("Selecting relations by tags...");
+        let mut relations = HashMap::new();
+        let mut matching_relations = Vec::new();
+        parallel::parallel_process(
+            relation_blocks.iter(),
+            |idx| -> Result<Vec<(i64, bool, Members)>, osmpbf::Error> {
+                let block = reader.read(idx)?;
+                let string = |idx| block_string(&block, idx);
+                let relations = block
+                    .primitivegroup
+                    .iter()
+                    .flat_map(|group| &group.relations);
+                Ok(relations
+                    .map(|relation| {
+                        let tags = relation.keys.iter().zip(&relation.vals);
+                        let tags = tags.map(|(&k, &v)| (string(k), string(v)));
+                        let is_match = matches(expressions, MemberType::Relation, tags);
+                        (relation.id, is_match, members(relation))
+                    })
+                    .collect())
+            },
+            |block_relations| -> Result<(), osmpbf::Error> {
+                for (id, is_match, members) in block_relations? {
+                    if is_match {
+                        matching_relations.push(id);
+                    }
+                    relations.insert(id, members);
+                }
+                Ok(())
+            },
+        )?;
+        let mut selection = Selection::default();
+        let mut member_ways = IdSet::default();
+        let mut referenced_nodes = IdSet::default();
+        selection
+            .relations
+            .extend(matching_relations.iter().copied());
+        while let Some(id) = matching_relations.pop() {
+            for &(member_type, member_id) in relations.get(&id).into_iter().flatten() {
+                match member_type {
+                    MemberType::Node => {
+                        referenced_nodes.insert(member_id);
+                    }
+                    MemberType::Way => {
+                        member_ways.insert(member_id);
+                    }
+                    MemberType::Relation => {
+                        if selection.relations.insert(member_id) {
+                            matching_relations.push(member_id);
+                        }
+                    }
+                }
+            }
+        }
+        drop(relations);
+
+        info!("Selecting ways by tags...");
+        parallel::parallel_process(
+            way_blocks.iter(),
+            |idx| -> Result<Vec<(i64, Vec<i64>)>, osmpbf::Error> {
+                let block = reader.read(idx)?;
+                let string = |idx| block_string(&block, idx);
+                let ways = block.primitivegroup.iter().flat_map(|group| &group.ways);
+                Ok(ways
+                    .filter(|way| {
+                        let tags = way.keys.iter().zip(&way.vals);
+                        let tags = tags.map(|(&k, &v)| (string(k), string(v)));
+                        member_ways.contains(way.id) || matches(expressions, MemberType::Way, tags)
+                    })
+                    .map(|way| (way.id, decode_deltas(&way.refs)))
+                    .collect())
+            },
+            |ways| -> Result<(), osmpbf::Error> {
+                for (id, refs) in ways? {
+                    selection.ways.insert(id);
+                    referenced_nodes.extend(refs);
+                }
+                Ok(())
+            },
+        )?;
+
+        info!("Selecting nodes by tags...");
+        parallel::parallel_process(
+            node_blocks.iter(),
+            |idx| -> Result<Vec<i64>, osmpbf::Error> {
+                let mut ids = Vec::new();
+                for_each_node_with_tags(&reader.read(idx)?, |id, tags| {
+                    if referenced_nodes.contains(id) || matches(expressions, MemberType::Node, tags)
+                    {
+                        ids.push(id);
+                    }
+                });
+                Ok(ids)
+            },
+            |ids| -> Result<(), osmpbf::Error> {
+                selection.nodes.extend(ids?);
+                Ok(())
+            },
+        )?;
+
+        info!(
+            "Selected {} nodes, {} ways and {} relations.",
+            selection.nodes.len(),
+            selection.ways.len(),
+            selection.relations.len()
+        );
+        Ok(selection)
+    }
+
+    fn contains(&self, member_type: MemberType, id: i64) -> bool {
+        match member_type {
+            MemberType::Node => self.nodes.contains(id),
+            MemberType::Way => self.ways.contains(id),
+            MemberType::Relation => self.relations.contains(id),
+        }
+    }
+
+    /// Removes the entities from a block, which are not selected.
+    pub fn filter_block(&self, block: &mut osmpbf::PrimitiveBlock) {
+        for group in &mut block.primitivegroup {
+            if let Some(dense) = &mut group.dense {
+                retain_dense_nodes(dense, |id, _| self.nodes.contains(id));
+            }
+            group.nodes.retain(|node| self.nodes.contains(node.id));
+            group.ways.retain(|way| self.ways.contains(way.id));
+            group
+                .relations
+                .retain(|relation| self.relations.contains(relation.id));
+        }
+    }
+}
+
+/// Number of ids covered by a page of an [`IdSet`]
+const ID_SET_PAGE_BITS: u32 = 16;
+
+/// Set of ids stored as a paged bitset.
+///
+/// Each page covers 2^16 consecutive ids and is only allocated if it contains
+/// any id, so that the memory follows the ids present in the set, also for
+/// large or negative ids.
+#[derive(Debug, Default)]
+pub struct IdSet {
+    pages: HashMap<i64, Box<[u64]>>,
+}
+
+impl IdSet {
+    fn position(id: i64) -> (i64, usize, u64) {
+        let page = id >> ID_SET_PAGE_BITS;
+        let offset = (id & ((1 << ID_SET_PAGE_BITS) - 1)) as usize;
+        (page, offset / 64, 1 << (offset % 64))
+    }
+
+    /// Inserts an id and returns whether it was not contained before.
+    pub fn insert(&mut self, id: i64) -> bool {
+        let (page, word, mask) = Self::position(id);
+        let words = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| vec![0; 1 << (ID_SET_PAGE_BITS - 6)].into_boxed_slice());
+        let is_new = words[word] & mask == 0;
+        words[word] |= mask;
+        is_new
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        let (page, word, mask) = Self::position(id);
+        self.pages
+            .get(&page)
+            .is_some_and(|words| words[word] & mask != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        let words = self.pages.values().flat_map(|words| words.iter());
+        words.map(|word| u64::from(word.count_ones())).sum::<u64>() as usize
+    }
+
+    /// Inserts all ids of `other`.
+    pub fn union_with(&mut self, other: &IdSet) {
+        for (page, other_words) in &other.pages {
+            match self.pages.get_mut(page) {
+                Some(words) => {
+                    for (word, other_word) in words.iter_mut().zip(other_words.iter()) {
+                        *word |= other_word;
+                    }
+                }
+                None => {
+                    self.pages.insert(*page, other_words.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Extend<i64> for IdSet {
+    fn extend<I: IntoIterator<Item = i64>>(&mut self, ids: I) {
+        for id in ids {
+            self.insert(id);
+        }
+    }
+}
+
+/// Collects the ids of the nodes referenced by ways or relations.
+pub fn used_nodes(
+    reader: &BlockReader,
+    way_blocks: &[BlockIndex],
+    relation_blocks: &[BlockIndex],
+) -> Result<IdSet, osmpbf::Error> {
+    info!("Collecting nodes used by ways and relations...");
+    let mut used = IdSet::default();
+    parallel::parallel_process(
+        way_blocks.iter().chain(relation_blocks),
+        |idx| -> Result<Vec<i64>, osmpbf::Error> {
+            let block = reader.read(idx)?;
+            let mut ids = Vec::new();
+            for group in &block.primitivegroup {
+                for way in &group.ways {
+                    ids.extend(decode_deltas(&way.refs));
+                }
+                for relation in &group.relations {
+                    let members = members(relation).into_iter();
+                    ids.extend(
+                        members
+                            .filter(|&(t, _)| t == MemberType::Node)
+                            .map(|(_, id)| id),
+                    );
+                }
+            }
+            Ok(ids)
+        },
+        |ids| -> Result<(), osmpbf::Error> {
+            for id in ids? {
+                used.insert(id);
+            }
+            Ok(())
+        },
+    )?;
+    info!("Found {} used nodes.", used.len());
+    Ok(used)
+}
+
+/// Reads primitive blocks of the input, keeping only the selected entities.
+pub struct BlockReader<'a> {
+    data: &'a [u8],
+    selection: Option<Selection>,
+    used_nodes: Option<IdSet>,
+}
+
+impl<'a> BlockReader<'a> {
+    /// Creates a reader of the blocks in `data`; if `selection` is `None`, all
+    /// entities are kept.
+    pub fn new(data: &'a [u8], selection: Option<Selection>) -> Self {
+        Self {
+            data,
+            selection,
+            used_nodes: None,
+        }
+    }
+
+    /// Additionally drops the untagged nodes which are not in `used_nodes`.
+    pub fn drop_orphan_nodes(self, used_nodes: IdSet) -> Self {
+        Self {
+            used_nodes: Some(used_nodes),
+            ..self
+        }
+    }
+
+    pub fn read(&self, idx: &BlockIndex) -> Result<osmpbf::PrimitiveBlock, osmpbf::Error> {
+        let mut block = read_block(self.data, idx)?;
+        if let Some(selection) = &self.selection {
+            selection.filter_block(&mut block);
+        }
+        if let Some(used) = &self.used_nodes {
+            for group in &mut block.primitivegroup {
+                if let Some(dense) = &mut group.dense {
+                    retain_dense_nodes(dense, |id, has_tags| has_tags || used.contains(id));
+                }
+                group
+                    .nodes
+                    .retain(|node| !node.keys.is_empty() || used.contains(node.id));
+            }
+        }
+        Ok(block)
+    }
+}
+
+/// Expression selecting entities by a tag, e.g. `w/highway`, `n/amenity=pub,bar`
+/// or `r/type!=route`.
+///
+/// The optional prefix restricts the expression to nodes (`n`), ways (`w`)
+/// and/or relations (`r`). A key alone matches any value of the key, values
+/// after `=` match any of the comma-separated values, and values after `!=`
+/// match any other value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagExpression {
+    nodes: bool,
+    ways: bool,
+    relations: bool,
+    key: String,
+    values: Vec<String>,
+    negated: bool,
+}
+
+impl TagExpression {
+    fn applies_to(&self, member_type: MemberType) -> bool {
+        match member_type {
+            MemberType::Node => self.nodes,
+            MemberType::Way => self.ways,
+            MemberType::Relation => self.relations,
+        }
+    }
+
+    fn matches(&self, key: &[u8], value: &[u8]) -> bool {
+        key == self.key.as_bytes()
+            && (self.values.is_empty()
+                || self.values.iter().any(|v| v.as_bytes() == value) != self.negated)
+    }
+}
+
+impl FromStr for TagExpression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid tag expression: {}", s);
+        let (types, tag) = match s.find('/') {
+            Some(pos) if pos > 0 && s[..pos].bytes().all(|c| b"nwr".contains(&c)) => {
+                (&s[..pos], &s[pos + 1..])
+            }
+            _ => ("nwr", s),
+        };
+        let (key, values, negated) = match tag.find('=') {
+            Some(pos) if tag[..pos].ends_with('!') => {
+                (&tag[..pos - 1], Some(&tag[pos + 1..]), true)
+            }
+            Some(pos) => (&tag[..pos], Some(&tag[pos + 1..]), false),
+            None => (tag, None, false),
+        };
+        let values: Vec<String> = values
+            .map(|values| values.split(',').map(String::from).collect())
+            .unwrap_or_default();
+        if key.is_empty() || values.iter().any(String::is_empty) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            nodes: types.contains('n'),
+            ways: types.contains('w'),
+            relations: types.contains('r'),
+            key: key.to_string(),
+            values,
+            negated,
+        })
+    }
+}
+
+/// Whether any of the tags matches any of the expressions applying to the type
+/// of the entity.
+fn matches<'a>(
+    expressions: &[TagExpression],
+    member_type: MemberType,
+    mut tags: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+) -> bool {
+    tags.any(|(key, value)| {
+        expressions
+            .iter()
+            .any(|e| e.applies_to(member_type) && e.matches(key, value))
+    })
+}
+
+fn block_string(block: &osmpbf::PrimitiveBlock, idx: u32) -> &[u8] {
+    block
+        .stringtable
+        .s
+        .get(idx as usize)
+        .map_or(&[][..], Vec::as_slice)
+}
+
+fn members(relation: &osmpbf::Relation) -> Members {
+    let types = relation.types.iter().map(|&t| MemberType::from_i32(t));
+    types
+        .zip(decode_deltas(&relation.memids))
+        .filter_map(|(member_type, id)| Some((member_type?, id)))
+        .collect()
+}
+
+/// Calls `f` with the id and the tags of all nodes in a block.
+fn for_each_node_with_tags<'a>(
+    block: &'a osmpbf::PrimitiveBlock,
+    mut f: impl FnMut(i64, &mut dyn Iterator<Item = (&'a [u8], &'a [u8])>),
+) {
+    let string = |idx| block_string(block, idx);
+    for group in &block.primitivegroup {
+        if let Some(dense) = &group.dense {
+            let keys_vals = &dense.keys_vals;
+            let mut offset = 0;
+            for id in decode_deltas(&dense.id) {
+                let mut tags = std::iter::from_fn(|| {
+                    let key = *keys_vals.get(offset)?;
+                    offset += 1;
+                    if key == 0 {
+                        return None;
+                    }
+                    let value = *keys_vals.get(offset)?;
+                    offset += 1;
+                    Some((string(key as u32), string(value as u32)))
+                })
+                .fuse();
+                f(id, &mut tags);
+                // skip the tags not consumed by `f`
+                tags.for_each(drop);
+            }
+        }
+        for node in &group.nodes {
+            let tags = node.keys.iter().zip(&node.vals);
+            f(node.id, &mut tags.map(|(&k, &v)| (string(k), string(v))));
+        }
+    }
+}
+
+/// Calls `f` with the id and the coordinates in nanodegrees of all nodes in a
+/// block.
+fn for_each_node(block: &osmpbf::PrimitiveBlock, mut f: impl FnMut(i64, i64, i64)) {
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    for group in &block.primitivegroup {
+        if let Some(dense) = &group.dense {
+            let ids = decode_deltas(&dense.id);
+            let lats = decode_deltas(&dense.lat);
+            let lons = decode_deltas(&dense.lon);
+            for ((id, lat), lon) in ids.into_iter().zip(lats).zip(lons) {
+                f(
+                    id,
+                    lat_offset + granularity * lat,
+                    lon_offset + granularity * lon,
+                );
+            }
+        }
+        for node in &group.nodes {
+            f(
+                node.id,
+                lat_offset + granularity * node.lat,
+                lon_offset + granularity * node.lon,
+            );
+        }
+    }
+}
+
+fn decode_deltas<T>(deltas: &[T]) -> Vec<T>
+where
+    T: Copy + Default + Add<Output = T>,
+{
+    let mut value = T::default();
+    deltas
+        .iter()
+        .map(|&delta| {
+            value = value + delta;
+            value
+        })
+        .collect()
+}
+
+/// Keeps the delta coded values at the positions where `keep` is set.
+fn retain_deltas<T>(deltas: &[T], keep: &[bool]) -> Vec<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    let mut previous = T::default();
+    decode_deltas(deltas)
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(value, _)| {
+            let delta = value - previous;
+            previous = value;
+            delta
+        })
+        .collect()
+}
+
+fn retain_values<T: Copy>(values: &[T], keep: &[bool]) -> Vec<T> {
+    values
+        .iter()
+        .zip(keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(&value, _)| value)
+        .collect()
+}
+
+/// Keeps the dense nodes for which `keep` returns true when called with the id
+/// of the node and whether the node has tags.
+fn retain_dense_nodes(dense: &mut osmpbf::DenseNodes, keep: impl Fn(i64, bool) -> bool) {
+    // tags of the nodes are key-value pairs terminated by 0; the list is empty
+    // if no node has tags
+    let mut tag_ranges = Vec::with_capacity(dense.id.len());
+    let mut offset = 0;
+    for _ in 0..dense.id.len() {
+        let start = offset;
+        while offset < dense.keys_vals.len() {
+            offset += 1;
+            if dense.keys_vals[offset - 1] == 0 {
+                break;
+            }
+            offset += 1;
+        }
+        tag_ranges.push(start..offset.min(dense.keys_vals.len()));
+    }
+    let keep: Vec<bool> = decode_deltas(&dense.id)
+        .into_iter()
+        .zip(&tag_ranges)
+        .map(|(id, range)| keep(id, range.len() > 1))
+        .collect();
+    if keep.iter().all(|&keep| keep) {
+        return;
+    }
+
+    dense.id = retain_deltas(&dense.id, &keep);
+    dense.lat = retain_deltas(&dense.lat, &keep);
+    dense.lon = retain_deltas(&dense.lon, &keep);
+    dense.keys_vals = tag_ranges
+        .into_iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .flat_map(|(range, _)| dense.keys_vals[range].to_vec())
+        .collect();
+
+    if let Some(info) = &mut dense.denseinfo {
+        info.version = retain_values(&info.version, &keep);
+        info.timestamp = retain_deltas(&info.timestamp, &keep);
+        info.changeset = retain_deltas(&info.changeset, &keep);
+        info.uid = retain_deltas(&info.uid, &keep);
+        info.user_sid = retain_deltas(&info.user_sid, &keep);
+        info.visible = retain_values(&info.visible, &keep);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn test_clip_adds_members_of_relations() {
+        let degrees = |d: f64| (d * 1e9) as i64;
+        let entities = vec![
+            node(1, degrees(0.5), degrees(0.5)),
+            node(2, degrees(2.0), degrees(2.0)),
+            node(3, degrees(3.0), degrees(3.0)),
+            node(4, degrees(4.0), degrees(4.0)),
+            node(5, degrees(5.0), degrees(5.0)),
+            node(6, degrees(6.0), degrees(6.0)),
+            way(10, &[1, 2]),
+            way(11, &[3, 4]),
+            way(12, &[5, 6]),
+            relation(
+                20,
+                &[
+                    (MemberType::Node, 1, "label"),
+                    (MemberType::Way, 11, "outer"),
+                    (MemberType::Node, 5, "admin_centre"),
+                ],
+            ),
+            relation(21, &[(MemberType::Way, 12, "outer")]),
+        ];
+        let (_dir, archive) = compile_entities(entities, &["--bbox", "0,0,1,1"]);
+
+        assert_eq!(node_ids(&archive), vec![1, 2, 3, 4, 5]);
+        assert_eq!(way_ids(&archive), vec![10, 11]);
+        assert_eq!(relation_ids(&archive), vec![20]);
+        for idx in 0..archive.ways().len() {
+            assert!(way_refs(&archive, idx).iter().all(Option::is_some));
+        }
+        let members = relation_members(&archive, 0);
+        assert!(members.iter().all(|(_, id, _)| id.is_some()));
+    }
+
+    #[test]
+    fn test_clip_does_not_complete_parent_relations() {
+        let degrees = |d: f64| (d * 1e9) as i64;
+        let entities = vec![
+            node(1, degrees(0.5), degrees(0.5)),
+            node(2, degrees(2.0), degrees(2.0)),
+            node(3, degrees(3.0), degrees(3.0)),
+            node(4, degrees(4.0), degrees(4.0)),
+            node(5, degrees(5.0), degrees(5.0)),
+            way(40, &[2, 3]),
+            way(41, &[4, 5]),
+            // a route stopping inside the box, and one outside of it
+            relation(
+                30,
+                &[(MemberType::Node, 1, "stop"), (MemberType::Way, 40, "")],
+            ),
+            relation(31, &[(MemberType::Way, 41, "")]),
+            // the route master of both routes
+            relation(
+                32,
+                &[
+                    (MemberType::Relation, 30, ""),
+                    (MemberType::Relation, 31, ""),
+                ],
+            ),
+        ];
+        let (_dir, archive) = compile_entities(entities, &["--bbox", "0,0,1,1"]);
+
+        assert_eq!(node_ids(&archive), vec![1, 2, 3]);
+        assert_eq!(way_ids(&archive), vec![40]);
+        assert_eq!(relation_ids(&archive), vec![30, 32]);
+        assert_eq!(
+            relation_members(&archive, 1),
+            vec![
+                (MemberType::Relation, Some(30), String::new()),
+                (MemberType::Relation, None, String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clip_large_ids() {
+        // the largest id stored in an archive
+        let id = (1 << 39) - 1;
+        let entities = vec![node(id, 1_000_000_000, 1_000_000_000), way(id, &[id])];
+        let (_dir, archive) = compile_entities(entities, &["--bbox", "0,0,2,2"]);
+        assert_eq!(node_ids(&archive), vec![id]);
+        assert_eq!(way_refs(&archive, 0), vec![Some(id)]);
+    }
+
+    #[test]
+    fn test_parse_bbox() {
+        assert_eq!(
+            "13.3,52.5,13.45,52.55".parse::<BBox>(),
+            Ok(BBox {
+                min_lon: 13_300_000_000,
+                min_lat: 52_500_000_000,
+                max_lon: 13_450_000_000,
+                max_lat: 52_550_000_000,
+            })
+        );
+        assert!("13.3,52.5,13.45".parse::<BBox>().is_err());
+        assert!("13.3,52.5,13.2,52.55".parse::<BBox>().is_err());
+        assert!("-181,0,0,1".parse::<BBox>().is_err());
+        assert!("a,b,c,d".parse::<BBox>().is_err());
+    }
+
+    #[test]
+    fn test_parse_tag_expression() {
+        assert_eq!(
+            "n/amenity=pub,bar".parse::<TagExpression>(),
+            Ok(TagExpression {
+                nodes: true,
+                ways: false,
+                relations: false,
+                key: "amenity".into(),
+                values: vec!["pub".into(), "bar".into()],
+                negated: false,
+            })
+        );
+        assert_eq!(
+            "wr/type!=route".parse::<TagExpression>(),
+            Ok(TagExpression {
+                nodes: false,
+                ways: true,
+                relations: true,
+                key: "type".into(),
+                values: vec!["route".into()],
+                negated: true,
+            })
+        );
+        let expression: TagExpression = "highway".parse().unwrap();
+        assert!(expression.nodes && expression.ways && expression.relations);
+        assert!(expression.values.is_empty());
+        assert!("n/".parse::<TagExpression>().is_err());
+        assert!("n/amenity=".parse::<TagExpression>().is_err());
+        assert!("n/amenity=pub,".parse::<TagExpression>().is_err());
+    }
+
+    #[test]
+    fn test_match_dense_node_tags() {
+        let block = osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable {
+                s: ["", "amenity", "pub", "cafe", "name"]
+                    .iter()
+                    .map(|s| s.as_bytes().to_vec())
+                    .collect(),
+            },
+            primitivegroup: vec![osmpbf::PrimitiveGroup {
+                dense: Some(osmpbf::DenseNodes {
+                    id: vec![1, 1, 1],
+                    lat: vec![0, 0, 0],
+                    lon: vec![0, 0, 0],
+                    keys_vals: vec![4, 3, 1, 3, 0, 0, 4, 2, 1, 2, 0],
+                    denseinfo: None,
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let select = |expressions: &[&str]| {
+            let expressions: Vec<TagExpression> =
+                expressions.iter().map(|e| e.parse().unwrap()).collect();
+            let mut ids = Vec::new();
+            for_each_node_with_tags(&block, |id, tags| {
+                if matches(&expressions, MemberType::Node, tags) {
+                    ids.push(id);
+                }
+            });
+            ids
+        };
+        assert_eq!(select(&["n/amenity=pub"]), vec![3]);
+        assert_eq!(select(&["amenity"]), vec![1, 3]);
+        assert_eq!(select(&["n/amenity!=pub"]), vec![1]);
+        assert_eq!(select(&["w/amenity", "n/name=pub"]), vec![3]);
+    }
+
+    #[test]
+    fn test_id_set() {
+        let sparse = [1 << 50, i64::MAX, i64::MIN, -5];
+        let mut ids = IdSet::default();
+        for &id in [0, 63, 64, 65_536].iter().chain(&sparse) {
+            ids.insert(id);
+        }
+        assert!(!ids.insert(64));
+        assert_eq!(ids.len(), 8);
+        assert!(ids.contains(0) && ids.contains(63) && ids.contains(64));
+        assert!(sparse.iter().all(|&id| ids.contains(id)));
+        assert!(!ids.contains(1) && !ids.contains(65) && !ids.contains(-1));
+        assert!(!ids.contains(i64::MAX - 1) && !ids.contains((1 << 50) + 1));
+        // only the pages containing ids are allocated
+        assert_eq!(ids.pages.len(), 6);
+
+        let mut other = IdSet::default();
+        for &id in &[1, 64, -1] {
+            other.insert(id);
+        }
+        other.union_with(&ids);
+        assert_eq!(other.len(), 10);
+        assert!(other.contains(1) && other.contains(-1));
+        assert!(sparse.iter().all(|&id| other.contains(id)));
+        assert_eq!(other.pages.len(), 6);
+    }
+
+    #[test]
+    fn test_retain_untagged_dense_nodes() {
+        let mut dense = osmpbf::DenseNodes {
+            id: vec![1, 1, 1],
+            lat: vec![0, 0, 0],
+            lon: vec![0, 0, 0],
+            keys_vals: vec![1, 2, 0, 0, 3, 4, 0],
+            denseinfo: None,
+        };
+        retain_dense_nodes(&mut dense, |_, has_tags| !has_tags);
+        assert_eq!(dense.id, vec![2]);
+        assert_eq!(dense.keys_vals, vec![0]);
+    }
+
+    #[test]
+    fn test_retain_dense_nodes() {
+        let mut dense = osmpbf::DenseNodes {
+            id: vec![1, 1, 1, 1],
+            lat: vec![10, 10, -5, 20],
+            lon: vec![0, 1, 1, 1],
+            keys_vals: vec![1, 2, 0, 3, 4, 0, 0, 5, 6, 0],
+            denseinfo: Some(osmpbf::DenseInfo {
+                version: vec![1, 2, 3, 4],
+                timestamp: vec![100, 10, 10, 10],
+                ..Default::default()
+            }),
+        };
+        retain_dense_nodes(&mut dense, |id, _| id % 2 == 0);
+        assert_eq!(dense.id, vec![2, 2]);
+        assert_eq!(dense.lat, vec![20, 15]);
+        assert_eq!(dense.lon, vec![1, 2]);
+        assert_eq!(dense.keys_vals, vec![3, 4, 0, 5, 6, 0]);
+        let info = dense.denseinfo.unwrap();
+        assert_eq!(info.version, vec![2, 4]);
+        assert_eq!(info.timestamp, vec![110, 20]);
+    }
+}
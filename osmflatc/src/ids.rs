@@ -1,21 +1,40 @@
 use rayon::prelude::*;
 
-/// Maps u64 integers to a consecutive range of ids
-#[derive(Debug)]
+use std::collections::HashMap;
+use std::fmt;
+
+/// Maps i64 ids to a consecutive range of indices
+#[derive(Debug, Default)]
 pub struct IdTable {
-    // map u64 id x to u32 by storing a sorted mapping table for each value of x / 2^24
-    // each mapping entry (u64) represents (u24) id set (x % 2^24), and mapped id (u40)
-    data: Vec<Vec<u64>>,
-    num_ids: u64,
+    // map i64 id x to an index by storing a sorted mapping table for each value of
+    // x / 2^24; each mapping entry (u64) represents (u24) id set (x % 2^24), and
+    // mapped index (u40). The id sets are sorted by their key, so that sparse and
+    // negative ids need only as many tables as there are distinct id sets.
+    data: Vec<(i64, Vec<u64>)>,
 }
 
 #[derive(Debug, Default)]
 pub struct IdTableBuilder {
     // stored the same data as IdTable, but not yet sorted
-    data: Vec<Vec<u64>>,
+    data: HashMap<i64, Vec<u64>>,
     next_id: u64,
+    // if set, consecutive versions of an id are mapped to the first one
+    with_versions: bool,
+    last_id: Option<i64>,
 }
 
+/// Error of an id which was inserted several times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateId(pub i64);
+
+impl fmt::Display for DuplicateId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "id {} occurs several times", self.0)
+    }
+}
+
+impl std::error::Error for DuplicateId {}
+
 // pack index compactly in 8 bytes: supports 1 trillion indices
 fn pack_index(x: (u32, u64)) -> u64 {
     assert!(x.0 < (1_u32 << 24));
@@ -27,45 +46,94 @@ fn unpack_packed_index(x: u64) -> (u32, u64) {
     ((x >> 40) as u32, x % (1_u64 << 40))
 }
 
+// split an id into its id set and its position in the set; the shift rounds
+// towards negative infinity, so the position is never negative
+fn split_id(x: i64) -> (i64, u32) {
+    (x >> 24, (x & ((1 << 24) - 1)) as u32)
+}
+
 impl IdTableBuilder {
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Creates a builder for ids with several versions, as in history files.
+    ///
+    /// Versions of the same id are inserted consecutively and get consecutive
+    /// indices, but only the index of the first version is mapped.
+    pub fn with_versions() -> Self {
+        Self {
+            with_versions: true,
+            ..Default::default()
+        }
+    }
+
     /// Inserts an Id and returns a mapped index
-    pub fn insert(&mut self, x: u64) -> u64 {
-        let id_set = (x >> 24) as usize;
-        if self.data.len() <= id_set {
-            self.data.resize(id_set + 1, Vec::new());
+    pub fn insert(&mut self, x: i64) -> u64 {
+        if self.with_versions && self.last_id.replace(x) == Some(x) {
+            let result = self.next_id;
+            self.next_id += 1;
+            return result;
         }
-        self.data[id_set].push(pack_index(((x % (1u64 << 24)) as u32, self.next_id)));
+        let (id_set, pos) = split_id(x);
+        self.data
+            .entry(id_set)
+            .or_default()
+            .push(pack_index((pos, self.next_id)));
         let result = self.next_id;
         self.next_id += 1;
         result
     }
 
-    pub fn build(mut self) -> IdTable {
-        self.data.par_iter_mut().for_each(|x| x.par_sort_unstable());
+    /// Builds the table.
+    ///
+    /// Fails if an id was inserted several times, except for consecutive
+    /// versions in a builder with versions.
+    pub fn build(self) -> Result<IdTable, DuplicateId> {
+        let mut data: Vec<_> = self.data.into_iter().collect();
+        data.par_iter_mut().for_each(|(_, x)| x.par_sort_unstable());
+        data.sort_unstable_by_key(|(id_set, _)| *id_set);
 
-        IdTable {
-            data: self.data,
-            num_ids: self.next_id,
+        // entries are sorted by their position, so that duplicates are adjacent
+        let duplicate = data.par_iter().find_map_first(|(id_set, x)| {
+            x.windows(2)
+                .map(|pair| {
+                    (
+                        unpack_packed_index(pair[0]).0,
+                        unpack_packed_index(pair[1]).0,
+                    )
+                })
+                .find(|(pos, next_pos)| pos == next_pos)
+                .map(|(pos, _)| DuplicateId((id_set << 24) | i64::from(pos)))
+        });
+        match duplicate {
+            Some(duplicate) => Err(duplicate),
+            None => Ok(IdTable { data }),
         }
     }
 }
 
 impl IdTable {
-    pub fn get(&self, x: u64) -> Option<u64> {
-        let id_set = (x >> 24) as usize;
-        if id_set > self.data.len() {
-            return None;
-        }
-        self.data[id_set]
-            .binary_search_by_key(&((x % (1u64 << 24)) as u32), |item| {
-                unpack_packed_index(*item).0
-            })
+    pub fn get(&self, x: i64) -> Option<u64> {
+        let (id_set, pos) = split_id(x);
+        let set_pos = self
+            .data
+            .binary_search_by_key(&id_set, |(id_set, _)| *id_set)
+            .ok()?;
+        let data = &self.data[set_pos].1;
+        data.binary_search_by_key(&pos, |item| unpack_packed_index(*item).0)
             .ok()
-            .map(|pos| unpack_packed_index(self.data[id_set][pos]).1)
+            .map(|pos| unpack_packed_index(data[pos]).1)
+    }
+
+    /// Iterates over all mapped ids and their indices, sorted by id.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, u64)> + '_ {
+        self.data.iter().flat_map(|(id_set, data)| {
+            data.iter().map(move |item| {
+                let (pos, idx) = unpack_packed_index(*item);
+                ((id_set << 24) | i64::from(pos), idx)
+            })
+        })
     }
 }
 
@@ -81,33 +149,125 @@ mod test {
             builder.insert(*x);
         }
 
-        let lookup = builder.build();
+        let lookup = builder.build().unwrap();
         for (pos, x) in data.iter().enumerate() {
             let res = lookup.get(*x);
             assert_eq!(res, Some(pos as u64));
         }
 
-        for x in [0, 1, 2, 5, 6, 11, 12, 14].iter() {
+        for x in [0, 1, 2, 5, 6, 11, 12, 14, 1 << 24, 1 << 25].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
+        assert_eq!(IdTable::default().get(0), None);
+    }
+
+    #[test]
+    fn test_mapping_of_versions() {
+        let mut builder = IdTableBuilder::with_versions();
+        let indices: Vec<_> = [3, 3, 3, 5, 7, 7]
+            .iter()
+            .map(|x| builder.insert(*x))
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+
+        let lookup = builder.build().unwrap();
+        assert_eq!(lookup.get(3), Some(0));
+        assert_eq!(lookup.get(5), Some(3));
+        assert_eq!(lookup.get(7), Some(4));
+        assert_eq!(lookup.get(4), None);
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let mut builder = IdTableBuilder::new();
+        for x in [3, -(1 << 30), 5, -(1 << 30)].iter() {
+            builder.insert(*x);
+        }
+        assert_eq!(builder.build().unwrap_err(), DuplicateId(-(1 << 30)));
+
+        // versions must be consecutive
+        let mut builder = IdTableBuilder::with_versions();
+        for x in [3, 3, 5, 3].iter() {
+            builder.insert(*x);
+        }
+        assert_eq!(builder.build().unwrap_err(), DuplicateId(3));
     }
 
     #[test]
     fn test_mapping_of_large_ints() {
         let mut builder = IdTableBuilder::new();
-        let data = [2, 1, 1_u64 << 33, 1_u64 << 34];
+        let data = [2, 1, 1_i64 << 33, 1_i64 << 34];
+        for x in data.iter() {
+            builder.insert(*x);
+        }
+
+        let lookup = builder.build().unwrap();
+        for (pos, x) in data.iter().enumerate() {
+            let res = lookup.get(*x);
+            assert_eq!(res, Some(pos as u64));
+        }
+
+        for x in [0, 3, (1_i64 << 33) + 1, (1_i64 << 34) + 1, 1_i64 << 35].iter() {
+            let res = lookup.get(*x);
+            assert_eq!(res, None);
+        }
+    }
+
+    #[test]
+    fn test_mapping_of_negative_ints() {
+        let mut builder = IdTableBuilder::new();
+        let data = [
+            -1,
+            -2,
+            5,
+            -(1_i64 << 24),
+            -(1_i64 << 24) - 1,
+            i64::MIN,
+            i64::MAX,
+        ];
         for x in data.iter() {
             builder.insert(*x);
         }
 
-        let lookup = builder.build();
+        let lookup = builder.build().unwrap();
         for (pos, x) in data.iter().enumerate() {
             let res = lookup.get(*x);
             assert_eq!(res, Some(pos as u64));
         }
 
-        for x in [0, 3, (1_u64 << 33) + 1, (1_u64 << 34) + 1, 1_u64 << 35].iter() {
+        for x in [
+            0,
+            -3,
+            1,
+            (1_i64 << 24) - 1,
+            1 - (1_i64 << 24),
+            i64::MIN + 1,
+            i64::MAX - 1,
+        ]
+        .iter()
+        {
+            let res = lookup.get(*x);
+            assert_eq!(res, None);
+        }
+    }
+
+    #[test]
+    fn test_mapping_of_sparse_ints() {
+        let mut builder = IdTableBuilder::new();
+        let data = [1_i64 << 62, 7, -(1_i64 << 50), 1_i64 << 40];
+        for x in data.iter() {
+            builder.insert(*x);
+        }
+
+        let lookup = builder.build().unwrap();
+        assert_eq!(lookup.data.len(), 4);
+        for (pos, x) in data.iter().enumerate() {
+            let res = lookup.get(*x);
+            assert_eq!(res, Some(pos as u64));
+        }
+
+        for x in [(1_i64 << 62) + 1, 8, -(1_i64 << 50) + 1, (1_i64 << 40) - 1].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
@@ -117,20 +277,33 @@ mod test {
     fn test_large_indices() {
         let mut builder = IdTableBuilder::new();
         builder.next_id += 1u64 << 33;
-        let data = [2, 1, 1_u64 << 33, 1_u64 << 34];
+        let data = [2, 1, 1_i64 << 33, 1_i64 << 34];
         for x in data.iter() {
             builder.insert(*x);
         }
 
-        let lookup = builder.build();
+        let lookup = builder.build().unwrap();
         for (pos, x) in data.iter().enumerate() {
             let res = lookup.get(*x);
             assert_eq!(res, Some((pos as u64) + (1u64 << 33)));
         }
 
-        for x in [0, 3, (1_u64 << 33) + 1, (1_u64 << 34) + 1, 1_u64 << 35].iter() {
+        for x in [0, 3, (1_i64 << 33) + 1, (1_i64 << 34) + 1, 1_i64 << 35].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
     }
+
+    #[test]
+    fn test_iter_sorted_by_id() {
+        let mut builder = IdTableBuilder::with_versions();
+        for x in [9, 9, -3, 1_i64 << 40, 4, i64::MIN].iter() {
+            builder.insert(*x);
+        }
+        let lookup = builder.build().unwrap();
+        assert_eq!(
+            lookup.iter().collect::<Vec<_>>(),
+            vec![(i64::MIN, 5), (-3, 2), (4, 4), (9, 0), (1_i64 << 40, 3)]
+        );
+    }
 }
@@ -0,0 +1,56 @@
+use crate::args::InputFormat;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Determines the format of an input file from its extension.
+///
+/// Extensions of compressed files (`.gz`, `.bz2`) are ignored. Files with an
+/// unknown extension are assumed to be PBF.
+pub fn detect_format(path: &Path) -> InputFormat {
+    let mut path = path.to_path_buf();
+    while let Some("gz") | Some("bz2") = path.extension().and_then(|ext| ext.to_str()) {
+        path.set_extension("");
+    }
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("osm") | Some("osh") | Some("xml") => InputFormat::Xml,
+        Some("o5m") => InputFormat::O5m,
+        _ => InputFormat::Pbf,
+    }
+}
+
+/// Opens a file and transparently decompresses gzip and bzip2 compressed
+/// data.
+///
+/// The compression is detected from the magic bytes at the start of the file.
+pub fn open_decompressed(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let magic = reader.fill_buf()?;
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if magic.starts_with(b"BZh") {
+        Box::new(BufReader::new(MultiBzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let format = |path: &str| detect_format(Path::new(path));
+        assert_eq!(format("berlin.osm.pbf"), InputFormat::Pbf);
+        assert_eq!(format("berlin.osm"), InputFormat::Xml);
+        assert_eq!(format("berlin.osh.gz"), InputFormat::Xml);
+        assert_eq!(format("berlin.xml.bz2"), InputFormat::Xml);
+        assert_eq!(format("berlin.o5m"), InputFormat::O5m);
+        assert_eq!(format("berlin"), InputFormat::Pbf);
+    }
+}
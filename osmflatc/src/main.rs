@@ -1,25 +1,47 @@
 mod args;
+mod changes;
+mod entities;
+mod external_sort;
+mod filter;
 mod ids;
+mod input;
+mod o5m;
 mod osmpbf;
+mod osmxml;
 mod parallel;
+mod pbfwriter;
+mod polygon;
+mod reorder;
+mod replication;
+mod report;
+mod spatial_index;
 mod stats;
 mod strings;
-
+mod tag_index;
+#[cfg(test)]
+mod testing;
+
+use crate::args::{Command, CompileOptions, Dangling, InputFormat, TagDedup};
+use crate::external_sort::ExternalSorter;
+use crate::filter::{BlockReader, IdSet, Region, Selection};
+use crate::osmpbf::relation::MemberType;
 use crate::osmpbf::{build_block_index, read_block, BlockIndex, BlockType};
+use crate::report::UnresolvedReport;
 use crate::stats::Stats;
 use crate::strings::StringTable;
 
 use colored::*;
 use flatdata::FileResourceStorage;
 use itertools::Itertools;
-use log::info;
+use log::{info, warn};
+use lru::LruCache;
 use memmap::Mmap;
 use pbr::ProgressBar;
-use structopt::StructOpt;
 
-use std::collections::{hash_map, HashMap};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io;
+use std::io::{self, BufWriter};
+use std::path::Path;
 use std::str;
 
 type Error = Box<dyn std::error::Error>;
@@ -41,22 +63,22 @@ fn serialize_header(
     header.set_required_feature_first_idx(stringtable.next_index());
     header.set_required_features_size(header_block.required_features.len() as u32);
     for feature in &header_block.required_features {
-        stringtable.insert(feature);
+        stringtable.insert(feature)?;
     }
 
     header.set_optional_feature_first_idx(stringtable.next_index());
     header.set_optional_features_size(header_block.optional_features.len() as u32);
     for feature in &header_block.optional_features {
-        stringtable.insert(feature);
+        stringtable.insert(feature)?;
     }
 
     if let Some(ref writingprogram) = header_block.writingprogram {
         // TODO: Should we also add our name here?
-        header.set_writingprogram_idx(stringtable.insert(writingprogram));
+        header.set_writingprogram_idx(stringtable.insert(writingprogram)?);
     }
 
     if let Some(ref source) = header_block.source {
-        header.set_source_idx(stringtable.insert(source));
+        header.set_source_idx(stringtable.insert(source)?);
     }
 
     if let Some(timestamp) = header_block.osmosis_replication_timestamp {
@@ -68,38 +90,64 @@ fn serialize_header(
     }
 
     if let Some(ref url) = header_block.osmosis_replication_base_url {
-        header.set_osmosis_replication_base_url_idx(stringtable.insert(url));
+        header.set_osmosis_replication_base_url_idx(stringtable.insert(url)?);
     }
 
     builder.set_header(&header)?;
     Ok(())
 }
 
+/// Deduplication table of tags: (key_idx, val_idx) -> pos
+enum TagDedupTable {
+    All(HashMap<(u64, u64), u64>),
+    Lru(LruCache<(u64, u64), u64>),
+    None,
+}
+
 /// Holds tags external vector and deduplicates tags.
 struct TagSerializer<'a> {
     tags: flatdata::ExternalVector<'a, osmflat::Tag>,
     tags_index: flatdata::ExternalVector<'a, osmflat::TagIndex>,
-    dedup: HashMap<(u64, u64), u64>, // deduplication table: (key_idx, val_idx) -> pos
+    dedup: TagDedupTable,
 }
 
 impl<'a> TagSerializer<'a> {
-    fn new(builder: &'a osmflat::OsmBuilder) -> io::Result<Self> {
+    fn new(builder: &'a osmflat::OsmBuilder, options: &CompileOptions) -> io::Result<Self> {
+        let dedup = match options.tag_dedup {
+            TagDedup::All => TagDedupTable::All(HashMap::new()),
+            TagDedup::Lru => TagDedupTable::Lru(LruCache::new(options.tag_dedup_capacity)),
+            TagDedup::None => TagDedupTable::None,
+        };
         Ok(Self {
             tags: builder.start_tags()?,
             tags_index: builder.start_tags_index()?,
-            dedup: HashMap::new(),
+            dedup,
         })
     }
 
     fn serialize(&mut self, key_idx: u64, val_idx: u64) -> Result<(), Error> {
-        let idx = match self.dedup.entry((key_idx, val_idx)) {
-            hash_map::Entry::Occupied(entry) => *entry.get(),
-            hash_map::Entry::Vacant(entry) => {
+        let key = (key_idx, val_idx);
+        let deduplicated = match &mut self.dedup {
+            TagDedupTable::All(table) => table.get(&key).copied(),
+            TagDedupTable::Lru(table) => table.get(&key).copied(),
+            TagDedupTable::None => None,
+        };
+        let idx = match deduplicated {
+            Some(idx) => idx,
+            None => {
                 let idx = self.tags.len() as u64;
                 let tag = self.tags.grow()?;
                 tag.set_key_idx(key_idx);
                 tag.set_value_idx(val_idx);
-                entry.insert(idx);
+                match &mut self.dedup {
+                    TagDedupTable::All(table) => {
+                        table.insert(key, idx);
+                    }
+                    TagDedupTable::Lru(table) => {
+                        table.put(key, idx);
+                    }
+                    TagDedupTable::None => (),
+                }
                 idx
             }
         };
@@ -110,6 +158,11 @@ impl<'a> TagSerializer<'a> {
         Ok(())
     }
 
+    /// Number of tags written to the tags vector
+    fn num_tags(&self) -> usize {
+        self.tags.len()
+    }
+
     fn next_index(&self) -> u64 {
         self.tags_index.len() as u64
     }
@@ -132,68 +185,274 @@ fn add_string_table(
 ) -> Result<Vec<u64>, Error> {
     let mut result = Vec::with_capacity(pbf_stringtable.s.len());
     for x in &pbf_stringtable.s {
-        let string = str::from_utf8(&x)?;
-        result.push(stringtable.insert(string));
+        let string = str::from_utf8(x)?;
+        result.push(stringtable.insert(string)?);
     }
     Ok(result)
 }
 
-fn serialize_dense_nodes(
+/// Serializes the metadata of entities, and the validity of their versions in
+/// history archives.
+struct InfoSerializer<'a> {
+    infos: flatdata::ExternalVector<'a, osmflat::Info>,
+    validity: Option<flatdata::ExternalVector<'a, osmflat::Validity>>,
+    /// `(id, valid_from, visible)` of the last version, whose validity ends
+    /// with the next version of the same entity
+    pending_version: Option<(i64, i64, bool)>,
+}
+
+impl<'a> InfoSerializer<'a> {
+    fn new(
+        infos: flatdata::ExternalVector<'a, osmflat::Info>,
+        validity: Option<flatdata::ExternalVector<'a, osmflat::Validity>>,
+    ) -> Self {
+        Self {
+            infos,
+            validity,
+            pending_version: None,
+        }
+    }
+
+    /// Serializes the metadata of the entity `id`.
+    ///
+    /// Entities without metadata get an empty info, which keeps the info
+    /// vector parallel to the entities.
+    fn serialize(
+        &mut self,
+        block: &osmpbf::PrimitiveBlock,
+        id: i64,
+        pbf_info: Option<&osmpbf::Info>,
+        string_refs: &[u64],
+    ) -> Result<(), Error> {
+        let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+        let timestamp =
+            pbf_info.and_then(|info| info.timestamp).unwrap_or(0) * date_granularity / 1000;
+        let info = self.infos.grow()?;
+        info.set_timestamp(timestamp);
+        if let Some(pbf_info) = pbf_info {
+            info.set_version(pbf_info.version.unwrap_or(0));
+            info.set_changeset(pbf_info.changeset.unwrap_or(0));
+            info.set_uid(pbf_info.uid.unwrap_or(0));
+            // string 0 is the empty string, which denotes an unknown user
+            info.set_user_idx(
+                pbf_info
+                    .user_sid
+                    .filter(|&sid| sid != 0)
+                    .map(|sid| string_refs[sid as usize]),
+            );
+        } else {
+            info.set_user_idx(None);
+        }
+
+        if let Some(validity) = &mut self.validity {
+            let visible = pbf_info.and_then(|info| info.visible).unwrap_or(true);
+            if let Some(previous) = self.pending_version.replace((id, timestamp, visible)) {
+                let valid_to = Some(timestamp).filter(|_| previous.0 == id);
+                serialize_validity(validity, previous, valid_to)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(self) -> Result<(), Error> {
+        self.infos.close()?;
+        if let Some(mut validity) = self.validity {
+            if let Some(previous) = self.pending_version {
+                serialize_validity(&mut validity, previous, None)?;
+            }
+            validity.close()?;
+        }
+        Ok(())
+    }
+}
+
+fn serialize_validity(
+    validity: &mut flatdata::ExternalVector<osmflat::Validity>,
+    (_, valid_from, visible): (i64, i64, bool),
+    valid_to: Option<i64>,
+) -> io::Result<()> {
+    let validity = validity.grow()?;
+    validity.set_valid_from(valid_from);
+    validity.set_valid_to(valid_to);
+    validity.set_visible(visible);
+    Ok(())
+}
+
+/// Decodes the metadata of the dense node `i` given the metadata of the
+/// previous node, since all fields except the version are delta coded.
+fn decode_dense_info(
+    dense_info: &osmpbf::DenseInfo,
+    i: usize,
+    previous: &osmpbf::Info,
+) -> osmpbf::Info {
+    let delta = |values: &[i64], previous: Option<i64>| {
+        values.get(i).map(|value| previous.unwrap_or(0) + value)
+    };
+    let delta32 = |values: &[i32], previous: Option<i64>| {
+        values
+            .get(i)
+            .map(|&value| previous.unwrap_or(0) + i64::from(value))
+    };
+    osmpbf::Info {
+        version: dense_info.version.get(i).copied(),
+        timestamp: delta(&dense_info.timestamp, previous.timestamp),
+        changeset: delta(&dense_info.changeset, previous.changeset),
+        uid: delta32(&dense_info.uid, previous.uid.map(i64::from)).map(|uid| uid as i32),
+        user_sid: delta32(&dense_info.user_sid, previous.user_sid.map(i64::from))
+            .map(|sid| sid as u32),
+        visible: dense_info.visible.get(i).copied(),
+    }
+}
+
+/// Serializes all nodes of a block, in the order of its primitive groups.
+///
+/// A block may contain dense and non-dense groups, and even groups of ways
+/// and relations, which are skipped here.
+fn serialize_node_block(
     block: &osmpbf::PrimitiveBlock,
     nodes: &mut flatdata::ExternalVector<osmflat::Node>,
     nodes_id_to_idx: &mut ids::IdTableBuilder,
     stringtable: &mut StringTable,
     tags: &mut TagSerializer,
+    infos: &mut Option<InfoSerializer<'_>>,
 ) -> Result<Stats, Error> {
     let mut stats = Stats::default();
     let string_refs = add_string_table(&block.stringtable, stringtable)?;
-    for group in block.primitivegroup.iter() {
-        let dense_nodes = group.dense.as_ref().unwrap();
+    for group in &block.primitivegroup {
+        if let Some(dense_nodes) = &group.dense {
+            stats += serialize_dense_nodes(
+                block,
+                dense_nodes,
+                &string_refs,
+                nodes,
+                nodes_id_to_idx,
+                tags,
+                infos,
+            )?;
+        }
+        stats += serialize_nodes(
+            block,
+            &group.nodes,
+            &string_refs,
+            nodes,
+            nodes_id_to_idx,
+            tags,
+            infos,
+        )?;
+    }
+    Ok(stats)
+}
+
+fn serialize_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    pbf_nodes: &[osmpbf::Node],
+    string_refs: &[u64],
+    nodes: &mut flatdata::ExternalVector<osmflat::Node>,
+    nodes_id_to_idx: &mut ids::IdTableBuilder,
+    tags: &mut TagSerializer,
+    infos: &mut Option<InfoSerializer<'_>>,
+) -> Result<Stats, Error> {
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    for pbf_node in pbf_nodes {
+        let index = nodes_id_to_idx.insert(pbf_node.id);
+        assert_eq!(index as usize, nodes.len());
+
+        let node = nodes.grow()?;
+        node.set_id(pbf_node.id);
+        node.set_lat(lat_offset + granularity * pbf_node.lat);
+        node.set_lon(lon_offset + granularity * pbf_node.lon);
+
+        debug_assert_eq!(
+            pbf_node.keys.len(),
+            pbf_node.vals.len(),
+            "invalid input data"
+        );
+        node.set_tag_first_idx(tags.next_index());
+        for i in 0..pbf_node.keys.len() {
+            tags.serialize(
+                string_refs[pbf_node.keys[i] as usize],
+                string_refs[pbf_node.vals[i] as usize],
+            )?;
+        }
 
-        let granularity = block.granularity.unwrap_or(100);
-        let lat_offset = block.lat_offset.unwrap_or(0);
-        let lon_offset = block.lon_offset.unwrap_or(0);
-        let mut lat = 0;
-        let mut lon = 0;
+        if let Some(infos) = infos {
+            infos.serialize(block, pbf_node.id, pbf_node.info.as_ref(), string_refs)?;
+        }
+    }
+    Ok(Stats {
+        num_nodes: pbf_nodes.len(),
+        ..Default::default()
+    })
+}
 
-        let mut tags_offset = 0;
+fn serialize_dense_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    dense_nodes: &osmpbf::DenseNodes,
+    string_refs: &[u64],
+    nodes: &mut flatdata::ExternalVector<osmflat::Node>,
+    nodes_id_to_idx: &mut ids::IdTableBuilder,
+    tags: &mut TagSerializer,
+    infos: &mut Option<InfoSerializer<'_>>,
+) -> Result<Stats, Error> {
+    let granularity = block.granularity.unwrap_or(100);
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    let mut lat = 0;
+    let mut lon = 0;
 
-        let mut id = 0;
-        for i in 0..dense_nodes.id.len() {
-            id += dense_nodes.id[i];
+    let mut tags_offset = 0;
 
-            let index = nodes_id_to_idx.insert(id as u64);
-            assert_eq!(index as usize, nodes.len());
+    let mut info = osmpbf::Info::default();
 
-            let node = nodes.grow()?;
-            node.set_id(id);
+    let mut id = 0;
+    for i in 0..dense_nodes.id.len() {
+        id += dense_nodes.id[i];
 
-            lat += dense_nodes.lat[i];
-            lon += dense_nodes.lon[i];
-            node.set_lat(lat_offset + (i64::from(granularity) * lat));
-            node.set_lon(lon_offset + (i64::from(granularity) * lon));
+        let index = nodes_id_to_idx.insert(id);
+        assert_eq!(index as usize, nodes.len());
 
-            if tags_offset < dense_nodes.keys_vals.len() {
-                node.set_tag_first_idx(tags.next_index());
-                loop {
-                    let k = dense_nodes.keys_vals[tags_offset];
-                    tags_offset += 1;
+        let node = nodes.grow()?;
+        node.set_id(id);
 
-                    if k == 0 {
-                        break; // separator
-                    }
+        lat += dense_nodes.lat[i];
+        lon += dense_nodes.lon[i];
+        node.set_lat(lat_offset + (i64::from(granularity) * lat));
+        node.set_lon(lon_offset + (i64::from(granularity) * lon));
 
-                    let v = dense_nodes.keys_vals[tags_offset];
-                    tags_offset += 1;
+        // always set the first tag index, since the tag range of the previous node
+        // ends here, even if this block does not contain any tags
+        node.set_tag_first_idx(tags.next_index());
+        if tags_offset < dense_nodes.keys_vals.len() {
+            loop {
+                let k = dense_nodes.keys_vals[tags_offset];
+                tags_offset += 1;
 
-                    tags.serialize(string_refs[k as usize], string_refs[v as usize])?;
+                if k == 0 {
+                    break; // separator
                 }
+
+                let v = dense_nodes.keys_vals[tags_offset];
+                tags_offset += 1;
+
+                tags.serialize(string_refs[k as usize], string_refs[v as usize])?;
             }
         }
-        assert_eq!(tags_offset, dense_nodes.keys_vals.len());
-        stats.num_nodes += dense_nodes.id.len();
+
+        if let Some(infos) = infos {
+            let pbf_info = dense_nodes.denseinfo.as_ref().map(|dense_info| {
+                info = decode_dense_info(dense_info, i, &info);
+                &info
+            });
+            infos.serialize(block, id, pbf_info, string_refs)?;
+        }
     }
-    Ok(stats)
+    assert_eq!(tags_offset, dense_nodes.keys_vals.len());
+    Ok(Stats {
+        num_nodes: dense_nodes.id.len(),
+        ..Default::default()
+    })
 }
 
 fn resolve_ways(
@@ -207,7 +466,7 @@ fn resolve_ways(
             let mut node_ref = 0;
             for delta in &pbf_way.refs {
                 node_ref += delta;
-                let idx = nodes_id_to_idx.get(node_ref as u64);
+                let idx = nodes_id_to_idx.get(node_ref);
                 stats.num_unresolved_node_ids += idx.is_none() as usize;
 
                 result.push(idx);
@@ -217,6 +476,7 @@ fn resolve_ways(
     (result, stats)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn serialize_ways(
     block: &osmpbf::PrimitiveBlock,
     nodes_id_to_idx: &[Option<u64>],
@@ -225,13 +485,41 @@ fn serialize_ways(
     stringtable: &mut StringTable,
     tags: &mut TagSerializer,
     nodes_index: &mut flatdata::ExternalVector<osmflat::NodeIndex>,
+    infos: &mut Option<InfoSerializer<'_>>,
+    report: &mut Option<UnresolvedReport>,
+    dangling: Dangling,
+    dropped_ways: &mut IdSet,
+    node_ways: &mut Option<NodeWaysSorter>,
 ) -> Result<Stats, Error> {
     let mut stats = Stats::default();
     let string_refs = add_string_table(&block.stringtable, stringtable)?;
-    let mut nodes_idx = nodes_id_to_idx.iter().cloned();
+    let mut refs_offset = 0;
     for group in &block.primitivegroup {
         for pbf_way in &group.ways {
-            let index = ways_id_to_idx.insert(pbf_way.id as u64);
+            let refs = &nodes_id_to_idx[refs_offset..refs_offset + pbf_way.refs.len()];
+            refs_offset += pbf_way.refs.len();
+            if let Some(report) = report {
+                let mut node_ref = 0;
+                for (delta, idx) in pbf_way.refs.iter().zip(refs) {
+                    node_ref += delta;
+                    if idx.is_none() {
+                        report.write(
+                            MemberType::Way,
+                            pbf_way.id,
+                            MemberType::Node,
+                            node_ref,
+                            b"",
+                        )?;
+                    }
+                }
+            }
+            if dangling == Dangling::DropEntity && refs.iter().any(Option::is_none) {
+                stats.num_dropped_ways += 1;
+                dropped_ways.insert(pbf_way.id);
+                continue;
+            }
+
+            let index = ways_id_to_idx.insert(pbf_way.id);
             assert_eq!(index as usize, ways.len());
 
             let way = ways.grow()?;
@@ -248,29 +536,296 @@ fn serialize_ways(
             }
 
             way.set_ref_first_idx(nodes_index.len() as u64);
-            for _ in &pbf_way.refs {
-                nodes_index.grow()?.set_value(nodes_idx.next().unwrap());
+            for &idx in refs {
+                if idx.is_some() || dangling == Dangling::Keep {
+                    nodes_index.grow()?.set_value(idx);
+                }
+            }
+            if let Some(node_ways) = node_ways {
+                for &node_idx in refs.iter().flatten() {
+                    node_ways.push((node_idx, index))?;
+                }
+            }
+
+            if let Some(infos) = infos {
+                infos.serialize(block, pbf_way.id, pbf_way.info.as_ref(), &string_refs)?;
             }
+            stats.num_ways += 1;
         }
-        stats.num_ways += group.ways.len();
     }
     Ok(stats)
 }
 
-fn build_relations_index<I>(data: &[u8], block_index: I) -> Result<ids::IdTable, Error>
+/// Creates a temporary file next to `output`.
+///
+/// Temporary files of the size of the input are not created in the system's
+/// temporary directory, which is often small.
+fn temp_file_next_to(output: &Path) -> io::Result<File> {
+    let dir = output
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    tempfile::tempfile_in(dir)
+}
+
+/// Error for an entity found several times in the input.
+///
+/// Without `--history`, this is usually caused by a history file, whose format
+/// does not mark it as such (e.g. XML or o5m).
+fn duplicate_id_error(
+    entity_type: &str,
+    ids::DuplicateId(id): ids::DuplicateId,
+    history: bool,
+) -> Error {
+    if history {
+        format!(
+            "The versions of {} {} are not ordered consecutively",
+            entity_type, id
+        )
+    } else {
+        format!(
+            "Found {} {} several times; history files must be compiled with --history",
+            entity_type, id
+        )
+    }
+    .into()
+}
+
+/// Creates a builder of an index from ids to entities.
+///
+/// In history archives, an id is mapped to the first version of the entity.
+fn id_table_builder(options: &CompileOptions) -> ids::IdTableBuilder {
+    if options.history {
+        ids::IdTableBuilder::with_versions()
+    } else {
+        ids::IdTableBuilder::new()
+    }
+}
+
+/// Writes an index from ids to entities sorted by id into the archive.
+fn serialize_id_index(
+    mut index: flatdata::ExternalVector<osmflat::IdIndex>,
+    id_to_idx: &ids::IdTable,
+) -> Result<(), Error> {
+    for (id, idx) in id_to_idx.iter() {
+        let entry = index.grow()?;
+        entry.set_id(id);
+        entry.set_idx(idx);
+    }
+    index.close()?;
+    Ok(())
+}
+
+/// Pairs of node and way indexes, sorted with bounded memory
+type NodeWaysSorter = ExternalSorter<(u64, u64), (u64, u64)>;
+
+/// Writes the ranges of ways using each node and the indexes of these ways.
+///
+/// `node_ways` contains pairs of node and way indexes in arbitrary order.
+fn serialize_node_ways(
+    builder: &osmflat::OsmBuilder,
+    num_nodes: usize,
+    node_ways: NodeWaysSorter,
+) -> Result<(), Error> {
+    let mut ranges = builder.start_node_ways()?;
+    let mut ways_index = builder.start_node_ways_index()?;
+    // a way uses a node more than once if it is closed or self-intersecting
+    let mut node_ways = node_ways.sort()?.dedup().peekable();
+    for node_idx in 0..num_nodes as u64 {
+        ranges.grow()?.set_way_first_idx(ways_index.len() as u64);
+        while let Some((_, way_idx)) = node_ways.next_if(|&(idx, _)| idx == node_idx) {
+            ways_index.grow()?.set_value(way_idx);
+        }
+    }
+    // sentinel of the last range, like the sentinel of the nodes
+    ranges.grow()?.set_way_first_idx(ways_index.len() as u64);
+    ranges.close()?;
+    ways_index.close()?;
+    Ok(())
+}
+
+/// Members of relations with the index of the relation and the role, sorted
+/// stably by the index of the member with bounded memory
+type MembersSorter = ExternalSorter<(u64, u64, u64), u64>;
+
+/// Members of relations together with the index of the relation and their role
+///
+/// Collected during the conversion of relations for the parent relations
+/// indexes.
+struct ParentRelationsBuilder {
+    nodes: MembersSorter,
+    ways: MembersSorter,
+    relations: MembersSorter,
+}
+
+impl ParentRelationsBuilder {
+    /// Creates a builder which spills the members to temporary files in
+    /// `temp_dir`.
+    fn new(temp_dir: &Path) -> Self {
+        let sorter = || MembersSorter::new(temp_dir, |&(idx, _, _)| idx);
+        Self {
+            nodes: sorter(),
+            ways: sorter(),
+            relations: sorter(),
+        }
+    }
+
+    fn add(
+        &mut self,
+        relation_idx: u64,
+        members: &[(MemberType, i64, Option<u64>, usize)],
+        string_refs: &[u64],
+    ) -> io::Result<()> {
+        for &(member_type, _, idx, role_sid) in members {
+            let member = match idx {
+                Some(idx) => (idx, relation_idx, string_refs[role_sid]),
+                None => continue,
+            };
+            match member_type {
+                MemberType::Node => self.nodes.push(member)?,
+                MemberType::Way => self.ways.push(member)?,
+                MemberType::Relation => self.relations.push(member)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize(self, builder: &osmflat::OsmBuilder, stats: &Stats) -> Result<(), Error> {
+        Self::serialize_members(
+            builder.start_node_parent_relations()?,
+            builder.start_node_parent_relations_index()?,
+            stats.num_nodes,
+            self.nodes,
+        )?;
+        Self::serialize_members(
+            builder.start_way_parent_relations()?,
+            builder.start_way_parent_relations_index()?,
+            stats.num_ways,
+            self.ways,
+        )?;
+        Self::serialize_members(
+            builder.start_relation_parent_relations()?,
+            builder.start_relation_parent_relations_index()?,
+            stats.num_relations,
+            self.relations,
+        )
+    }
+
+    /// Writes the ranges of parent relations of each entity and the parent
+    /// relations with the roles.
+    fn serialize_members(
+        mut ranges: flatdata::ExternalVector<osmflat::ParentRelations>,
+        mut parents: flatdata::ExternalVector<osmflat::ParentRelation>,
+        num_entities: usize,
+        members: MembersSorter,
+    ) -> Result<(), Error> {
+        // stable, so that the parents of an entity stay in the order of the relations
+        // and their members
+        let mut members = members.sort()?.peekable();
+        for entity_idx in 0..num_entities as u64 {
+            ranges.grow()?.set_relation_first_idx(parents.len() as u64);
+            while let Some((_, relation_idx, role_idx)) =
+                members.next_if(|&(idx, _, _)| idx == entity_idx)
+            {
+                let parent = parents.grow()?;
+                parent.set_relation_idx(relation_idx);
+                parent.set_role_idx(role_idx);
+            }
+        }
+        ranges.grow()?.set_relation_first_idx(parents.len() as u64);
+        ranges.close()?;
+        parents.close()?;
+        Ok(())
+    }
+}
+
+/// Finds the relations with members missing in the input, also indirectly
+/// via other relations.
+fn find_dangling_relations<I>(
+    reader: &BlockReader,
+    block_index: I,
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+) -> Result<HashSet<i64>, Error>
 where
     I: ExactSizeIterator<Item = BlockIndex> + Send + 'static,
 {
-    let mut result = ids::IdTableBuilder::new();
+    let mut relation_ids = HashSet::new();
+    let mut dangling = HashSet::new();
+    let mut relation_members = Vec::new();
+    let no_relations = ids::IdTable::default();
+    info!("Finding relations with dangling references...");
+    parallel::parallel_process(
+        block_index,
+        |idx| reader.read(&idx),
+        |block: Result<osmpbf::PrimitiveBlock, _>| -> Result<(), Error> {
+            for group in &block?.primitivegroup {
+                for relation in &group.relations {
+                    relation_ids.insert(relation.id);
+                    let members =
+                        resolve_members(relation, nodes_id_to_idx, ways_id_to_idx, &no_relations);
+                    let is_dangling = members
+                        .iter()
+                        .any(|&(t, _, idx, _)| t != MemberType::Relation && idx.is_none());
+                    if is_dangling {
+                        dangling.insert(relation.id);
+                    } else {
+                        let members = members.into_iter();
+                        let relations = members.filter(|&(t, ..)| t == MemberType::Relation);
+                        relation_members.push((relation.id, relations.map(|m| m.1).collect_vec()));
+                    }
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    // relations referencing missing or dangling relations are dangling as well,
+    // which is repeated until no more relations are found
+    loop {
+        let num_dangling = dangling.len();
+        relation_members.retain(|(id, members): &(i64, Vec<i64>)| {
+            let is_dangling = members
+                .iter()
+                .any(|id| !relation_ids.contains(id) || dangling.contains(id));
+            if is_dangling {
+                dangling.insert(*id);
+            }
+            !is_dangling
+        });
+        if dangling.len() == num_dangling {
+            break;
+        }
+    }
+    info!(
+        "Found {} relations with dangling references.",
+        dangling.len()
+    );
+    Ok(dangling)
+}
+
+/// Builds the index of relation ids, skipping the `dangling_relations`.
+fn build_relations_index<I>(
+    reader: &BlockReader,
+    block_index: I,
+    dangling_relations: &HashSet<i64>,
+    options: &CompileOptions,
+) -> Result<ids::IdTable, Error>
+where
+    I: ExactSizeIterator<Item = BlockIndex> + Send + 'static,
+{
+    let mut result = id_table_builder(options);
     let mut pb = ProgressBar::new(block_index.len() as u64);
     pb.message("Building relations index...");
     parallel::parallel_process(
         block_index,
-        |idx| read_block(&data, &idx),
+        |idx| reader.read(&idx),
         |block: Result<osmpbf::PrimitiveBlock, _>| -> Result<(), Error> {
             for group in &block?.primitivegroup {
                 for relation in &group.relations {
-                    result.insert(relation.id as u64);
+                    if !dangling_relations.contains(&relation.id) {
+                        result.insert(relation.id);
+                    }
                 }
             }
             pb.inc();
@@ -278,9 +833,44 @@ where
         },
     )?;
 
-    Ok(result.build())
+    result
+        .build()
+        .map_err(|e| duplicate_id_error("relation", e, options.history))
 }
 
+/// Resolves the members of a relation to their type, id, index and role.
+fn resolve_members(
+    pbf_relation: &osmpbf::Relation,
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+    relations_id_to_idx: &ids::IdTable,
+) -> Vec<(MemberType, i64, Option<u64>, usize)> {
+    debug_assert!(
+        pbf_relation.roles_sid.len() == pbf_relation.memids.len()
+            && pbf_relation.memids.len() == pbf_relation.types.len(),
+        "invalid input data"
+    );
+
+    let mut memid = 0;
+    let mut members = Vec::with_capacity(pbf_relation.memids.len());
+    for i in 0..pbf_relation.roles_sid.len() {
+        memid += pbf_relation.memids[i];
+
+        let member_type = MemberType::from_i32(pbf_relation.types[i]);
+        debug_assert!(member_type.is_some());
+        let member_type = member_type.unwrap();
+
+        let idx = match member_type {
+            MemberType::Node => nodes_id_to_idx.get(memid),
+            MemberType::Way => ways_id_to_idx.get(memid),
+            MemberType::Relation => relations_id_to_idx.get(memid),
+        };
+        members.push((member_type, memid, idx, pbf_relation.roles_sid[i] as usize));
+    }
+    members
+}
+
+#[allow(clippy::too_many_arguments)]
 fn serialize_relations(
     block: &osmpbf::PrimitiveBlock,
     nodes_id_to_idx: &ids::IdTable,
@@ -290,11 +880,61 @@ fn serialize_relations(
     relations: &mut flatdata::ExternalVector<osmflat::Relation>,
     relation_members: &mut flatdata::MultiVector<osmflat::RelationMembers>,
     tags: &mut TagSerializer,
+    infos: &mut Option<InfoSerializer<'_>>,
+    report: &mut Option<UnresolvedReport>,
+    dangling: Dangling,
+    dropped_ways: &IdSet,
+    dangling_relations: &HashSet<i64>,
+    parents: &mut Option<ParentRelationsBuilder>,
 ) -> Result<Stats, Error> {
     let mut stats = Stats::default();
     let string_refs = add_string_table(&block.stringtable, stringtable)?;
     for group in &block.primitivegroup {
         for pbf_relation in &group.relations {
+            let members = resolve_members(
+                pbf_relation,
+                nodes_id_to_idx,
+                ways_id_to_idx,
+                relations_id_to_idx,
+            );
+            for &(member_type, memid, idx, role_sid) in &members {
+                if idx.is_some() {
+                    continue;
+                }
+                // members dropped because of dangling references are not
+                // missing in the input
+                match member_type {
+                    MemberType::Way if dropped_ways.contains(memid) => {
+                        stats.num_refs_to_dropped_ways += 1;
+                        continue;
+                    }
+                    MemberType::Relation if dangling_relations.contains(&memid) => {
+                        stats.num_refs_to_dropped_relations += 1;
+                        continue;
+                    }
+                    _ => (),
+                }
+                match member_type {
+                    MemberType::Node => stats.num_unresolved_node_ids += 1,
+                    MemberType::Way => stats.num_unresolved_way_ids += 1,
+                    MemberType::Relation => stats.num_unresolved_rel_ids += 1,
+                }
+                if let Some(report) = report {
+                    report.write(
+                        MemberType::Relation,
+                        pbf_relation.id,
+                        member_type,
+                        memid,
+                        &block.stringtable.s[role_sid],
+                    )?;
+                }
+            }
+            if dangling_relations.contains(&pbf_relation.id) {
+                stats.num_dropped_relations += 1;
+                continue;
+            }
+
+            let relation_idx = relations.len() as u64;
             let relation = relations.grow()?;
             relation.set_id(pbf_relation.id);
 
@@ -311,76 +951,87 @@ fn serialize_relations(
                 )?;
             }
 
-            debug_assert!(
-                pbf_relation.roles_sid.len() == pbf_relation.memids.len()
-                    && pbf_relation.memids.len() == pbf_relation.types.len(),
-                "invalid input data"
-            );
-
-            let mut memid = 0;
-            let mut members = relation_members.grow()?;
-            for i in 0..pbf_relation.roles_sid.len() {
-                memid += pbf_relation.memids[i];
-
-                let member_type = osmpbf::relation::MemberType::from_i32(pbf_relation.types[i]);
-                debug_assert!(member_type.is_some());
-
-                match member_type.unwrap() {
-                    osmpbf::relation::MemberType::Node => {
-                        let idx = nodes_id_to_idx.get(memid as u64);
-                        stats.num_unresolved_node_ids = idx.is_none() as usize;
+            if let Some(parents) = parents {
+                parents.add(relation_idx, &members, &string_refs)?;
+            }
 
-                        let member = members.add_node_member();
+            let mut relation_members = relation_members.grow()?;
+            for (member_type, _, idx, role_sid) in members {
+                if idx.is_none() && dangling != Dangling::Keep {
+                    continue;
+                }
+                match member_type {
+                    MemberType::Node => {
+                        let member = relation_members.add_node_member();
                         member.set_node_idx(idx);
-                        member.set_role_idx(string_refs[pbf_relation.roles_sid[i] as usize]);
+                        member.set_role_idx(string_refs[role_sid]);
                     }
-                    osmpbf::relation::MemberType::Way => {
-                        let idx = ways_id_to_idx.get(memid as u64);
-                        stats.num_unresolved_way_ids = idx.is_none() as usize;
-
-                        let member = members.add_way_member();
+                    MemberType::Way => {
+                        let member = relation_members.add_way_member();
                         member.set_way_idx(idx);
-                        member.set_role_idx(string_refs[pbf_relation.roles_sid[i] as usize]);
+                        member.set_role_idx(string_refs[role_sid]);
                     }
-                    osmpbf::relation::MemberType::Relation => {
-                        let idx = relations_id_to_idx.get(memid as u64);
-                        stats.num_unresolved_rel_ids = idx.is_none() as usize;
-
-                        let member = members.add_relation_member();
+                    MemberType::Relation => {
+                        let member = relation_members.add_relation_member();
                         member.set_relation_idx(idx);
-                        member.set_role_idx(string_refs[pbf_relation.roles_sid[i] as usize]);
+                        member.set_role_idx(string_refs[role_sid]);
                     }
                 }
             }
+
+            if let Some(infos) = infos {
+                infos.serialize(
+                    block,
+                    pbf_relation.id,
+                    pbf_relation.info.as_ref(),
+                    &string_refs,
+                )?;
+            }
             stats.num_relations += 1;
         }
     }
     Ok(stats)
 }
 
-fn serialize_dense_node_blocks(
+/// Serializes dense and non-dense node blocks.
+///
+/// `blocks` must be sorted by their position in the input and must not contain
+/// duplicates, so that nodes from both kinds of blocks end up in the same order
+/// as in the input.
+fn serialize_node_blocks(
     builder: &osmflat::OsmBuilder,
     blocks: Vec<BlockIndex>,
-    data: &[u8],
+    reader: &BlockReader,
     tags: &mut TagSerializer,
     stringtable: &mut StringTable,
+    options: &CompileOptions,
     stats: &mut Stats,
 ) -> Result<ids::IdTable, Error> {
-    let mut nodes_id_to_idx = ids::IdTableBuilder::new();
+    let mut nodes_id_to_idx = id_table_builder(options);
     let mut nodes = builder.start_nodes()?;
+    let mut infos = if options.with_metadata {
+        let validity = options
+            .history
+            .then(|| builder.start_node_validity())
+            .transpose()?;
+        Some(InfoSerializer::new(builder.start_node_info()?, validity))
+    } else {
+        None
+    };
     let mut pb = ProgressBar::new(blocks.len() as u64);
-    pb.message("Converting dense nodes...");
+    pb.message("Converting nodes...");
 
     parallel::parallel_process(
         blocks.into_iter(),
-        |idx| read_block(&data, &idx),
+        |idx| reader.read(&idx),
         |block| -> Result<(), Error> {
-            *stats += serialize_dense_nodes(
+            *stats += serialize_node_block(
                 &block?,
                 &mut nodes,
                 &mut nodes_id_to_idx,
                 stringtable,
                 tags,
+                &mut infos,
             )?;
 
             pb.inc();
@@ -392,35 +1043,60 @@ fn serialize_dense_node_blocks(
     // of the last node
     nodes.grow()?.set_tag_first_idx(tags.next_index());
     nodes.close()?;
-    info!("Dense nodes converted.");
-    info!("Building dense nodes index...");
-    let nodes_id_to_idx = nodes_id_to_idx.build();
-    info!("Dense nodes index built.");
+    if let Some(infos) = infos {
+        infos.close()?;
+    }
+    info!("Nodes converted.");
+    info!("Building nodes index...");
+    let nodes_id_to_idx = nodes_id_to_idx
+        .build()
+        .map_err(|e| duplicate_id_error("node", e, options.history))?;
+    info!("Nodes index built.");
+    if options.with_id_index {
+        serialize_id_index(builder.start_node_id_index()?, &nodes_id_to_idx)?;
+    }
     Ok(nodes_id_to_idx)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn serialize_way_blocks(
     builder: &osmflat::OsmBuilder,
+    temp_dir: &Path,
     blocks: Vec<BlockIndex>,
-    data: &[u8],
+    reader: &BlockReader,
     nodes_id_to_idx: &ids::IdTable,
     tags: &mut TagSerializer,
     stringtable: &mut StringTable,
+    options: &CompileOptions,
     stats: &mut Stats,
-) -> Result<ids::IdTable, Error> {
-    let mut ways_id_to_idx = ids::IdTableBuilder::new();
+    report: &mut Option<UnresolvedReport>,
+) -> Result<(ids::IdTable, IdSet), Error> {
+    let mut ways_id_to_idx = id_table_builder(options);
+    let mut dropped_ways = IdSet::default();
     let mut ways = builder.start_ways()?;
+    let mut infos = if options.with_metadata {
+        let validity = options
+            .history
+            .then(|| builder.start_way_validity())
+            .transpose()?;
+        Some(InfoSerializer::new(builder.start_way_info()?, validity))
+    } else {
+        None
+    };
     let mut pb = ProgressBar::new(blocks.len() as u64);
     let mut nodes_index = builder.start_nodes_index()?;
+    let mut node_ways = options
+        .with_node_ways
+        .then(|| NodeWaysSorter::new(temp_dir, |&pair| pair));
     pb.message("Converting ways...");
     parallel::parallel_process(
         blocks.into_iter(),
-        |idx| {
-            let block: osmpbf::PrimitiveBlock = read_block(&data, &idx)?;
+        |idx| -> Result<_, osmpbf::Error> {
+            let block = reader.read(&idx)?;
             let ids = resolve_ways(&block, nodes_id_to_idx);
             Ok((block, ids))
         },
-        |block: Result<(osmpbf::PrimitiveBlock, (Vec<Option<u64>>, Stats)), io::Error>| -> Result<(), Error> {
+        |block| -> Result<(), Error> {
             let (block, (ids, stats_resolve)) = block?;
             *stats += stats_resolve;
             *stats += serialize_ways(
@@ -431,6 +1107,11 @@ fn serialize_way_blocks(
                 stringtable,
                 tags,
                 &mut nodes_index,
+                &mut infos,
+                report,
+                options.dangling,
+                &mut dropped_ways,
+                &mut node_ways,
             )?;
             pb.inc();
             Ok(())
@@ -444,46 +1125,106 @@ fn serialize_way_blocks(
     }
     ways.close()?;
     nodes_index.close()?;
+    if let Some(infos) = infos {
+        infos.close()?;
+    }
 
     info!("Ways converted.");
+    if let Some(node_ways) = node_ways {
+        info!("Building node ways index...");
+        serialize_node_ways(builder, stats.num_nodes, node_ways)?;
+        info!("Node ways index built.");
+    }
     info!("Building ways index...");
-    let ways_id_to_idx = ways_id_to_idx.build();
+    let ways_id_to_idx = ways_id_to_idx
+        .build()
+        .map_err(|e| duplicate_id_error("way", e, options.history))?;
     info!("Way index built.");
-    Ok(ways_id_to_idx)
+    if options.with_id_index {
+        serialize_id_index(builder.start_way_id_index()?, &ways_id_to_idx)?;
+    }
+    Ok((ways_id_to_idx, dropped_ways))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn serialize_relation_blocks(
     builder: &osmflat::OsmBuilder,
+    temp_dir: &Path,
     blocks: Vec<BlockIndex>,
-    data: &[u8],
+    reader: &BlockReader,
     nodes_id_to_idx: &ids::IdTable,
     ways_id_to_idx: &ids::IdTable,
+    dropped_ways: &IdSet,
     tags: &mut TagSerializer,
     stringtable: &mut StringTable,
+    options: &CompileOptions,
     stats: &mut Stats,
+    report: &mut Option<UnresolvedReport>,
 ) -> Result<(), Error> {
+    // Relations which are dropped because of dangling references must not be in
+    // the index.
+    let dangling_relations = if options.dangling == Dangling::DropEntity {
+        find_dangling_relations(
+            reader,
+            blocks.clone().into_iter(),
+            nodes_id_to_idx,
+            ways_id_to_idx,
+        )?
+    } else {
+        HashSet::new()
+    };
+
     // We need to build the index of relation ids first, since relations can refer
     // again to relations.
-    let relations_id_to_idx = build_relations_index(data, blocks.clone().into_iter())?;
+    let relations_id_to_idx = build_relations_index(
+        reader,
+        blocks.clone().into_iter(),
+        &dangling_relations,
+        options,
+    )?;
+    if options.with_id_index {
+        serialize_id_index(builder.start_relation_id_index()?, &relations_id_to_idx)?;
+    }
 
     let mut relations = builder.start_relations()?;
     let mut relation_members = builder.start_relation_members()?;
+    let mut parents = options
+        .with_parent_relations
+        .then(|| ParentRelationsBuilder::new(temp_dir));
+    let mut infos = if options.with_metadata {
+        let validity = options
+            .history
+            .then(|| builder.start_relation_validity())
+            .transpose()?;
+        Some(InfoSerializer::new(
+            builder.start_relation_info()?,
+            validity,
+        ))
+    } else {
+        None
+    };
 
     let mut pb = ProgressBar::new(blocks.len() as u64);
     pb.message("Converting relations...");
     parallel::parallel_process(
         blocks.into_iter(),
-        |idx| read_block(&data, &idx),
+        |idx| reader.read(&idx),
         |block| -> Result<(), Error> {
             *stats += serialize_relations(
                 &block?,
-                &nodes_id_to_idx,
-                &ways_id_to_idx,
+                nodes_id_to_idx,
+                ways_id_to_idx,
                 &relations_id_to_idx,
                 stringtable,
                 &mut relations,
                 &mut relation_members,
                 tags,
+                &mut infos,
+                report,
+                options.dangling,
+                dropped_ways,
+                &dangling_relations,
+                &mut parents,
             )?;
             pb.inc();
             Ok(())
@@ -497,47 +1238,125 @@ fn serialize_relation_blocks(
 
     relations.close()?;
     relation_members.close()?;
+    if let Some(infos) = infos {
+        infos.close()?;
+    }
 
     info!("Relations converted.");
+    if let Some(parents) = parents {
+        info!("Building parent relations indexes...");
+        parents.serialize(builder, stats)?;
+        info!("Parent relations indexes built.");
+    }
 
     Ok(())
 }
 
 fn run(args: args::Args) -> Result<(), Error> {
-    let input_file = File::open(&args.input)?;
-    let input_data = unsafe { Mmap::map(&input_file)? };
+    match &args.command {
+        Some(Command::ApplyChanges(apply_args)) => changes::run(apply_args, &args.options),
+        Some(Command::Update(update_args)) => replication::run(update_args, &args.options),
+        None => {
+            let input = args.input.as_ref().expect("missing input");
+            let output = args.output.as_ref().expect("missing output");
+            let input_format = args
+                .input_format
+                .unwrap_or_else(|| input::detect_format(input));
+            compile_file(input, input_format, output, &args.options)
+        }
+    }
+}
+
+fn compile_file(
+    input: &Path,
+    input_format: InputFormat,
+    output: &Path,
+    options: &CompileOptions,
+) -> Result<(), Error> {
+    match input_format {
+        InputFormat::Pbf => {
+            let input_file = File::open(input)?;
+            let input_data = unsafe { Mmap::map(&input_file)? };
+            compile(&input_data, output, options)
+        }
+        InputFormat::Xml => {
+            info!("Converting XML input to PBF...");
+            let input_data = osmxml::to_pbf(input::open_decompressed(input)?)?;
+            compile(&input_data, output, options)
+        }
+        InputFormat::O5m => {
+            info!("Converting o5m input to PBF...");
+            let input_data = o5m::to_pbf(input::open_decompressed(input)?)?;
+            compile(&input_data, output, options)
+        }
+    }
+}
 
-    let storage = FileResourceStorage::new(args.output.clone());
+/// Compiles PBF data into a new osmflat archive.
+fn compile(input_data: &[u8], output: &Path, options: &CompileOptions) -> Result<(), Error> {
+    // the validity of versions in history archives is derived from their metadata
+    let options = &CompileOptions {
+        with_metadata: options.with_metadata || options.history,
+        ..options.clone()
+    };
+    if options.hilbert_order {
+        info!("Reordering nodes and ways...");
+        let file = BufWriter::new(temp_file_next_to(output)?);
+        let file = reorder::write_pbf(input_data, options.skip_corrupt_blocks, file)?
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        let input_data = unsafe { Mmap::map(&file)? };
+        let options = CompileOptions {
+            hilbert_order: false,
+            ..options.clone()
+        };
+        return compile(&input_data, output, &options);
+    }
+    let region = match (options.bbox, &options.polygon) {
+        (Some(bbox), _) => Some(Region::BBox(bbox)),
+        (None, Some(path)) => Some(Region::Polygon(polygon::Polygon::read(path)?)),
+        (None, None) => None,
+    };
+    let storage = FileResourceStorage::new(output.to_path_buf());
     let builder = osmflat::OsmBuilder::new(storage)?;
 
-    // TODO: Would be nice not store all these strings in memory, but to flush them
-    // from time to time to disk.
-    let mut stringtable = StringTable::new();
-    let mut tags = TagSerializer::new(&builder)?;
+    let mut stringtable = StringTable::new(
+        output,
+        options
+            .stringtable_memory
+            .map(|megabytes| megabytes * 1024 * 1024),
+    )?;
+    let mut tags = TagSerializer::new(&builder, options)?;
 
-    info!(
-        "Initialized new osmflat archive at: {}",
-        &args.output.display()
-    );
+    info!("Initialized new osmflat archive at: {}", output.display());
+
+    let mut stats = Stats::default();
 
     info!("Building index of PBF blocks...");
-    let block_index = build_block_index(&input_data);
+    let (block_index, skipped_blocks) = build_block_index(input_data, options.skip_corrupt_blocks)?;
+    for e in &skipped_blocks {
+        warn!("Skipping corrupt block: {}", e);
+    }
+    stats.num_skipped_blocks = skipped_blocks.len();
 
     // TODO: move out into a function
     let groups = block_index.into_iter().group_by(|b| b.block_type);
     let mut pbf_header = Vec::new();
-    let mut pbf_dense_nodes = Vec::new();
+    let mut pbf_nodes = Vec::new();
     let mut pbf_ways = Vec::new();
     let mut pbf_relations = Vec::new();
     for (block_type, blocks) in &groups {
         match block_type {
-            BlockType::Header => pbf_header = blocks.collect(),
-            BlockType::Nodes => panic!("Found nodes block, only dense nodes are supported now"),
-            BlockType::DenseNodes => pbf_dense_nodes = blocks.collect(),
-            BlockType::Ways => pbf_ways = blocks.collect(),
-            BlockType::Relations => pbf_relations = blocks.collect(),
+            BlockType::Header => pbf_header.extend(blocks),
+            BlockType::Nodes | BlockType::DenseNodes => pbf_nodes.extend(blocks),
+            BlockType::Ways => pbf_ways.extend(blocks),
+            BlockType::Relations => pbf_relations.extend(blocks),
         }
     }
+    // restore the input order of dense and non-dense node blocks; a block containing
+    // both kinds of groups is indexed twice, but must be serialized only once
+    pbf_nodes.sort_by_key(|b| b.blob_start);
+    pbf_nodes.dedup_by_key(|b| b.blob_start);
     info!("PBF block index built.");
 
     // Serialize header
@@ -549,47 +1368,116 @@ fn run(args: args::Args) -> Result<(), Error> {
         .into());
     }
     let idx = &pbf_header[0];
-    let pbf_header: osmpbf::HeaderBlock = read_block(&input_data, &idx)?;
+    let mut pbf_header: osmpbf::HeaderBlock = read_block(input_data, idx)?;
+    let is_history = pbf_header
+        .required_features
+        .iter()
+        .any(|feature| feature == "HistoricalInformation");
+    if is_history && !options.history {
+        return Err("Input is a history file, which can only be compiled with --history".into());
+    }
+    if let Some(region) = &region {
+        pbf_header.bbox = Some(region.bbox().to_header_bbox());
+    }
     serialize_header(&pbf_header, &builder, &mut stringtable)?;
     info!("Header written.");
 
-    let mut stats = Stats::default();
+    // the tag filter is applied to the data inside the region
+    let mut reader = BlockReader::new(input_data, None);
+    if let Some(region) = &region {
+        let selection = Selection::clip(&reader, &pbf_nodes, &pbf_ways, &pbf_relations, region)?;
+        reader = BlockReader::new(input_data, Some(selection));
+    }
+    if !options.keep.is_empty() {
+        let selection = Selection::filter_tags(
+            &reader,
+            &pbf_nodes,
+            &pbf_ways,
+            &pbf_relations,
+            &options.keep,
+        )?;
+        reader = BlockReader::new(input_data, Some(selection));
+    }
+    if options.drop_orphan_nodes {
+        let used_nodes = filter::used_nodes(&reader, &pbf_ways, &pbf_relations)?;
+        reader = reader.drop_orphan_nodes(used_nodes);
+    }
 
-    let nodes_id_to_idx = serialize_dense_node_blocks(
+    let mut report = options
+        .unresolved_report
+        .as_deref()
+        .map(UnresolvedReport::create)
+        .transpose()?;
+
+    let nodes_id_to_idx = serialize_node_blocks(
         &builder,
-        pbf_dense_nodes,
-        &input_data,
+        pbf_nodes,
+        &reader,
         &mut tags,
         &mut stringtable,
+        options,
         &mut stats,
     )?;
 
-    let ways_id_to_idx = serialize_way_blocks(
+    let (ways_id_to_idx, dropped_ways) = serialize_way_blocks(
         &builder,
+        output,
         pbf_ways,
-        &input_data,
+        &reader,
         &nodes_id_to_idx,
         &mut tags,
         &mut stringtable,
+        options,
         &mut stats,
+        &mut report,
     )?;
 
     serialize_relation_blocks(
         &builder,
+        output,
         pbf_relations,
-        &input_data,
+        &reader,
         &nodes_id_to_idx,
         &ways_id_to_idx,
+        &dropped_ways,
         &mut tags,
         &mut stringtable,
+        options,
         &mut stats,
+        &mut report,
     )?;
+    if let Some(report) = report {
+        report.close()?;
+    }
 
     // Finalize data structures
+    stats.num_tag_refs = tags.next_index() as usize;
+    stats.num_tags = tags.num_tags();
+    stats.tag_dedup = Some(match options.tag_dedup {
+        TagDedup::Lru => format!("lru (capacity {})", options.tag_dedup_capacity),
+        policy => policy.to_string(),
+    });
     tags.close(); // drop the reference to stringtable
 
     info!("Writing stringtable to disk...");
-    builder.set_stringtable(&stringtable.into_bytes())?;
+    stats.num_evicted_strings = stringtable.num_evicted();
+    builder.set_stringtable(&stringtable.into_bytes()?)?;
+
+    if options.with_tag_index || options.with_spatial_index {
+        // the indexes are built from the complete archive, in which tags are resolved
+        // to their strings and ways to the locations of their nodes
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output.to_path_buf()))?;
+        if options.with_tag_index {
+            info!("Building tag index...");
+            let num_entries = tag_index::build(&archive, &builder)?;
+            info!("Tag index built with {} entries.", num_entries);
+        }
+        if options.with_spatial_index {
+            info!("Building spatial index...");
+            spatial_index::build(&archive, &builder)?;
+            info!("Spatial index built.");
+        }
+    }
 
     info!("osmflat archive built.");
 
@@ -598,7 +1486,7 @@ fn run(args: args::Args) -> Result<(), Error> {
 }
 
 fn main() {
-    let args = args::Args::from_args();
+    let args = args::Args::parse();
     let level = match args.verbose {
         0 => "info",
         1 => "debug",
@@ -614,3 +1502,412 @@ fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::testing::*;
+
+    #[test]
+    fn test_compile_plain_nodes() {
+        let strings = ["", "name", "a", "b"];
+        let block = osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            primitivegroup: vec![
+                osmpbf::PrimitiveGroup {
+                    nodes: vec![
+                        osmpbf::Node {
+                            id: 3,
+                            keys: vec![1],
+                            vals: vec![2],
+                            lat: 10,
+                            lon: -20,
+                            ..Default::default()
+                        },
+                        osmpbf::Node {
+                            id: 1,
+                            lat: -5,
+                            lon: 7,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                // plain and dense nodes can be mixed in a block
+                osmpbf::PrimitiveGroup {
+                    dense: Some(osmpbf::DenseNodes {
+                        id: vec![2],
+                        lat: vec![1],
+                        lon: vec![2],
+                        keys_vals: vec![1, 3, 0],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            granularity: Some(100),
+            lat_offset: Some(1000),
+            lon_offset: Some(2000),
+            ..Default::default()
+        };
+        let data = pbf_from_blocks(&Default::default(), &[block]);
+        let (_dir, archive) = compile_pbf(&data, &[]);
+
+        assert_eq!(node_ids(&archive), vec![3, 1, 2]);
+        let coordinates: Vec<_> = archive
+            .nodes()
+            .iter()
+            .map(|node| (node.lat(), node.lon()))
+            .collect();
+        assert_eq!(coordinates, vec![(2000, 0), (500, 2700), (1100, 2200)]);
+        let node_tags: Vec<_> = archive
+            .nodes()
+            .iter()
+            .map(|node| read_tags(&archive, node.tags()))
+            .collect();
+        assert_eq!(
+            node_tags,
+            vec![tags(&[("name", "a")]), tags(&[]), tags(&[("name", "b")])]
+        );
+    }
+
+    #[test]
+    fn test_compile_metadata() {
+        let block = |group| osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable {
+                s: vec![b"".to_vec(), b"alice".to_vec(), b"bob".to_vec()],
+            },
+            primitivegroup: vec![group],
+            date_granularity: Some(2000),
+            ..Default::default()
+        };
+        let info = |version, timestamp, changeset, uid, user_sid| osmpbf::Info {
+            version: Some(version),
+            timestamp: Some(timestamp),
+            changeset: Some(changeset),
+            uid: Some(uid),
+            user_sid: Some(user_sid),
+            visible: None,
+        };
+        let dense_nodes = osmpbf::PrimitiveGroup {
+            dense: Some(osmpbf::DenseNodes {
+                id: vec![1, 1],
+                lat: vec![0, 0],
+                lon: vec![0, 0],
+                // all fields except the version are delta coded
+                denseinfo: Some(osmpbf::DenseInfo {
+                    version: vec![1, 3],
+                    timestamp: vec![100, 50],
+                    changeset: vec![10, -3],
+                    uid: vec![5, 2],
+                    user_sid: vec![1, 1],
+                    visible: Vec::new(),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let nodes = osmpbf::PrimitiveGroup {
+            nodes: vec![
+                osmpbf::Node {
+                    id: 3,
+                    info: Some(info(2, 400, 8, 0, 0)),
+                    ..Default::default()
+                },
+                osmpbf::Node {
+                    id: 4,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let ways = osmpbf::PrimitiveGroup {
+            ways: vec![osmpbf::Way {
+                id: 10,
+                refs: vec![1],
+                info: Some(info(4, 500, 9, 5, 1)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let relations = osmpbf::PrimitiveGroup {
+            relations: vec![osmpbf::Relation {
+                id: 20,
+                roles_sid: vec![0],
+                memids: vec![1],
+                types: vec![MemberType::Node as i32],
+                info: Some(info(5, 600, 11, 7, 2)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let blocks = [
+            block(dense_nodes),
+            block(nodes),
+            block(ways),
+            block(relations),
+        ];
+        let data = pbf_from_blocks(&Default::default(), &blocks);
+        let (_dir, archive) = compile_pbf(&data, &["--with-metadata"]);
+
+        let infos = |infos: &[osmflat::Info]| -> Vec<_> {
+            infos
+                .iter()
+                .map(|info| {
+                    let user = info.user_idx().map(|idx| {
+                        str::from_utf8(archive.stringtable().substring_raw(idx as usize))
+                            .unwrap()
+                            .to_string()
+                    });
+                    (
+                        info.version(),
+                        info.timestamp(),
+                        info.changeset(),
+                        info.uid(),
+                        user,
+                    )
+                })
+                .collect()
+        };
+        let user = |name: &str| Some(name.to_string());
+        assert_eq!(node_ids(&archive), vec![1, 2, 3, 4]);
+        assert_eq!(
+            infos(archive.node_info().unwrap()),
+            vec![
+                (1, 200, 10, 5, user("alice")),
+                (3, 300, 7, 7, user("bob")),
+                (2, 800, 8, 0, None),
+                (0, 0, 0, 0, None),
+            ]
+        );
+        assert_eq!(
+            infos(archive.way_info().unwrap()),
+            vec![(4, 1000, 9, 5, user("alice"))]
+        );
+        assert_eq!(
+            infos(archive.relation_info().unwrap()),
+            vec![(5, 1200, 11, 7, user("bob"))]
+        );
+    }
+
+    #[test]
+    fn test_compile_history_xml() {
+        let xml = r#"<osm>
+  <node id="1" version="1" timestamp="2020-10-17T12:34:56Z" lat="1" lon="2"/>
+  <node id="1" version="2" timestamp="2020-10-17T12:35:00Z" lat="1" lon="3"/>
+  <node id="2" version="1" timestamp="2020-10-17T12:35:00Z" lat="1" lon="4"/>
+</osm>"#;
+        let data = osmxml::to_pbf(xml.as_bytes()).unwrap();
+        let e = try_compile_pbf(&data, &[]).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "Found node 1 several times; history files must be compiled with --history"
+        );
+
+        let (_dir, archive) = compile_pbf(&data, &["--history"]);
+        assert_eq!(node_ids(&archive), vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn test_node_ways() {
+        let entities = vec![
+            node(1, 0, 0),
+            node(2, 0, 0),
+            node(3, 0, 0),
+            node(4, 0, 0),
+            way(10, &[1, 2, 3, 1]),
+            way(11, &[3, 2]),
+        ];
+        let (_dir, archive) = compile_entities(entities, &["--with-node-ways"]);
+        let ways_of_node = |idx| archive.ways_of_node(idx).unwrap().collect::<Vec<_>>();
+        // the closed way 10 uses node 1 twice, but is stored once
+        assert_eq!(ways_of_node(0), vec![0]);
+        assert_eq!(ways_of_node(1), vec![0, 1]);
+        assert_eq!(ways_of_node(2), vec![0, 1]);
+        assert_eq!(ways_of_node(3), Vec::<u64>::new());
+        assert_eq!(archive.node_ways_index().unwrap().len(), 5);
+    }
+
+    fn parents<'a>(iter: Option<impl Iterator<Item = (u64, &'a [u8])>>) -> Vec<(u64, String)> {
+        let role = |role: &[u8]| String::from_utf8(role.to_vec()).unwrap();
+        iter.unwrap().map(|(idx, r)| (idx, role(r))).collect()
+    }
+
+    #[test]
+    fn test_parent_relations() {
+        let entities = vec![
+            node(1, 0, 0),
+            node(2, 0, 0),
+            way(10, &[1, 2]),
+            relation(
+                20,
+                &[
+                    (MemberType::Node, 1, "stop"),
+                    (MemberType::Way, 10, "outer"),
+                    (MemberType::Relation, 22, "subarea"),
+                ],
+            ),
+            relation(
+                21,
+                &[
+                    (MemberType::Relation, 22, "child"),
+                    (MemberType::Way, 10, "inner"),
+                    (MemberType::Node, 1, "platform"),
+                    (MemberType::Node, 1, "entrance"),
+                ],
+            ),
+            relation(22, &[(MemberType::Node, 2, "")]),
+        ];
+        let (_dir, archive) = compile_entities(entities, &["--with-parent-relations"]);
+        let parent = |idx, role: &str| (idx, role.to_string());
+
+        // a relation having a member several times is a parent for each role
+        assert_eq!(
+            parents(archive.parent_relations_of_node(0)),
+            vec![
+                parent(0, "stop"),
+                parent(1, "platform"),
+                parent(1, "entrance")
+            ]
+        );
+        assert_eq!(
+            parents(archive.parent_relations_of_node(1)),
+            vec![parent(2, "")]
+        );
+        assert_eq!(
+            parents(archive.parent_relations_of_way(0)),
+            vec![parent(0, "outer"), parent(1, "inner")]
+        );
+        assert_eq!(
+            parents(archive.parent_relations_of_relation(2)),
+            vec![parent(0, "subarea"), parent(1, "child")]
+        );
+        assert_eq!(parents(archive.parent_relations_of_relation(0)), vec![]);
+    }
+
+    fn dangling_entities() -> Vec<Entity> {
+        vec![
+            node(1, 0, 0),
+            node(2, 0, 0),
+            way(10, &[1, 99, 2]),
+            way(11, &[1, 2]),
+            relation(
+                20,
+                &[
+                    (MemberType::Node, 77, "label"),
+                    (MemberType::Way, 11, "outer"),
+                ],
+            ),
+            relation(21, &[(MemberType::Way, 10, "outer")]),
+            relation(
+                22,
+                &[(MemberType::Relation, 21, "sub"), (MemberType::Node, 1, "")],
+            ),
+            relation(23, &[(MemberType::Way, 11, "")]),
+        ]
+    }
+
+    #[test]
+    fn test_dangling_keep() {
+        let (_dir, archive) = compile_entities(dangling_entities(), &["--dangling", "keep"]);
+        assert_eq!(way_ids(&archive), vec![10, 11]);
+        assert_eq!(way_refs(&archive, 0), vec![Some(1), None, Some(2)]);
+        assert_eq!(relation_ids(&archive), vec![20, 21, 22, 23]);
+        assert_eq!(
+            relation_members(&archive, 0),
+            vec![
+                (MemberType::Node, None, "label".to_string()),
+                (MemberType::Way, Some(11), "outer".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dangling_drop() {
+        let (_dir, archive) = compile_entities(dangling_entities(), &["--dangling", "drop"]);
+        assert_eq!(way_ids(&archive), vec![10, 11]);
+        assert_eq!(way_refs(&archive, 0), vec![Some(1), Some(2)]);
+        assert_eq!(relation_ids(&archive), vec![20, 21, 22, 23]);
+        assert_eq!(
+            relation_members(&archive, 0),
+            vec![(MemberType::Way, Some(11), "outer".to_string())]
+        );
+        assert_eq!(
+            relation_members(&archive, 2),
+            vec![
+                (MemberType::Relation, Some(21), "sub".to_string()),
+                (MemberType::Node, Some(1), String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dangling_drop_entity() {
+        let args = ["--dangling", "drop-entity"];
+        let (_dir, archive) = compile_entities(dangling_entities(), &args);
+        assert_eq!(way_ids(&archive), vec![11]);
+        assert_eq!(way_refs(&archive, 0), vec![Some(1), Some(2)]);
+        // relation 21 loses the dropped way 10, and relation 22 the dropped
+        // relation 21
+        assert_eq!(relation_ids(&archive), vec![23]);
+        assert_eq!(
+            relation_members(&archive, 0),
+            vec![(MemberType::Way, Some(11), String::new())]
+        );
+    }
+
+    #[test]
+    fn test_dangling_drop_entity_large_ids() {
+        // the largest id stored in an archive
+        let id = (1 << 39) - 1;
+        let entities = vec![
+            node(1, 0, 0),
+            way(id, &[1, 99]),
+            way(id - 1, &[1]),
+            relation(20, &[(MemberType::Way, id, "")]),
+            relation(21, &[(MemberType::Way, id - 1, "")]),
+        ];
+        let args = ["--dangling", "drop-entity"];
+        let (_dir, archive) = compile_entities(entities, &args);
+        assert_eq!(way_ids(&archive), vec![id - 1]);
+        assert_eq!(relation_ids(&archive), vec![21]);
+    }
+
+    #[test]
+    fn test_report_without_dropped_entities() {
+        let entities = vec![
+            node(1, 0, 0),
+            way(10, &[1, 99]),
+            relation(
+                20,
+                &[(MemberType::Way, 10, "outer"), (MemberType::Node, 1, "")],
+            ),
+            relation(21, &[(MemberType::Relation, 20, "")]),
+            relation(22, &[(MemberType::Way, 12, "inner")]),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("unresolved.tsv");
+        let report_arg = report.to_str().unwrap();
+        let args = [
+            "--dangling",
+            "drop-entity",
+            "--unresolved-report",
+            report_arg,
+        ];
+        let (_dir, archive) = compile_entities(entities, &args);
+        assert_eq!(way_ids(&archive), Vec::<i64>::new());
+        assert_eq!(relation_ids(&archive), Vec::<i64>::new());
+
+        // the references to the dropped way 10 and relation 20 are not missing
+        // in the input
+        assert_eq!(
+            std::fs::read_to_string(report).unwrap(),
+            "referrer_type\treferrer_id\ttype\tid\trole\n\
+             way\t10\tnode\t99\t\n\
+             relation\t22\tway\t12\tinner\n"
+        );
+    }
+}
@@ -0,0 +1,895 @@
+use crate::entities::{Entity, Info, Member, Node, Relation, Tags, Way};
+use crate::osmpbf::{self, relation::MemberType};
+use crate::pbfwriter::PbfWriter;
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::str;
+
+/// Magic bytes at the start of an o5m file: reset followed by the header
+const O5M_HEADER: &[u8] = b"\xff\xe0\x04o5m2";
+/// Magic bytes at the start of an o5c (changes) file
+const O5C_HEADER: &[u8] = b"\xff\xe0\x04o5c2";
+
+const DATASET_NODE: u8 = 0x10;
+const DATASET_WAY: u8 = 0x11;
+const DATASET_RELATION: u8 = 0x12;
+const DATASET_BOUNDING_BOX: u8 = 0xdb;
+const DATASET_FILE_TIMESTAMP: u8 = 0xdc;
+const DATASET_END_OF_FILE: u8 = 0xfe;
+const DATASET_RESET: u8 = 0xff;
+
+/// Number of entries in the string reference table
+const STRING_TABLE_SIZE: usize = 15000;
+/// Maximum length of strings (without terminators) stored in the string
+/// reference table
+const MAX_STRING_TABLE_ENTRY_LEN: usize = 250;
+
+/// Coordinates in o5m are stored in 100 nanodegrees.
+const COORD_FACTOR: i64 = 100;
+
+/// Errors which can occur while reading an o5m file.
+#[derive(Debug)]
+pub enum Error {
+    /// The input could not be read.
+    Io(io::Error),
+    /// The input does not start with an o5m header.
+    InvalidHeader,
+    /// The input contains changes (o5c), which are not supported.
+    Changes,
+    /// A dataset could not be decoded.
+    MalformedDataset {
+        position: usize,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "failed to read o5m: {}", e),
+            Error::InvalidHeader => write!(f, "input is not an o5m file"),
+            Error::Changes => write!(f, "o5c change files are not supported"),
+            Error::MalformedDataset { position, reason } => {
+                write!(f, "o5m dataset at byte {}: {}", position, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Converts o5m to uncompressed PBF.
+pub fn to_pbf<R: BufRead>(input: R) -> Result<Vec<u8>, Error> {
+    let mut reader = Reader::new(input)?;
+    // the header information precedes the first entity
+    let mut entity = reader.next_entity()?;
+    let mut writer = PbfWriter::new(Vec::new(), reader.header())?;
+    while let Some(e) = entity {
+        writer.write(e)?;
+        entity = reader.next_entity()?;
+    }
+    Ok(writer.finish()?)
+}
+
+/// Table of recently used strings, which are referenced by their age.
+#[derive(Debug, Default)]
+struct StringTable {
+    entries: Vec<Vec<u8>>,
+    next: usize,
+}
+
+impl StringTable {
+    fn add(&mut self, entry: &[u8]) {
+        if self.entries.len() < STRING_TABLE_SIZE {
+            self.entries.push(entry.to_vec());
+        } else {
+            self.entries[self.next] = entry.to_vec();
+        }
+        self.next = (self.next + 1) % STRING_TABLE_SIZE;
+    }
+
+    /// Returns the `n`-th most recently added entry (starting with 1).
+    fn get(&self, n: u64) -> Option<&[u8]> {
+        if n == 0 || n as usize > self.entries.len() {
+            return None;
+        }
+        let idx = (self.next + STRING_TABLE_SIZE - n as usize) % STRING_TABLE_SIZE;
+        Some(&self.entries[idx])
+    }
+}
+
+/// Delta coded values, which are reset by a reset dataset.
+#[derive(Debug, Default)]
+struct Deltas {
+    id: i64,
+    timestamp: i64,
+    changeset: i64,
+    lon: i64,
+    lat: i64,
+    way_node_id: i64,
+    member_ids: [i64; 3],
+}
+
+/// Streaming reader of entities from o5m.
+pub struct Reader<R: BufRead> {
+    input: R,
+    position: usize,
+    buf: Vec<u8>,
+    header: osmpbf::HeaderBlock,
+    strings: StringTable,
+    deltas: Deltas,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Creates a new reader and checks the header of the input.
+    pub fn new(mut input: R) -> Result<Self, Error> {
+        let mut magic = [0; 7];
+        input.read_exact(&mut magic).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::InvalidHeader,
+            _ => Error::Io(e),
+        })?;
+        if magic == O5C_HEADER {
+            return Err(Error::Changes);
+        } else if magic != O5M_HEADER {
+            return Err(Error::InvalidHeader);
+        }
+        Ok(Self {
+            input,
+            position: magic.len(),
+            buf: Vec::new(),
+            header: osmpbf::HeaderBlock::default(),
+            strings: StringTable::default(),
+            deltas: Deltas::default(),
+        })
+    }
+
+    /// Header information (bounding box and file timestamp) read so far.
+    pub fn header(&self) -> &osmpbf::HeaderBlock {
+        &self.header
+    }
+
+    /// Reads the next entity, or returns `None` at the end of the input.
+    ///
+    /// Deleted entities (without coordinates or references) are skipped.
+    pub fn next_entity(&mut self) -> Result<Option<Entity>, Error> {
+        loop {
+            let position = self.position;
+            let dataset_type = match self.read_byte()? {
+                Some(DATASET_END_OF_FILE) | None => return Ok(None),
+                Some(DATASET_RESET) => {
+                    self.strings = StringTable::default();
+                    self.deltas = Deltas::default();
+                    continue;
+                }
+                // other datasets without length (e.g. sync, jump) are ignored
+                Some(x) if x >= 0xf0 => continue,
+                Some(x) => x,
+            };
+            self.read_dataset(position)?;
+
+            let mut buf = std::mem::take(&mut self.buf);
+            let result = self.decode_dataset(dataset_type, &buf, position);
+            std::mem::swap(&mut self.buf, &mut buf);
+            if let Some(entity) = result? {
+                return Ok(Some(entity));
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Error> {
+        let mut byte = [0];
+        match self.input.read(&mut byte)? {
+            0 => Ok(None),
+            _ => {
+                self.position += 1;
+                Ok(Some(byte[0]))
+            }
+        }
+    }
+
+    /// Reads the length and the payload of a dataset into `self.buf`.
+    fn read_dataset(&mut self, position: usize) -> Result<(), Error> {
+        let truncated = || Error::MalformedDataset {
+            position,
+            reason: "unexpected end of input",
+        };
+        let mut len: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?.ok_or_else(truncated)?;
+            len |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        self.buf.clear();
+        (&mut self.input).take(len).read_to_end(&mut self.buf)?;
+        if self.buf.len() as u64 != len {
+            return Err(truncated());
+        }
+        self.position += self.buf.len();
+        Ok(())
+    }
+
+    fn decode_dataset(
+        &mut self,
+        dataset_type: u8,
+        data: &[u8],
+        position: usize,
+    ) -> Result<Option<Entity>, Error> {
+        let mut decoder = Decoder { data, pos: 0 };
+        let result = match dataset_type {
+            DATASET_NODE => self.decode_node(&mut decoder),
+            DATASET_WAY => self.decode_way(&mut decoder),
+            DATASET_RELATION => self.decode_relation(&mut decoder),
+            DATASET_BOUNDING_BOX => self.decode_bounding_box(&mut decoder).map(|_| None),
+            DATASET_FILE_TIMESTAMP => decoder.zvarint().map(|timestamp| {
+                self.header.osmosis_replication_timestamp = Some(timestamp);
+                None
+            }),
+            // unknown datasets are skipped
+            _ => Ok(None),
+        };
+        result.map_err(|reason| Error::MalformedDataset { position, reason })
+    }
+
+    fn decode_node(&mut self, decoder: &mut Decoder) -> Result<Option<Entity>, &'static str> {
+        let id = delta(&mut self.deltas.id, decoder.zvarint()?);
+        let info = self.decode_info(decoder)?;
+        if decoder.is_empty() {
+            return Ok(None); // deleted
+        }
+        let lon = delta(&mut self.deltas.lon, decoder.zvarint()?);
+        let lat = delta(&mut self.deltas.lat, decoder.zvarint()?);
+        Ok(Some(Entity::Node(Node {
+            id,
+            lat: lat * COORD_FACTOR,
+            lon: lon * COORD_FACTOR,
+            tags: self.decode_tags(decoder)?,
+            info,
+        })))
+    }
+
+    fn decode_way(&mut self, decoder: &mut Decoder) -> Result<Option<Entity>, &'static str> {
+        let id = delta(&mut self.deltas.id, decoder.zvarint()?);
+        let info = self.decode_info(decoder)?;
+        if decoder.is_empty() {
+            return Ok(None); // deleted
+        }
+        let mut refs_decoder = decoder.section()?;
+        let mut refs = Vec::new();
+        while !refs_decoder.is_empty() {
+            refs.push(delta(&mut self.deltas.way_node_id, refs_decoder.zvarint()?));
+        }
+        Ok(Some(Entity::Way(Way {
+            id,
+            refs,
+            tags: self.decode_tags(decoder)?,
+            info,
+        })))
+    }
+
+    fn decode_relation(&mut self, decoder: &mut Decoder) -> Result<Option<Entity>, &'static str> {
+        let id = delta(&mut self.deltas.id, decoder.zvarint()?);
+        let info = self.decode_info(decoder)?;
+        if decoder.is_empty() {
+            return Ok(None); // deleted
+        }
+        let mut members_decoder = decoder.section()?;
+        let mut members = Vec::new();
+        while !members_decoder.is_empty() {
+            let member_id = members_decoder.zvarint()?;
+            let entry = self.decode_string(&mut members_decoder, 1)?;
+            let (member_type, idx) = match entry.first() {
+                Some(b'0') => (MemberType::Node, 0),
+                Some(b'1') => (MemberType::Way, 1),
+                Some(b'2') => (MemberType::Relation, 2),
+                _ => return Err("invalid member type"),
+            };
+            let role = str::from_utf8(&entry[1..entry.len() - 1])
+                .map_err(|_| "role is not valid UTF-8")?;
+            members.push(Member {
+                member_type,
+                id: delta(&mut self.deltas.member_ids[idx], member_id),
+                role: role.to_string(),
+            });
+        }
+        Ok(Some(Entity::Relation(Relation {
+            id,
+            members,
+            tags: self.decode_tags(decoder)?,
+            info,
+        })))
+    }
+
+    fn decode_bounding_box(&mut self, decoder: &mut Decoder) -> Result<(), &'static str> {
+        let left = decoder.zvarint()? * COORD_FACTOR;
+        let bottom = decoder.zvarint()? * COORD_FACTOR;
+        let right = decoder.zvarint()? * COORD_FACTOR;
+        let top = decoder.zvarint()? * COORD_FACTOR;
+        self.header.bbox = Some(osmpbf::HeaderBBox {
+            left,
+            right,
+            top,
+            bottom,
+        });
+        Ok(())
+    }
+
+    /// Decodes the version and author information of an entity.
+    fn decode_info(&mut self, decoder: &mut Decoder) -> Result<Option<Info>, &'static str> {
+        let version = decoder.varint()?;
+        if version == 0 {
+            return Ok(None);
+        }
+        let mut info = Info {
+            version: version as i32,
+            ..Default::default()
+        };
+        info.timestamp = delta(&mut self.deltas.timestamp, decoder.zvarint()?);
+        if info.timestamp == 0 {
+            return Ok(Some(info));
+        }
+        info.changeset = delta(&mut self.deltas.changeset, decoder.zvarint()?);
+        let (uid, user) = self.decode_user(decoder)?;
+        info.uid = uid as i32;
+        info.user = user;
+        Ok(Some(info))
+    }
+
+    /// Decodes the `(uid, user)` pair of an entity.
+    ///
+    /// The uid is stored as varint instead of a string.
+    fn decode_user(&mut self, decoder: &mut Decoder) -> Result<(u64, String), &'static str> {
+        let entry = if decoder.peek()? == 0 {
+            decoder.pos += 1;
+            let start = decoder.pos;
+            let uid = decoder.varint()?;
+            decoder.expect_zero()?;
+            if uid == 0 {
+                // anonymous user without name
+                self.strings.add(&decoder.data[start..decoder.pos]);
+                return Ok((0, String::new()));
+            }
+            decoder.skip_string()?;
+            let entry = &decoder.data[start..decoder.pos];
+            if entry.len() - 2 <= MAX_STRING_TABLE_ENTRY_LEN {
+                self.strings.add(entry);
+            }
+            entry
+        } else {
+            let n = decoder.varint()?;
+            self.strings.get(n).ok_or("invalid string reference")?
+        };
+
+        let mut entry_decoder = Decoder {
+            data: entry,
+            pos: 0,
+        };
+        let uid = entry_decoder.varint()?;
+        entry_decoder.expect_zero()?;
+        let user = &entry[entry_decoder.pos..];
+        let user = user.split(|&c| c == 0).next().unwrap_or_default();
+        let user = str::from_utf8(user).map_err(|_| "user is not valid UTF-8")?;
+        Ok((uid, user.to_string()))
+    }
+
+    /// Decodes an inline or referenced string entry consisting of `parts`
+    /// zero terminated strings.
+    ///
+    /// The returned entry includes the terminators.
+    fn decode_string(
+        &mut self,
+        decoder: &mut Decoder,
+        parts: usize,
+    ) -> Result<Vec<u8>, &'static str> {
+        if decoder.peek()? == 0 {
+            decoder.pos += 1;
+            let start = decoder.pos;
+            for _ in 0..parts {
+                decoder.skip_string()?;
+            }
+            let entry = &decoder.data[start..decoder.pos];
+            if entry.len() - parts <= MAX_STRING_TABLE_ENTRY_LEN {
+                self.strings.add(entry);
+            }
+            Ok(entry.to_vec())
+        } else {
+            let n = decoder.varint()?;
+            let entry = self.strings.get(n).ok_or("invalid string reference")?;
+            if entry.iter().filter(|&&c| c == 0).count() < parts {
+                return Err("invalid string reference");
+            }
+            Ok(entry.to_vec())
+        }
+    }
+
+    fn decode_tags(&mut self, decoder: &mut Decoder) -> Result<Tags, &'static str> {
+        let mut tags = Tags::new();
+        while !decoder.is_empty() {
+            let entry = self.decode_string(decoder, 2)?;
+            let mut parts = entry.split(|&c| c == 0);
+            let mut next = || {
+                str::from_utf8(parts.next().unwrap_or_default())
+                    .map(String::from)
+                    .map_err(|_| "tag is not valid UTF-8")
+            };
+            tags.push((next()?, next()?));
+        }
+        Ok(tags)
+    }
+}
+
+fn delta(value: &mut i64, delta: i64) -> i64 {
+    *value += delta;
+    *value
+}
+
+/// Decodes the payload of a dataset.
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn peek(&self) -> Result<u8, &'static str> {
+        self.data
+            .get(self.pos)
+            .cloned()
+            .ok_or("unexpected end of dataset")
+    }
+
+    fn varint(&mut self) -> Result<u64, &'static str> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.peek()?;
+            self.pos += 1;
+            result |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err("invalid varint")
+    }
+
+    fn zvarint(&mut self) -> Result<i64, &'static str> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn expect_zero(&mut self) -> Result<(), &'static str> {
+        match self.peek()? {
+            0 => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err("missing string terminator"),
+        }
+    }
+
+    /// Skips a zero terminated string including the terminator.
+    fn skip_string(&mut self) -> Result<(), &'static str> {
+        let len = self.data[self.pos.min(self.data.len())..]
+            .iter()
+            .position(|&c| c == 0)
+            .ok_or("missing string terminator")?;
+        self.pos += len + 1;
+        Ok(())
+    }
+
+    /// Splits off a section prefixed by its length.
+    fn section(&mut self) -> Result<Decoder<'a>, &'static str> {
+        let len = self.varint()? as usize;
+        let end = self.pos.checked_add(len).ok_or("invalid section length")?;
+        let data = self
+            .data
+            .get(self.pos..end)
+            .ok_or("unexpected end of dataset")?;
+        self.pos = end;
+        Ok(Decoder { data, pos: 0 })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::changes::{read_header, read_node, read_relation, read_way};
+    use crate::testing::compile_pbf;
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut result = Vec::new();
+        while value >= 0x80 {
+            result.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        result.push(value as u8);
+        result
+    }
+
+    fn zvarint(value: i64) -> Vec<u8> {
+        varint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn dataset(dataset_type: u8, parts: &[&[u8]]) -> Vec<u8> {
+        let payload = parts.concat();
+        let mut result = vec![dataset_type];
+        result.extend(varint(payload.len() as u64));
+        result.extend(payload);
+        result
+    }
+
+    fn o5m() -> Vec<u8> {
+        let mut data = O5M_HEADER.to_vec();
+        data.extend(dataset(
+            DATASET_BOUNDING_BOX,
+            &[
+                &zvarint(133_000_000),
+                &zvarint(525_000_000),
+                &zvarint(134_000_000),
+                &zvarint(526_000_000),
+            ],
+        ));
+        data.extend(dataset(DATASET_FILE_TIMESTAMP, &[&zvarint(1_600_000_000)]));
+        // node 1 with version, author and an inline tag
+        data.extend(dataset(
+            DATASET_NODE,
+            &[
+                &zvarint(1),
+                &varint(3),
+                &zvarint(1_500_000_000),
+                &zvarint(42),
+                b"\x00\x07\x00alice\x00",
+                &zvarint(133_100_000),
+                &zvarint(525_100_000),
+                b"\x00name\x00A\x00",
+            ],
+        ));
+        // node 3 without version, referencing the tag of node 1
+        data.extend(dataset(
+            DATASET_NODE,
+            &[
+                &zvarint(2),
+                &varint(0),
+                &zvarint(-1),
+                &zvarint(-2),
+                &varint(1),
+            ],
+        ));
+        // deleted node 4
+        data.extend(dataset(DATASET_NODE, &[&zvarint(1), &varint(0)]));
+        data.push(DATASET_RESET);
+        let refs = [zvarint(1), zvarint(2), zvarint(-2)].concat();
+        data.extend(dataset(
+            DATASET_WAY,
+            &[
+                &zvarint(10),
+                &varint(0),
+                &varint(refs.len() as u64),
+                &refs,
+                b"\x00highway\x00path\x00",
+            ],
+        ));
+        let members = [
+            zvarint(10),
+            b"\x001outer\x00".to_vec(),
+            zvarint(1),
+            b"\x000\x00".to_vec(),
+            zvarint(2),
+            varint(2), // reference to "1outer"
+        ]
+        .concat();
+        data.extend(dataset(
+            DATASET_RELATION,
+            &[
+                &zvarint(100),
+                &varint(0),
+                &varint(members.len() as u64),
+                &members,
+                &varint(3), // reference to ("highway", "path")
+            ],
+        ));
+        data.push(DATASET_END_OF_FILE);
+        data
+    }
+
+    /// Entities contained in `o5m()`.
+    fn expected_entities() -> Vec<Entity> {
+        let tag = |k: &str, v: &str| (k.to_string(), v.to_string());
+        vec![
+            Entity::Node(Node {
+                id: 1,
+                lat: 52_510_000_000,
+                lon: 13_310_000_000,
+                tags: vec![tag("name", "A")],
+                info: Some(Info {
+                    version: 3,
+                    timestamp: 1_500_000_000,
+                    changeset: 42,
+                    uid: 7,
+                    user: "alice".into(),
+                    visible: None,
+                }),
+            }),
+            Entity::Node(Node {
+                id: 3,
+                lat: 52_509_999_800,
+                lon: 13_309_999_900,
+                tags: vec![tag("name", "A")],
+                info: None,
+            }),
+            Entity::Way(Way {
+                id: 10,
+                refs: vec![1, 3, 1],
+                tags: vec![tag("highway", "path")],
+                info: None,
+            }),
+            Entity::Relation(Relation {
+                id: 110,
+                members: vec![
+                    Member {
+                        member_type: MemberType::Way,
+                        id: 10,
+                        role: "outer".into(),
+                    },
+                    Member {
+                        member_type: MemberType::Node,
+                        id: 1,
+                        role: "".into(),
+                    },
+                    Member {
+                        member_type: MemberType::Way,
+                        id: 12,
+                        role: "outer".into(),
+                    },
+                ],
+                tags: vec![tag("highway", "path")],
+                info: None,
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_read_entities() {
+        let data = o5m();
+        let mut reader = Reader::new(&data[..]).unwrap();
+        let mut entities = Vec::new();
+        while let Some(entity) = reader.next_entity().unwrap() {
+            entities.push(entity);
+        }
+
+        assert_eq!(
+            reader.header().bbox,
+            Some(osmpbf::HeaderBBox {
+                left: 13_300_000_000,
+                right: 13_400_000_000,
+                top: 52_600_000_000,
+                bottom: 52_500_000_000,
+            })
+        );
+        assert_eq!(
+            reader.header().osmosis_replication_timestamp,
+            Some(1_600_000_000)
+        );
+
+        assert_eq!(entities, expected_entities());
+    }
+
+    /// Header and entities of an archive compiled from PBF data, together
+    /// with the number of unresolved references of each entity.
+    fn compile_and_read(data: &[u8]) -> (osmpbf::HeaderBlock, Vec<(Entity, usize)>) {
+        let (_dir, archive) = compile_pbf(data, &["--with-metadata"]);
+        let nodes =
+            (0..archive.nodes().len()).map(|idx| (Entity::Node(read_node(&archive, idx)), 0));
+        let ways = (0..archive.ways().len()).map(|idx| {
+            let (way, num_unresolved) = read_way(&archive, idx);
+            (Entity::Way(way), num_unresolved)
+        });
+        let relations = (0..archive.relations().len()).map(|idx| {
+            let (relation, num_unresolved) = read_relation(&archive, idx);
+            (Entity::Relation(relation), num_unresolved)
+        });
+        let entities = nodes.chain(ways).chain(relations).collect();
+        (read_header(&archive), entities)
+    }
+
+    #[test]
+    fn test_compile_like_pbf() {
+        // the same data encoded independently as o5m and PBF, see
+        // testdata/generate_sample.py
+        let o5m = include_bytes!("../testdata/sample.o5m");
+        let pbf = include_bytes!("../testdata/sample.osm.pbf");
+
+        let (header, entities) = compile_and_read(&to_pbf(&o5m[..]).unwrap());
+        assert_eq!(compile_and_read(pbf), (header.clone(), entities.clone()));
+
+        assert_eq!(header.osmosis_replication_timestamp, Some(1_600_000_000));
+        assert_eq!(entities.len(), 5 + 2 + 2);
+        let cafe = entities.iter().find_map(|(entity, _)| match entity {
+            Entity::Node(node) if node.id == 106 => Some(node),
+            _ => None,
+        });
+        assert_eq!(
+            cafe.map(|node| (node.tags[1].1.as_str(), node.info.clone().unwrap().user)),
+            Some(("Café Ümlaut", "carol".to_string()))
+        );
+        // the way referencing a missing node
+        assert!(entities.iter().any(|(entity, num_unresolved)| {
+            matches!(entity, Entity::Way(way) if way.id == 201) && *num_unresolved == 1
+        }));
+    }
+
+    #[test]
+    fn test_reset_clears_string_table() {
+        let mut data = O5M_HEADER.to_vec();
+        let node = |id, tag: &[u8]| {
+            dataset(
+                DATASET_NODE,
+                &[&zvarint(id), &varint(0), &zvarint(0), &zvarint(0), tag],
+            )
+        };
+        data.extend(node(1, b"\x00a\x00b\x00"));
+        data.extend(node(1, &varint(1)));
+        data.push(DATASET_RESET);
+        let position = data.len();
+        // the tag of the first node is not in the string table anymore
+        data.extend(node(1, &varint(1)));
+
+        let mut reader = Reader::new(&data[..]).unwrap();
+        for _ in 0..2 {
+            assert!(reader.next_entity().unwrap().is_some());
+        }
+        match reader.next_entity() {
+            Err(Error::MalformedDataset {
+                position: p,
+                reason: "invalid string reference",
+            }) if p == position => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_references_after_reset() {
+        let mut data = O5M_HEADER.to_vec();
+        let info = |timestamp, changeset, user: &[u8]| {
+            [&varint(1), &zvarint(timestamp), &zvarint(changeset), user].concat()
+        };
+        data.extend(dataset(
+            DATASET_NODE,
+            &[
+                &zvarint(1),
+                &info(1000, 10, b"\x00\x01\x00alice\x00"),
+                &zvarint(20),
+                &zvarint(30),
+                b"\x00a\x00b\x00",
+            ],
+        ));
+        data.push(DATASET_RESET);
+        // delta coded values start again from zero, and references count only
+        // the strings added after the reset
+        data.extend(dataset(
+            DATASET_NODE,
+            &[
+                &zvarint(5),
+                &info(2000, 20, b"\x00\x02\x00bob\x00"),
+                &zvarint(50),
+                &zvarint(60),
+                b"\x00c\x00d\x00",
+            ],
+        ));
+        data.extend(dataset(
+            DATASET_NODE,
+            &[
+                &zvarint(1),
+                &info(1, 1, &varint(2)), // reference to (2, "bob")
+                &zvarint(1),
+                &zvarint(1),
+                &varint(1), // reference to ("c", "d")
+            ],
+        ));
+        data.push(DATASET_RESET);
+        let refs = [zvarint(6), zvarint(-1)].concat();
+        data.extend(dataset(
+            DATASET_WAY,
+            &[
+                &zvarint(10),
+                &info(3000, 30, b"\x00\x01\x00alice\x00"),
+                &varint(refs.len() as u64),
+                &refs,
+                b"\x00e\x00f\x00",
+            ],
+        ));
+        data.extend(dataset(
+            DATASET_WAY,
+            &[
+                &zvarint(1),
+                &info(1, 1, &varint(2)), // reference to (1, "alice")
+                &varint(1),
+                &zvarint(-1),
+                &varint(1), // reference to ("e", "f")
+            ],
+        ));
+
+        let mut reader = Reader::new(&data[..]).unwrap();
+        let mut entities = Vec::new();
+        while let Some(entity) = reader.next_entity().unwrap() {
+            entities.push(entity);
+        }
+        let info = |timestamp, changeset, uid, user: &str| Info {
+            version: 1,
+            timestamp,
+            changeset,
+            uid,
+            user: user.into(),
+            visible: None,
+        };
+        let node = |id, lon, lat, tag: (&str, &str), info| {
+            Entity::Node(Node {
+                id,
+                lat: lat * COORD_FACTOR,
+                lon: lon * COORD_FACTOR,
+                tags: vec![(tag.0.into(), tag.1.into())],
+                info: Some(info),
+            })
+        };
+        assert_eq!(
+            entities,
+            vec![
+                node(1, 20, 30, ("a", "b"), info(1000, 10, 1, "alice")),
+                node(5, 50, 60, ("c", "d"), info(2000, 20, 2, "bob")),
+                node(6, 51, 61, ("c", "d"), info(2001, 21, 2, "bob")),
+                Entity::Way(Way {
+                    id: 10,
+                    refs: vec![6, 5],
+                    tags: vec![("e".into(), "f".into())],
+                    info: Some(info(3000, 30, 1, "alice")),
+                }),
+                Entity::Way(Way {
+                    id: 11,
+                    refs: vec![4],
+                    tags: vec![("e".into(), "f".into())],
+                    info: Some(info(3001, 31, 1, "alice")),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        match Reader::new(&b"\xff\xe0\x04o5c2"[..]) {
+            Err(Error::Changes) => (),
+            x => panic!("unexpected result: {:?}", x.err()),
+        }
+        match Reader::new(&b"<osm>"[..]) {
+            Err(Error::InvalidHeader) => (),
+            x => panic!("unexpected result: {:?}", x.err()),
+        }
+
+        let mut data = O5M_HEADER.to_vec();
+        data.extend(dataset(
+            DATASET_NODE,
+            &[&zvarint(1), &varint(0), &zvarint(1)],
+        ));
+        let mut reader = Reader::new(&data[..]).unwrap();
+        match reader.next_entity() {
+            Err(Error::MalformedDataset { position: 7, .. }) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+}
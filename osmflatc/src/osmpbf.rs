@@ -4,6 +4,8 @@ use log::info;
 use prost::{self, Message};
 use rayon::prelude::*;
 
+use std::borrow::Cow;
+use std::fmt;
 use std::io::{self, Cursor, Read};
 
 include!(concat!(env!("OUT_DIR"), "/osmpbf.rs"));
@@ -18,61 +20,107 @@ pub enum BlockType {
 }
 
 impl BlockType {
-    /// Decode block type from PrimitiveBlock protobuf message
+    /// Decode block types from PrimitiveBlock protobuf message
     ///
     /// This does not decode any fields, it just checks which tags are present
-    /// in PrimitiveGroup fields of the message.
+    /// in PrimitiveGroup fields of the message. A block may contain several
+    /// groups of different types, therefore the types of all groups are
+    /// returned (sorted and without duplicates).
     ///
     /// `blob` should contain decompressed data of an OSMData PrimitiveBlock.
+    /// `offset` is the position of the blob in the input used for error
+    /// reporting.
     ///
     /// Note: We use public API of `prost` crate, which though is not exposed in
     /// the crate and marked with comment that it should be only used from
     /// `prost::Message`.
-    pub fn from_osmdata_blob(blob: &[u8]) -> io::Result<BlockType> {
+    pub fn from_osmdata_blob(blob: &[u8], offset: usize) -> Result<Vec<BlockType>, Error> {
         const PRIMITIVE_GROUP_TAG: u32 = 2;
-        const NODES_TAG: u32 = 1;
-        const DENSE_NODES_TAG: u32 = 2;
-        const WAY_STAG: u32 = 3;
-        const RELATIONS_TAG: u32 = 4;
-        const CHANGESETS_TAG: u32 = 5;
 
-        let mut cursor = Cursor::new(&blob[..]);
-        loop {
+        let decode_error = |source| Error::Decode { offset, source };
+
+        let mut block_types = Vec::new();
+        let mut cursor = Cursor::new(blob);
+        while (cursor.position() as usize) < blob.len() {
             // decode fields of PrimitiveBlock
-            let (key, wire_type) = prost::encoding::decode_key(&mut cursor)?;
+            let (key, wire_type) =
+                prost::encoding::decode_key(&mut cursor).map_err(decode_error)?;
             if key != PRIMITIVE_GROUP_TAG {
-                // primitive group
                 prost::encoding::skip_field(
                     wire_type,
                     key,
                     &mut cursor,
                     prost::encoding::DecodeContext::default(),
-                )?;
+                )
+                .map_err(decode_error)?;
                 continue;
             }
 
-            // We found a PrimitiveGroup field. There could be several of them, but
-            // follwoing the specs of OSMPBF, all of them will have the same single
-            // optional field, which defines the type of the block.
+            // We found a PrimitiveGroup field. There could be several of them, and
+            // each of them might be of a different type.
+            let group_len =
+                prost::encoding::decode_varint(&mut cursor).map_err(decode_error)? as usize;
+            let group_start = cursor.position() as usize;
+            let group = blob
+                .get(group_start..group_start.saturating_add(group_len))
+                .ok_or_else(|| Error::MalformedBlock {
+                    offset,
+                    reason: "truncated primitive group".into(),
+                })?;
+            Self::from_primitive_group(group, offset, &mut block_types)?;
+            cursor.set_position((group_start + group_len) as u64);
+        }
+
+        block_types.sort();
+        block_types.dedup();
+        Ok(block_types)
+    }
+
+    /// Decode block types from fields of a single PrimitiveGroup message
+    ///
+    /// Following the specs of OSMPBF, a group contains a single type of
+    /// entities, however we do not rely on that and collect the types of all
+    /// present fields.
+    fn from_primitive_group(
+        group: &[u8],
+        offset: usize,
+        block_types: &mut Vec<BlockType>,
+    ) -> Result<(), Error> {
+        const NODES_TAG: u32 = 1;
+        const DENSE_NODES_TAG: u32 = 2;
+        const WAY_STAG: u32 = 3;
+        const RELATIONS_TAG: u32 = 4;
+        const CHANGESETS_TAG: u32 = 5;
+
+        let decode_error = |source| Error::Decode { offset, source };
 
-            // Decode the number of primitive groups.
-            let _ = prost::encoding::decode_varint(&mut cursor)?;
-            // Decode the tag of the first primitive group defining the type.
-            let (tag, _wire_type) = prost::encoding::decode_key(&mut cursor)?;
+        let mut cursor = Cursor::new(group);
+        while (cursor.position() as usize) < group.len() {
+            let (tag, wire_type) =
+                prost::encoding::decode_key(&mut cursor).map_err(decode_error)?;
             let block_type = match tag {
                 NODES_TAG => BlockType::Nodes,
                 DENSE_NODES_TAG => BlockType::DenseNodes,
                 WAY_STAG => BlockType::Ways,
                 RELATIONS_TAG => BlockType::Relations,
-                CHANGESETS_TAG => {
-                    panic!("found block containing unsupported changesets");
-                }
+                CHANGESETS_TAG => return Err(Error::Changesets { offset }),
                 _ => {
-                    panic!("invalid input data: malformed primitive block");
+                    return Err(Error::MalformedBlock {
+                        offset,
+                        reason: format!("unknown field {} in primitive group", tag),
+                    })
                 }
             };
-            return Ok(block_type);
+            block_types.push(block_type);
+            prost::encoding::skip_field(
+                wire_type,
+                tag,
+                &mut cursor,
+                prost::encoding::DecodeContext::default(),
+            )
+            .map_err(decode_error)?;
         }
+        Ok(())
     }
 }
 
@@ -83,14 +131,112 @@ pub struct BlockIndex {
     pub blob_len: usize,
 }
 
+/// Errors which can occur while reading a PBF file.
+///
+/// Each error carries the byte offset of the affected blob in the input.
+#[derive(Debug)]
+pub enum Error {
+    /// The input ends in the middle of a blob.
+    Truncated { offset: usize },
+    /// The header of a blob could not be decoded.
+    InvalidBlobHeader { offset: usize, reason: String },
+    /// The type of a blob is neither `OSMHeader` nor `OSMData`.
+    UnknownBlobType { offset: usize, blob_type: String },
+    /// A blob or the block contained in it could not be decoded.
+    Decode {
+        offset: usize,
+        source: prost::DecodeError,
+    },
+    /// The data of a blob is compressed with an unsupported algorithm.
+    UnsupportedCompression {
+        offset: usize,
+        compression: &'static str,
+    },
+    /// The data of a blob could not be decompressed.
+    Decompression { offset: usize, source: io::Error },
+    /// The size of the decompressed data does not match the size stored in
+    /// the blob.
+    RawSizeMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A primitive block contains changesets, which are not supported.
+    Changesets { offset: usize },
+    /// A primitive block does not follow the OSMPBF specification.
+    MalformedBlock { offset: usize, reason: String },
+}
+
+impl Error {
+    /// Byte offset of the affected blob in the input.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Error::Truncated { offset }
+            | Error::InvalidBlobHeader { offset, .. }
+            | Error::UnknownBlobType { offset, .. }
+            | Error::Decode { offset, .. }
+            | Error::UnsupportedCompression { offset, .. }
+            | Error::Decompression { offset, .. }
+            | Error::RawSizeMismatch { offset, .. }
+            | Error::Changesets { offset }
+            | Error::MalformedBlock { offset, .. } => offset,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "blob at offset {}: ", self.offset())?;
+        match self {
+            Error::Truncated { .. } => write!(f, "unexpected end of input"),
+            Error::InvalidBlobHeader { reason, .. } => write!(f, "invalid blob header: {}", reason),
+            Error::UnknownBlobType { blob_type, .. } => {
+                write!(f, "unknown blob type {:?}", blob_type)
+            }
+            Error::Decode { source, .. } => write!(f, "failed to decode: {}", source),
+            Error::UnsupportedCompression {
+                compression: "unknown",
+                ..
+            } => write!(f, "unknown compression"),
+            Error::UnsupportedCompression { compression, .. } => write!(
+                f,
+                "unsupported compression {0}, osmflatc must be built with feature `{0}`",
+                compression
+            ),
+            Error::Decompression { source, .. } => write!(f, "failed to decompress: {}", source),
+            Error::RawSizeMismatch {
+                expected, actual, ..
+            } => write!(
+                f,
+                "decompressed size {} does not match raw size {}",
+                actual, expected
+            ),
+            Error::Changesets { .. } => write!(f, "changesets are not supported"),
+            Error::MalformedBlock { reason, .. } => {
+                write!(f, "malformed primitive block: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode { source, .. } => Some(source),
+            Error::Decompression { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 struct BlockIndexIterator<'a> {
     data: &'a [u8],
     cursor: usize,
 }
 
-enum BlobInfo {
+enum BlobInfo<'a> {
     Header(BlockIndex),
-    Unknown(usize, usize, Vec<u8>),
+    Unknown(usize, &'a [u8]),
 }
 
 impl<'a> BlockIndexIterator<'a> {
@@ -98,130 +244,476 @@ impl<'a> BlockIndexIterator<'a> {
         Self { data, cursor: 0 }
     }
 
-    fn read(&mut self, len: usize) -> &[u8] {
-        let data = &self.data[self.cursor..self.cursor + len];
+    fn read(&mut self, len: usize) -> Option<&'a [u8]> {
+        let data = self.data.get(self.cursor..self.cursor.checked_add(len)?)?;
         self.cursor += len;
-        data
+        Some(data)
     }
 
-    fn next_blob(&mut self) -> Result<BlobInfo, io::Error> {
+    fn next_blob(&mut self) -> Result<BlobInfo<'a>, Error> {
+        let offset = self.cursor;
+        let truncated = || Error::Truncated { offset };
+
         // read size of blob header
-        let blob_header_len: i32 = NetworkEndian::read_i32(self.read(4));
+        let blob_header_len = NetworkEndian::read_i32(self.read(4).ok_or_else(truncated)?);
+        if blob_header_len < 0 {
+            return Err(Error::InvalidBlobHeader {
+                offset,
+                reason: format!("negative size {}", blob_header_len),
+            });
+        }
 
         // read blob header
-        let blob_header = BlobHeader::decode(self.read(blob_header_len as usize))?;
+        let blob_header =
+            BlobHeader::decode(self.read(blob_header_len as usize).ok_or_else(truncated)?)
+                .map_err(|e| Error::InvalidBlobHeader {
+                    offset,
+                    reason: e.to_string(),
+                })?;
+        if blob_header.datasize < 0 {
+            return Err(Error::InvalidBlobHeader {
+                offset,
+                reason: format!("negative data size {}", blob_header.datasize),
+            });
+        }
 
         let blob_start = self.cursor;
         let blob_len = blob_header.datasize as usize;
+        let blob = self.read(blob_len).ok_or_else(truncated)?;
 
         if blob_header.r#type == "OSMHeader" {
-            self.cursor += blob_len;
             Ok(BlobInfo::Header(BlockIndex {
                 block_type: BlockType::Header,
                 blob_start,
                 blob_len,
             }))
         } else if blob_header.r#type == "OSMData" {
-            // read blob
-            Ok(BlobInfo::Unknown(
-                blob_start,
-                blob_len,
-                self.read(blob_header.datasize as usize).to_vec(),
-            ))
+            Ok(BlobInfo::Unknown(blob_start, blob))
         } else {
-            panic!("unknown blob type");
+            Err(Error::UnknownBlobType {
+                offset,
+                blob_type: blob_header.r#type,
+            })
         }
     }
 }
 
 impl<'a> Iterator for BlockIndexIterator<'a> {
-    type Item = Result<BlobInfo, io::Error>;
+    type Item = Result<BlobInfo<'a>, Error>;
     fn next(&mut self) -> Option<Self::Item> {
         if self.cursor < self.data.len() {
-            Some(self.next_blob())
+            let blob = self.next_blob();
+            if blob.is_err() {
+                // the position of the next blob is unknown, so we cannot continue
+                self.cursor = self.data.len();
+            }
+            Some(blob)
         } else {
             None
         }
     }
 }
 
-pub fn read_block<T: prost::Message + Default>(
-    data: &[u8],
-    idx: &BlockIndex,
-) -> Result<T, io::Error> {
-    let blob = Blob::decode(&data[idx.blob_start..idx.blob_start + idx.blob_len])?;
-
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
-        // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
-        let mut decoder = ZlibDecoder::new(&data[..]);
-        decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
+/// Decompresses the data of a blob.
+///
+/// `offset` is the position of the blob in the input used for error reporting.
+fn decompress_blob(blob: &Blob, offset: usize) -> Result<Cow<'_, [u8]>, Error> {
+    let data = if let Some(raw) = &blob.raw {
+        Cow::Borrowed(&raw[..])
+    } else if let Some(data) = &blob.zlib_data {
+        Cow::Owned(decompress_zlib(data, offset)?)
+    } else if let Some(data) = &blob.lzma_data {
+        Cow::Owned(decompress_lzma(data, offset)?)
+    } else if let Some(data) = &blob.lz4_data {
+        Cow::Owned(decompress_lz4(data, blob.raw_size, offset)?)
+    } else if let Some(data) = &blob.zstd_data {
+        Cow::Owned(decompress_zstd(data, offset)?)
     } else {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "unknown compression",
-        ));
+        return Err(Error::UnsupportedCompression {
+            offset,
+            compression: "unknown",
+        });
     };
-    Ok(T::decode(blob_data.as_slice())?)
+
+    if let Some(raw_size) = blob.raw_size {
+        if raw_size as usize != data.len() {
+            return Err(Error::RawSizeMismatch {
+                offset,
+                expected: raw_size as usize,
+                actual: data.len(),
+            });
+        }
+    }
+    Ok(data)
 }
 
-fn blob_type_from_blob_info(
-    blob_start: usize,
-    blob_len: usize,
-    blob: Vec<u8>,
-) -> Result<BlockIndex, io::Error> {
-    let blob = Blob::decode(blob.as_slice())?;
-
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        // use raw bytes
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
-        // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
-        let mut decoder = ZlibDecoder::new(&data[..]);
-        decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
-    } else {
-        panic!("can only read raw or zlib compressed blob");
-    };
-    assert_eq!(
-        blob_data.len(),
-        blob.raw_size.unwrap_or_else(|| blob_data.len() as i32) as usize
-    );
-
-    Ok(BlockIndex {
-        block_type: BlockType::from_osmdata_blob(&blob_data[..])?,
-        blob_start,
-        blob_len,
+fn decompress_zlib(data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut result)
+        .map_err(|source| Error::Decompression { offset, source })?;
+    Ok(result)
+}
+
+#[cfg(feature = "lzma")]
+fn decompress_lzma(data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    let stream =
+        xz2::stream::Stream::new_lzma_decoder(u64::MAX).map_err(|e| Error::Decompression {
+            offset,
+            source: e.into(),
+        })?;
+    let mut result = Vec::new();
+    xz2::read::XzDecoder::new_stream(data, stream)
+        .read_to_end(&mut result)
+        .map_err(|source| Error::Decompression { offset, source })?;
+    Ok(result)
+}
+
+#[cfg(not(feature = "lzma"))]
+fn decompress_lzma(_data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    Err(Error::UnsupportedCompression {
+        offset,
+        compression: "lzma",
+    })
+}
+
+#[cfg(feature = "lz4")]
+fn decompress_lz4(data: &[u8], raw_size: Option<i32>, offset: usize) -> Result<Vec<u8>, Error> {
+    // LZ4 data is stored in the block format, which does not contain the size of
+    // the decompressed data
+    let raw_size = raw_size.ok_or_else(|| Error::Decompression {
+        offset,
+        source: io::Error::new(io::ErrorKind::InvalidData, "missing raw size"),
+    })?;
+    lz4::block::decompress(data, Some(raw_size))
+        .map_err(|source| Error::Decompression { offset, source })
+}
+
+#[cfg(not(feature = "lz4"))]
+fn decompress_lz4(_data: &[u8], _raw_size: Option<i32>, offset: usize) -> Result<Vec<u8>, Error> {
+    Err(Error::UnsupportedCompression {
+        offset,
+        compression: "lz4",
+    })
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    zstd::decode_all(data).map_err(|source| Error::Decompression { offset, source })
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    Err(Error::UnsupportedCompression {
+        offset,
+        compression: "zstd",
     })
 }
 
-pub fn build_block_index(pbf_data: &[u8]) -> Vec<BlockIndex> {
-    let mut result: Vec<BlockIndex> = BlockIndexIterator::new(pbf_data)
+pub fn read_block<T: prost::Message + Default>(data: &[u8], idx: &BlockIndex) -> Result<T, Error> {
+    let offset = idx.blob_start;
+    let blob = data
+        .get(idx.blob_start..idx.blob_start + idx.blob_len)
+        .ok_or(Error::Truncated { offset })?;
+    let blob = Blob::decode(blob).map_err(|source| Error::Decode { offset, source })?;
+    let blob_data = decompress_blob(&blob, offset)?;
+    T::decode(&blob_data[..]).map_err(|source| Error::Decode { offset, source })
+}
+
+/// Determines the types of the block contained in a blob.
+///
+/// If `validate` is set, the block is also fully decoded to make sure that it
+/// can be read later.
+fn blob_types_from_blob_info(
+    blob_start: usize,
+    blob: &[u8],
+    validate: bool,
+) -> Result<Vec<BlockIndex>, Error> {
+    let offset = blob_start;
+    let blob_len = blob.len();
+    let blob = Blob::decode(blob).map_err(|source| Error::Decode { offset, source })?;
+    let blob_data = decompress_blob(&blob, offset)?;
+
+    if validate {
+        PrimitiveBlock::decode(&blob_data[..])
+            .map_err(|source| Error::Decode { offset, source })?;
+    }
+
+    // a block containing groups of different types is indexed once for each type
+    Ok(BlockType::from_osmdata_blob(&blob_data[..], offset)?
+        .into_iter()
+        .map(|block_type| BlockIndex {
+            block_type,
+            blob_start,
+            blob_len,
+        })
+        .collect())
+}
+
+/// Builds an index of all blocks in PBF data.
+///
+/// Fails with the error of the first (by offset) corrupt blob. If
+/// `skip_corrupt_blocks` is set, corrupt blobs are skipped instead, and their
+/// errors are returned sorted by offset together with the index.
+pub fn build_block_index(
+    pbf_data: &[u8],
+    skip_corrupt_blocks: bool,
+) -> Result<(Vec<BlockIndex>, Vec<Error>), Error> {
+    let results: Vec<Result<Vec<BlockIndex>, Error>> = BlockIndexIterator::new(pbf_data)
         .par_bridge()
-        .filter_map(|blob| {
-            let block = match blob {
-                Ok(BlobInfo::Header(b)) => Ok(b),
-                Ok(BlobInfo::Unknown(start, len, blob)) => {
-                    blob_type_from_blob_info(start, len, blob)
-                }
-                Err(e) => Err(e),
-            };
-            match block {
-                Ok(b) => Some(b),
-                Err(e) => {
-                    eprintln!("Skipping block due to error: {}", e);
-                    None
-                }
+        .map(|blob| match blob? {
+            BlobInfo::Header(b) => Ok(vec![b]),
+            BlobInfo::Unknown(start, blob) => {
+                blob_types_from_blob_info(start, blob, skip_corrupt_blocks)
             }
         })
         .collect();
+
+    let mut result = Vec::new();
+    let mut errors = Vec::new();
+    for blocks in results {
+        match blocks {
+            Ok(blocks) => result.extend(blocks),
+            Err(e) => errors.push(e),
+        }
+    }
+    errors.sort_by_key(|e| e.offset());
+    if !skip_corrupt_blocks && !errors.is_empty() {
+        return Err(errors.swap_remove(0));
+    }
+
     result.par_sort_unstable();
     info!("Found {} blocks", result.len());
-    result
+    Ok((result, errors))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(groups: Vec<PrimitiveGroup>) -> Vec<u8> {
+        let block = PrimitiveBlock {
+            primitivegroup: groups,
+            granularity: Some(100),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        block.encode(&mut data).unwrap();
+        data
+    }
+
+    fn dense_group() -> PrimitiveGroup {
+        PrimitiveGroup {
+            dense: Some(DenseNodes {
+                id: vec![1, 1],
+                lat: vec![0, 0],
+                lon: vec![0, 0],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn ways_group() -> PrimitiveGroup {
+        PrimitiveGroup {
+            ways: vec![Way {
+                id: 1,
+                refs: vec![1, 1],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn encode_blob(blob_type: &str, data: Vec<u8>) -> Vec<u8> {
+        let mut blob = Vec::new();
+        Blob {
+            raw: Some(data),
+            ..Default::default()
+        }
+        .encode(&mut blob)
+        .unwrap();
+        let mut header = Vec::new();
+        BlobHeader {
+            r#type: blob_type.into(),
+            indexdata: None,
+            datasize: blob.len() as i32,
+        }
+        .encode(&mut header)
+        .unwrap();
+
+        let mut result = (header.len() as u32).to_be_bytes().to_vec();
+        result.extend(header);
+        result.extend(blob);
+        result
+    }
+
+    #[test]
+    fn test_block_index() {
+        let mut pbf = encode_blob("OSMHeader", Vec::new());
+        pbf.extend(encode_blob("OSMData", encode(vec![ways_group()])));
+        let (index, skipped) = build_block_index(&pbf, false).unwrap();
+        let block_types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(block_types, vec![BlockType::Header, BlockType::Ways]);
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn test_block_index_of_truncated_input() {
+        let mut pbf = encode_blob("OSMHeader", Vec::new());
+        let offset = pbf.len();
+        let data = encode_blob("OSMData", encode(vec![ways_group()]));
+        pbf.extend(&data[..data.len() - 1]);
+
+        match build_block_index(&pbf, false) {
+            Err(Error::Truncated { offset: o }) => assert_eq!(o, offset),
+            x => panic!("unexpected result: {:?}", x),
+        }
+
+        let (index, skipped) = build_block_index(&pbf, true).unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].offset(), offset);
+    }
+
+    #[test]
+    fn test_block_index_with_corrupt_blobs() {
+        let mut pbf = encode_blob("OSMHeader", Vec::new());
+        pbf.extend(encode_blob("OSMData", vec![0xff; 8]));
+        pbf.extend(encode_blob("OSMData", encode(vec![ways_group()])));
+        pbf.extend(encode_blob(
+            "OSMData",
+            encode(vec![PrimitiveGroup {
+                changesets: vec![ChangeSet { id: 1 }],
+                ..Default::default()
+            }]),
+        ));
+
+        match build_block_index(&pbf, false) {
+            Err(Error::Decode { .. }) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+
+        let (index, skipped) = build_block_index(&pbf, true).unwrap();
+        let block_types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(block_types, vec![BlockType::Header, BlockType::Ways]);
+        assert_eq!(skipped.len(), 2);
+        assert!(matches!(skipped[0], Error::Decode { .. }));
+        assert!(matches!(skipped[1], Error::Changesets { .. }));
+    }
+
+    #[test]
+    fn test_block_index_with_unknown_blob_type() {
+        let pbf = encode_blob("OSMFoo", Vec::new());
+        match build_block_index(&pbf, false) {
+            Err(Error::UnknownBlobType {
+                offset: 0,
+                blob_type,
+            }) => assert_eq!(blob_type, "OSMFoo"),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_block_type_of_single_group() {
+        let data = encode(vec![ways_group()]);
+        assert_eq!(
+            BlockType::from_osmdata_blob(&data, 0).unwrap(),
+            vec![BlockType::Ways]
+        );
+    }
+
+    #[test]
+    fn test_block_types_of_mixed_groups() {
+        let data = encode(vec![ways_group(), dense_group(), ways_group()]);
+        assert_eq!(
+            BlockType::from_osmdata_blob(&data, 0).unwrap(),
+            vec![BlockType::DenseNodes, BlockType::Ways]
+        );
+    }
+
+    #[test]
+    fn test_block_types_of_empty_block() {
+        let data = encode(vec![PrimitiveGroup::default()]);
+        assert_eq!(BlockType::from_osmdata_blob(&data, 0).unwrap(), vec![]);
+    }
+
+    fn assert_decompresses_to(blob: Blob, expected: &[u8]) {
+        assert_eq!(&decompress_blob(&blob, 0).unwrap()[..], expected);
+    }
+
+    #[test]
+    fn test_decompress_zlib() {
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let data = encode(vec![ways_group()]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let blob = Blob {
+            raw_size: Some(data.len() as i32),
+            zlib_data: Some(encoder.finish().unwrap()),
+            ..Default::default()
+        };
+        assert_decompresses_to(blob, &data);
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn test_decompress_lzma() {
+        use std::io::Write;
+
+        let data = encode(vec![ways_group()]);
+        let options = xz2::stream::LzmaOptions::new_preset(6).unwrap();
+        let stream = xz2::stream::Stream::new_lzma_encoder(&options).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+        encoder.write_all(&data).unwrap();
+        let blob = Blob {
+            raw_size: Some(data.len() as i32),
+            lzma_data: Some(encoder.finish().unwrap()),
+            ..Default::default()
+        };
+        assert_decompresses_to(blob, &data);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_decompress_lz4() {
+        let data = encode(vec![ways_group()]);
+        let blob = Blob {
+            raw_size: Some(data.len() as i32),
+            lz4_data: Some(lz4::block::compress(&data, None, false).unwrap()),
+            ..Default::default()
+        };
+        assert_decompresses_to(blob, &data);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decompress_zstd() {
+        let data = encode(vec![ways_group()]);
+        let blob = Blob {
+            raw_size: Some(data.len() as i32),
+            zstd_data: Some(zstd::encode_all(&data[..], 0).unwrap()),
+            ..Default::default()
+        };
+        assert_decompresses_to(blob, &data);
+    }
+
+    #[test]
+    fn test_decompress_with_raw_size_mismatch() {
+        let blob = Blob {
+            raw: Some(vec![1, 2, 3]),
+            raw_size: Some(4),
+            ..Default::default()
+        };
+        match decompress_blob(&blob, 7) {
+            Err(Error::RawSizeMismatch {
+                offset: 7,
+                expected: 4,
+                actual: 3,
+            }) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
 }
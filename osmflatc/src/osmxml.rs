@@ -0,0 +1,752 @@
+use crate::entities::{Entity, Info, Member, Node, Relation, Way};
+use crate::osmpbf::{self, relation::MemberType};
+use crate::pbfwriter::PbfWriter;
+
+use quick_xml::events::{BytesStart, Event};
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str;
+
+/// Errors which can occur while reading an OSM XML file.
+///
+/// Each error carries the byte position in the (decompressed) input.
+#[derive(Debug)]
+pub enum Error {
+    /// The input is not well-formed XML or could not be read.
+    Xml {
+        position: usize,
+        source: quick_xml::Error,
+    },
+    /// The input ends inside of an entity.
+    UnexpectedEof { position: usize },
+    /// A required attribute of an element is missing.
+    MissingAttribute {
+        position: usize,
+        element: String,
+        attribute: &'static str,
+    },
+    /// The value of an attribute is invalid.
+    InvalidAttribute {
+        position: usize,
+        attribute: &'static str,
+        value: String,
+    },
+    /// An entity of an OsmChange file is not contained in a `create`,
+    /// `modify` or `delete` section.
+    MissingAction { position: usize },
+    /// The converted data could not be written.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Xml { position, source } => write!(f, "XML at byte {}: {}", position, source),
+            Error::UnexpectedEof { position } => {
+                write!(f, "XML at byte {}: unexpected end of input", position)
+            }
+            Error::MissingAttribute {
+                position,
+                element,
+                attribute,
+            } => write!(
+                f,
+                "XML at byte {}: element <{}> has no attribute {:?}",
+                position, element, attribute
+            ),
+            Error::InvalidAttribute {
+                position,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "XML at byte {}: invalid value {:?} of attribute {:?}",
+                position, value, attribute
+            ),
+            Error::MissingAction { position } => write!(
+                f,
+                "XML at byte {}: entity is not in a create, modify or delete section",
+                position
+            ),
+            Error::Io(e) => write!(f, "failed to write converted data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Xml { source, .. } => Some(source),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Converts OSM XML to uncompressed PBF.
+pub fn to_pbf<R: BufRead>(input: R) -> Result<Vec<u8>, Error> {
+    let mut reader = Reader::new(input);
+    // the header information precedes the first entity
+    let mut entity = reader.next_entity()?;
+    let mut writer = PbfWriter::new(Vec::new(), reader.header())?;
+    while let Some(e) = entity {
+        writer.write(e)?;
+        entity = reader.next_entity()?;
+    }
+    Ok(writer.finish()?)
+}
+
+/// Kind of a change in an OsmChange file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// Streaming reader of entities from OSM XML and OsmChange files.
+///
+/// Entities marked as deleted by JOSM (`action="delete"`) are skipped.
+pub struct Reader<R: BufRead> {
+    reader: quick_xml::Reader<R>,
+    buf: Vec<u8>,
+    header: osmpbf::HeaderBlock,
+    action: Option<Action>,
+    latest_timestamp: Option<i64>,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        let mut reader = quick_xml::Reader::from_reader(input);
+        reader.expand_empty_elements(true);
+        Self {
+            reader,
+            buf: Vec::new(),
+            header: osmpbf::HeaderBlock::default(),
+            action: None,
+            latest_timestamp: None,
+        }
+    }
+
+    /// Header information (generator and bounds) read so far.
+    pub fn header(&self) -> &osmpbf::HeaderBlock {
+        &self.header
+    }
+
+    /// Latest timestamp (in seconds since epoch) of the entities read so far.
+    pub fn latest_timestamp(&self) -> Option<i64> {
+        self.latest_timestamp
+    }
+
+    /// Reads the next change of an OsmChange file, or returns `None` at the
+    /// end of the input.
+    ///
+    /// Deleted entities may lack coordinates, in which case they are set to 0.
+    pub fn next_change(&mut self) -> Result<Option<(Action, Entity)>, Error> {
+        let entity = match self.next_entity()? {
+            Some(entity) => entity,
+            None => return Ok(None),
+        };
+        let position = self.reader.buffer_position();
+        let action = self.action.ok_or(Error::MissingAction { position })?;
+        Ok(Some((action, entity)))
+    }
+
+    /// Reads the next entity, or returns `None` at the end of the input.
+    pub fn next_entity(&mut self) -> Result<Option<Entity>, Error> {
+        let mut current = None;
+        let mut skip = false;
+        loop {
+            let position = self.reader.buffer_position();
+            self.buf.clear();
+            let event = self
+                .reader
+                .read_event(&mut self.buf)
+                .map_err(|source| Error::Xml { position, source })?;
+            match event {
+                Event::Start(e) => {
+                    let element = Element::new(&e, position)?;
+                    match e.name() {
+                        b"osm" | b"osmChange" => {
+                            self.header.writingprogram = element.get("generator").map(String::from);
+                        }
+                        b"create" => self.action = Some(Action::Create),
+                        b"modify" => self.action = Some(Action::Modify),
+                        b"delete" => self.action = Some(Action::Delete),
+                        b"bounds" => {
+                            self.header.bbox = Some(osmpbf::HeaderBBox {
+                                left: element.coordinate("minlon")?,
+                                right: element.coordinate("maxlon")?,
+                                top: element.coordinate("maxlat")?,
+                                bottom: element.coordinate("minlat")?,
+                            });
+                        }
+                        b"node" | b"way" | b"relation"
+                            if element.get("action") == Some("delete") =>
+                        {
+                            skip = true;
+                            current = None;
+                        }
+                        b"node" => {
+                            self.update_latest_timestamp(&element)?;
+                            // deleted nodes, also deleted versions in history files,
+                            // may lack coordinates
+                            let (lat, lon) = if self.action == Some(Action::Delete)
+                                || element.get("visible") == Some("false")
+                            {
+                                (
+                                    element.optional_coordinate("lat")?.unwrap_or(0),
+                                    element.optional_coordinate("lon")?.unwrap_or(0),
+                                )
+                            } else {
+                                (element.coordinate("lat")?, element.coordinate("lon")?)
+                            };
+                            current = Some(Entity::Node(Node {
+                                id: element.id("id")?,
+                                lat,
+                                lon,
+                                tags: Vec::new(),
+                                info: element.info()?,
+                            }));
+                        }
+                        b"way" => {
+                            self.update_latest_timestamp(&element)?;
+                            current = Some(Entity::Way(Way {
+                                id: element.id("id")?,
+                                info: element.info()?,
+                                ..Default::default()
+                            }));
+                        }
+                        b"relation" => {
+                            self.update_latest_timestamp(&element)?;
+                            current = Some(Entity::Relation(Relation {
+                                id: element.id("id")?,
+                                info: element.info()?,
+                                ..Default::default()
+                            }));
+                        }
+                        b"tag" => {
+                            let tag = (
+                                element.required("k")?.to_string(),
+                                element.required("v")?.to_string(),
+                            );
+                            match &mut current {
+                                Some(Entity::Node(node)) => node.tags.push(tag),
+                                Some(Entity::Way(way)) => way.tags.push(tag),
+                                Some(Entity::Relation(relation)) => relation.tags.push(tag),
+                                None => (),
+                            }
+                        }
+                        b"nd" => {
+                            if let Some(Entity::Way(way)) = &mut current {
+                                way.refs.push(element.id("ref")?);
+                            }
+                        }
+                        b"member" => {
+                            if let Some(Entity::Relation(relation)) = &mut current {
+                                relation.members.push(Member {
+                                    member_type: element.member_type("type")?,
+                                    id: element.id("ref")?,
+                                    role: element.get("role").unwrap_or_default().to_string(),
+                                });
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                Event::End(e) => match e.name() {
+                    b"node" | b"way" | b"relation" => {
+                        if skip {
+                            skip = false;
+                        } else if current.is_some() {
+                            return Ok(current);
+                        }
+                    }
+                    b"create" | b"modify" | b"delete" => self.action = None,
+                    _ => (),
+                },
+                Event::Eof => {
+                    if current.is_some() || skip {
+                        return Err(Error::UnexpectedEof { position });
+                    }
+                    return Ok(None);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn update_latest_timestamp(&mut self, element: &Element) -> Result<(), Error> {
+        if let Some(timestamp) = element.optional_timestamp("timestamp")? {
+            self.latest_timestamp = self.latest_timestamp.max(Some(timestamp));
+        }
+        Ok(())
+    }
+}
+
+/// Decoded attributes of an XML element.
+struct Element {
+    name: String,
+    position: usize,
+    attributes: Vec<(Vec<u8>, String)>,
+}
+
+impl Element {
+    fn new(e: &BytesStart, position: usize) -> Result<Self, Error> {
+        let xml_error = |source| Error::Xml { position, source };
+        let mut attributes = Vec::new();
+        for attribute in e.attributes() {
+            let attribute = attribute.map_err(xml_error)?;
+            let value = attribute.unescaped_value().map_err(xml_error)?;
+            let value = str::from_utf8(&value)
+                .map_err(|e| xml_error(quick_xml::Error::Utf8(e)))?
+                .to_string();
+            attributes.push((attribute.key.to_vec(), value));
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(e.name()).into_owned(),
+            position,
+            attributes,
+        })
+    }
+
+    fn get(&self, attribute: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == attribute.as_bytes())
+            .map(|(_, value)| &value[..])
+    }
+
+    fn required(&self, attribute: &'static str) -> Result<&str, Error> {
+        self.get(attribute).ok_or_else(|| Error::MissingAttribute {
+            position: self.position,
+            element: self.name.clone(),
+            attribute,
+        })
+    }
+
+    fn invalid(&self, attribute: &'static str, value: &str) -> Error {
+        Error::InvalidAttribute {
+            position: self.position,
+            attribute,
+            value: value.to_string(),
+        }
+    }
+
+    fn id(&self, attribute: &'static str) -> Result<i64, Error> {
+        let value = self.required(attribute)?;
+        value.parse().map_err(|_| self.invalid(attribute, value))
+    }
+
+    fn coordinate(&self, attribute: &'static str) -> Result<i64, Error> {
+        let value = self.required(attribute)?;
+        parse_coordinate(value).ok_or_else(|| self.invalid(attribute, value))
+    }
+
+    fn optional_coordinate(&self, attribute: &'static str) -> Result<Option<i64>, Error> {
+        self.get(attribute)
+            .map(|value| parse_coordinate(value).ok_or_else(|| self.invalid(attribute, value)))
+            .transpose()
+    }
+
+    fn optional_timestamp(&self, attribute: &'static str) -> Result<Option<i64>, Error> {
+        self.get(attribute)
+            .map(|value| parse_timestamp(value).ok_or_else(|| self.invalid(attribute, value)))
+            .transpose()
+    }
+
+    fn optional_number<T: str::FromStr>(
+        &self,
+        attribute: &'static str,
+    ) -> Result<Option<T>, Error> {
+        self.get(attribute)
+            .map(|value| value.parse().map_err(|_| self.invalid(attribute, value)))
+            .transpose()
+    }
+
+    /// Metadata of an entity, if any of its attributes is present.
+    fn info(&self) -> Result<Option<Info>, Error> {
+        let version = self.optional_number("version")?;
+        let timestamp = self.optional_timestamp("timestamp")?;
+        let changeset = self.optional_number("changeset")?;
+        let uid = self.optional_number("uid")?;
+        let user = self.get("user");
+        let visible = match self.get("visible") {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            Some(value) => return Err(self.invalid("visible", value)),
+            None => None,
+        };
+        if version.is_none()
+            && timestamp.is_none()
+            && changeset.is_none()
+            && uid.is_none()
+            && user.is_none()
+            && visible.is_none()
+        {
+            return Ok(None);
+        }
+        Ok(Some(Info {
+            version: version.unwrap_or(0),
+            timestamp: timestamp.unwrap_or(0),
+            changeset: changeset.unwrap_or(0),
+            uid: uid.unwrap_or(0),
+            user: user.unwrap_or_default().to_string(),
+            visible,
+        }))
+    }
+
+    fn member_type(&self, attribute: &'static str) -> Result<MemberType, Error> {
+        match self.required(attribute)? {
+            "node" => Ok(MemberType::Node),
+            "way" => Ok(MemberType::Way),
+            "relation" => Ok(MemberType::Relation),
+            value => Err(self.invalid(attribute, value)),
+        }
+    }
+}
+
+/// Parses a coordinate given in degrees into nanodegrees.
+///
+/// The parsing is exact, i.e. it does not use floating point numbers. Digits
+/// after the 9th decimal place are ignored.
+pub fn parse_coordinate(value: &str) -> Option<i64> {
+    let (negative, value) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+    let (integral, fractional) = match value.find('.') {
+        Some(pos) => (&value[..pos], &value[pos + 1..]),
+        None => (value, ""),
+    };
+    let is_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+    if (integral.is_empty() && fractional.is_empty())
+        || !is_digits(integral)
+        || !is_digits(fractional)
+    {
+        return None;
+    }
+
+    let mut result: i64 = 0;
+    for c in integral.bytes() {
+        result = result.checked_mul(10)?.checked_add(i64::from(c - b'0'))?;
+    }
+    let mut digits = fractional.bytes().chain(std::iter::repeat(b'0')).take(9);
+    result = digits.try_fold(result, |result, c| {
+        result.checked_mul(10)?.checked_add(i64::from(c - b'0'))
+    })?;
+    Some(if negative { -result } else { result })
+}
+
+/// Parses a timestamp in the format `YYYY-MM-DDThh:mm:ssZ` into seconds since
+/// epoch.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let bytes = value.as_bytes();
+    if bytes.len() != 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'Z'
+    {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = &value[range];
+        if digits.bytes().all(|c| c.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // days since epoch of the proleptic Gregorian calendar, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_coordinate() {
+        assert_eq!(parse_coordinate("52.5"), Some(52_500_000_000));
+        assert_eq!(parse_coordinate("-13.4050001"), Some(-13_405_000_100));
+        assert_eq!(parse_coordinate("+0.000000001"), Some(1));
+        assert_eq!(parse_coordinate("180"), Some(180_000_000_000));
+        assert_eq!(parse_coordinate(".5"), Some(500_000_000));
+        assert_eq!(parse_coordinate("1.1234567891"), Some(1_123_456_789));
+        for invalid in &["", "-", ".", "1.2.3", "1e5", "abc", " 1"] {
+            assert_eq!(parse_coordinate(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2020-10-17T12:34:56Z"), Some(1_602_938_096));
+        assert_eq!(parse_timestamp("2000-02-29T23:59:59Z"), Some(951_868_799));
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59Z"), Some(-1));
+        for invalid in &[
+            "",
+            "2020-10-17",
+            "2020-10-17T12:34:56",
+            "2020-13-01T00:00:00Z",
+        ] {
+            assert_eq!(parse_timestamp(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    const XML: &str = r#"<?xml version='1.0' encoding='UTF-8'?>
+<osm version="0.6" generator="JOSM">
+  <bounds minlat="52.5" minlon="13.3" maxlat="52.6" maxlon="13.4"/>
+  <node id="1" lat="52.51" lon="13.31" version="2" timestamp="2020-10-17T12:34:56Z"
+        changeset="42" uid="7" user="alice">
+    <tag k="name" v="A &amp; B"/>
+  </node>
+  <node id="2" lat="52.52" lon="13.32"/>
+  <node id="3" action="delete" visible="true"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <tag k="highway" v="path"/>
+  </way>
+  <relation id="100">
+    <member type="way" ref="10" role="outer"/>
+    <member type="node" ref="2"/>
+    <tag k="type" v="multipolygon"/>
+  </relation>
+</osm>
+"#;
+
+    #[test]
+    fn test_read_entities() {
+        let mut reader = Reader::new(XML.as_bytes());
+        let mut entities = Vec::new();
+        while let Some(entity) = reader.next_entity().unwrap() {
+            entities.push(entity);
+        }
+
+        assert_eq!(reader.header().writingprogram.as_deref(), Some("JOSM"));
+        assert_eq!(
+            reader.header().bbox,
+            Some(osmpbf::HeaderBBox {
+                left: 13_300_000_000,
+                right: 13_400_000_000,
+                top: 52_600_000_000,
+                bottom: 52_500_000_000,
+            })
+        );
+
+        assert_eq!(
+            entities,
+            vec![
+                Entity::Node(Node {
+                    id: 1,
+                    lat: 52_510_000_000,
+                    lon: 13_310_000_000,
+                    tags: vec![("name".into(), "A & B".into())],
+                    info: Some(Info {
+                        version: 2,
+                        timestamp: 1_602_938_096,
+                        changeset: 42,
+                        uid: 7,
+                        user: "alice".into(),
+                        visible: None,
+                    }),
+                }),
+                Entity::Node(Node {
+                    id: 2,
+                    lat: 52_520_000_000,
+                    lon: 13_320_000_000,
+                    tags: vec![],
+                    info: None,
+                }),
+                Entity::Way(Way {
+                    id: 10,
+                    refs: vec![1, 2],
+                    tags: vec![("highway".into(), "path".into())],
+                    info: None,
+                }),
+                Entity::Relation(Relation {
+                    id: 100,
+                    members: vec![
+                        Member {
+                            member_type: MemberType::Way,
+                            id: 10,
+                            role: "outer".into(),
+                        },
+                        Member {
+                            member_type: MemberType::Node,
+                            id: 2,
+                            role: "".into(),
+                        },
+                    ],
+                    tags: vec![("type".into(), "multipolygon".into())],
+                    info: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_changes() {
+        let xml = r#"<osmChange version="0.6">
+  <create>
+    <node id="1" lat="1" lon="2" timestamp="2020-10-17T12:34:56Z"/>
+  </create>
+  <modify>
+    <way id="10" timestamp="2020-10-17T12:35:00Z"><nd ref="1"/></way>
+  </modify>
+  <delete>
+    <node id="2" timestamp="2020-10-17T12:30:00Z"/>
+  </delete>
+</osmChange>"#;
+        let mut reader = Reader::new(xml.as_bytes());
+        let mut changes = Vec::new();
+        while let Some(change) = reader.next_change().unwrap() {
+            changes.push(change);
+        }
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    Action::Create,
+                    Entity::Node(Node {
+                        id: 1,
+                        lat: 1_000_000_000,
+                        lon: 2_000_000_000,
+                        tags: vec![],
+                        info: Some(Info {
+                            timestamp: 1_602_938_096,
+                            ..Default::default()
+                        }),
+                    })
+                ),
+                (
+                    Action::Modify,
+                    Entity::Way(Way {
+                        id: 10,
+                        refs: vec![1],
+                        tags: vec![],
+                        info: Some(Info {
+                            timestamp: 1_602_938_100,
+                            ..Default::default()
+                        }),
+                    })
+                ),
+                (
+                    Action::Delete,
+                    Entity::Node(Node {
+                        id: 2,
+                        info: Some(Info {
+                            timestamp: 1_602_937_800,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                ),
+            ]
+        );
+        assert_eq!(reader.latest_timestamp(), Some(1_602_938_100));
+
+        let mut reader =
+            Reader::new(r#"<osmChange><node id="1" lat="1" lon="2"/></osmChange>"#.as_bytes());
+        match reader.next_change() {
+            Err(Error::MissingAction { .. }) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_read_history() {
+        let xml = r#"<osm>
+  <node id="1" version="1" timestamp="2020-10-17T12:34:56Z" visible="true" lat="1" lon="2"/>
+  <node id="1" version="2" timestamp="2020-10-17T12:35:00Z" visible="false"/>
+</osm>"#;
+        let mut reader = Reader::new(xml.as_bytes());
+        let mut entities = Vec::new();
+        while let Some(entity) = reader.next_entity().unwrap() {
+            entities.push(entity);
+        }
+        assert_eq!(
+            entities,
+            vec![
+                Entity::Node(Node {
+                    id: 1,
+                    lat: 1_000_000_000,
+                    lon: 2_000_000_000,
+                    tags: vec![],
+                    info: Some(Info {
+                        version: 1,
+                        timestamp: 1_602_938_096,
+                        visible: Some(true),
+                        ..Default::default()
+                    }),
+                }),
+                Entity::Node(Node {
+                    id: 1,
+                    lat: 0,
+                    lon: 0,
+                    tags: vec![],
+                    info: Some(Info {
+                        version: 2,
+                        timestamp: 1_602_938_100,
+                        visible: Some(false),
+                        ..Default::default()
+                    }),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_entity() {
+        let mut reader = Reader::new(r#"<osm><node id="1" lat="x" lon="1"/></osm>"#.as_bytes());
+        match reader.next_entity() {
+            Err(Error::InvalidAttribute {
+                position: 5,
+                attribute: "lat",
+                value,
+            }) => assert_eq!(value, "x"),
+            x => panic!("unexpected result: {:?}", x),
+        }
+
+        let mut reader = Reader::new(r#"<osm><way id="1"><nd/></way></osm>"#.as_bytes());
+        match reader.next_entity() {
+            Err(Error::MissingAttribute {
+                element,
+                attribute: "ref",
+                ..
+            }) => assert_eq!(element, "nd"),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use crate::entities::{Entity, Info, Node, Relation, Tags, Way};
+use crate::osmpbf;
+
+use byteorder::{ByteOrder, NetworkEndian};
+use prost::Message;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Maximum number of entities in a single block
+const BLOCK_SIZE: usize = 8000;
+
+/// Writes entities as uncompressed PBF.
+///
+/// Entities are buffered and written in blocks of a single type. Consecutive
+/// entities of the same type are written into the same block, therefore
+/// entities should be ordered by type (nodes, ways, relations) to get a
+/// compact output.
+pub struct PbfWriter<W: Write> {
+    out: W,
+    nodes: Vec<Node>,
+    ways: Vec<Way>,
+    relations: Vec<Relation>,
+}
+
+impl<W: Write> PbfWriter<W> {
+    /// Creates a new writer and writes the header block.
+    ///
+    /// The features `OsmSchema-V0.6` and `DenseNodes` are added to the
+    /// required features of the header.
+    pub fn new(mut out: W, header: &osmpbf::HeaderBlock) -> io::Result<Self> {
+        let mut header = header.clone();
+        for feature in &["OsmSchema-V0.6", "DenseNodes"] {
+            if !header.required_features.iter().any(|f| f == feature) {
+                header.required_features.push(feature.to_string());
+            }
+        }
+        write_blob(&mut out, "OSMHeader", &header)?;
+        Ok(Self {
+            out,
+            nodes: Vec::new(),
+            ways: Vec::new(),
+            relations: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, entity: Entity) -> io::Result<()> {
+        match entity {
+            Entity::Node(node) => {
+                self.flush_ways()?;
+                self.flush_relations()?;
+                self.nodes.push(node);
+                if self.nodes.len() == BLOCK_SIZE {
+                    self.flush_nodes()?;
+                }
+            }
+            Entity::Way(way) => {
+                self.flush_nodes()?;
+                self.flush_relations()?;
+                self.ways.push(way);
+                if self.ways.len() == BLOCK_SIZE {
+                    self.flush_ways()?;
+                }
+            }
+            Entity::Relation(relation) => {
+                self.flush_nodes()?;
+                self.flush_ways()?;
+                self.relations.push(relation);
+                if self.relations.len() == BLOCK_SIZE {
+                    self.flush_relations()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes all buffered entities and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_nodes()?;
+        self.flush_ways()?;
+        self.flush_relations()?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn flush_nodes(&mut self) -> io::Result<()> {
+        if self.nodes.is_empty() {
+            return Ok(());
+        }
+        let mut strings = StringTableBuilder::new();
+        let mut dense = osmpbf::DenseNodes::default();
+        // metadata is written for all nodes of the block, if any node has metadata
+        let mut dense_info = if self.nodes.iter().any(|node| node.info.is_some()) {
+            Some(DenseInfoBuilder {
+                with_visible: self.nodes.iter().any(|node| {
+                    node.info
+                        .as_ref()
+                        .is_some_and(|info| info.visible.is_some())
+                }),
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+        let (mut id, mut lat, mut lon) = (0, 0, 0);
+        for node in self.nodes.drain(..) {
+            dense.id.push(node.id - id);
+            dense.lat.push(node.lat - lat);
+            dense.lon.push(node.lon - lon);
+            id = node.id;
+            lat = node.lat;
+            lon = node.lon;
+            for (key, value) in &node.tags {
+                dense.keys_vals.push(strings.insert(key) as i32);
+                dense.keys_vals.push(strings.insert(value) as i32);
+            }
+            dense.keys_vals.push(0);
+            if let Some(dense_info) = &mut dense_info {
+                dense_info.push(&node.info.unwrap_or_default(), &mut strings);
+            }
+        }
+        dense.denseinfo = dense_info.map(|builder| builder.info);
+        let group = osmpbf::PrimitiveGroup {
+            dense: Some(dense),
+            ..Default::default()
+        };
+        write_blob(&mut self.out, "OSMData", &primitive_block(strings, group))
+    }
+
+    fn flush_ways(&mut self) -> io::Result<()> {
+        if self.ways.is_empty() {
+            return Ok(());
+        }
+        let mut strings = StringTableBuilder::new();
+        let mut group = osmpbf::PrimitiveGroup::default();
+        for way in self.ways.drain(..) {
+            let (keys, vals) = strings.insert_tags(&way.tags);
+            let mut node_ref = 0;
+            let refs = way
+                .refs
+                .iter()
+                .map(|&id| {
+                    let delta = id - node_ref;
+                    node_ref = id;
+                    delta
+                })
+                .collect();
+            let info = way.info.map(|info| to_pbf_info(&info, &mut strings));
+            group.ways.push(osmpbf::Way {
+                id: way.id,
+                keys,
+                vals,
+                info,
+                refs,
+            });
+        }
+        write_blob(&mut self.out, "OSMData", &primitive_block(strings, group))
+    }
+
+    fn flush_relations(&mut self) -> io::Result<()> {
+        if self.relations.is_empty() {
+            return Ok(());
+        }
+        let mut strings = StringTableBuilder::new();
+        let mut group = osmpbf::PrimitiveGroup::default();
+        for relation in self.relations.drain(..) {
+            let (keys, vals) = strings.insert_tags(&relation.tags);
+            let info = relation.info.map(|info| to_pbf_info(&info, &mut strings));
+            let mut pbf_relation = osmpbf::Relation {
+                id: relation.id,
+                keys,
+                vals,
+                info,
+                ..Default::default()
+            };
+            let mut memid = 0;
+            for member in &relation.members {
+                pbf_relation
+                    .roles_sid
+                    .push(strings.insert(&member.role) as i32);
+                pbf_relation.memids.push(member.id - memid);
+                pbf_relation.types.push(member.member_type as i32);
+                memid = member.id;
+            }
+            group.relations.push(pbf_relation);
+        }
+        write_blob(&mut self.out, "OSMData", &primitive_block(strings, group))
+    }
+}
+
+/// Collects the strings of a block in the order of their first occurrence.
+struct StringTableBuilder {
+    strings: Vec<Vec<u8>>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTableBuilder {
+    fn new() -> Self {
+        Self {
+            // index 0 is reserved as delimiter in dense nodes
+            strings: vec![Vec::new()],
+            indices: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, s: &str) -> u32 {
+        if let Some(&idx) = self.indices.get(s) {
+            return idx;
+        }
+        let idx = self.strings.len() as u32;
+        self.strings.push(s.as_bytes().to_vec());
+        self.indices.insert(s.to_string(), idx);
+        idx
+    }
+
+    /// Inserts a user name; an unknown (empty) user is stored as index 0.
+    fn insert_user(&mut self, user: &str) -> u32 {
+        if user.is_empty() {
+            0
+        } else {
+            self.insert(user)
+        }
+    }
+
+    fn insert_tags(&mut self, tags: &Tags) -> (Vec<u32>, Vec<u32>) {
+        tags.iter()
+            .map(|(key, value)| (self.insert(key), self.insert(value)))
+            .unzip()
+    }
+}
+
+/// Collects the delta coded metadata of dense nodes.
+#[derive(Default)]
+struct DenseInfoBuilder {
+    info: osmpbf::DenseInfo,
+    last: Info,
+    last_user_sid: i32,
+    /// Whether the visible flag is written
+    with_visible: bool,
+}
+
+impl DenseInfoBuilder {
+    fn push(&mut self, info: &Info, strings: &mut StringTableBuilder) {
+        let user_sid = strings.insert_user(&info.user) as i32;
+        self.info.version.push(info.version);
+        self.info
+            .timestamp
+            .push(info.timestamp - self.last.timestamp);
+        self.info
+            .changeset
+            .push(info.changeset - self.last.changeset);
+        self.info.uid.push(info.uid - self.last.uid);
+        self.info.user_sid.push(user_sid - self.last_user_sid);
+        if self.with_visible {
+            self.info.visible.push(info.visible.unwrap_or(true));
+        }
+        self.last_user_sid = user_sid;
+        self.last.timestamp = info.timestamp;
+        self.last.changeset = info.changeset;
+        self.last.uid = info.uid;
+    }
+}
+
+fn to_pbf_info(info: &Info, strings: &mut StringTableBuilder) -> osmpbf::Info {
+    osmpbf::Info {
+        version: Some(info.version),
+        timestamp: Some(info.timestamp),
+        changeset: Some(info.changeset),
+        uid: Some(info.uid),
+        user_sid: Some(strings.insert_user(&info.user)),
+        visible: info.visible,
+    }
+}
+
+fn primitive_block(
+    strings: StringTableBuilder,
+    group: osmpbf::PrimitiveGroup,
+) -> osmpbf::PrimitiveBlock {
+    osmpbf::PrimitiveBlock {
+        stringtable: osmpbf::StringTable { s: strings.strings },
+        primitivegroup: vec![group],
+        // coordinates are stored in nanodegrees without loss
+        granularity: Some(1),
+        ..Default::default()
+    }
+}
+
+fn encode<M: Message>(message: &M) -> Vec<u8> {
+    let mut data = Vec::with_capacity(message.encoded_len());
+    message
+        .encode(&mut data)
+        .expect("failed to encode message into a vector");
+    data
+}
+
+fn write_blob<W: Write, M: Message>(out: &mut W, blob_type: &str, message: &M) -> io::Result<()> {
+    let data = encode(message);
+    let blob = encode(&osmpbf::Blob {
+        raw_size: Some(data.len() as i32),
+        raw: Some(data),
+        ..Default::default()
+    });
+    let header = encode(&osmpbf::BlobHeader {
+        r#type: blob_type.into(),
+        indexdata: None,
+        datasize: blob.len() as i32,
+    });
+    let mut header_size = [0; 4];
+    NetworkEndian::write_u32(&mut header_size, header.len() as u32);
+    out.write_all(&header_size)?;
+    out.write_all(&header)?;
+    out.write_all(&blob)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entities::Member;
+    use crate::osmpbf::{build_block_index, read_block, relation::MemberType, BlockType};
+
+    fn tags(tags: &[(&str, &str)]) -> Tags {
+        tags.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_entities() {
+        let header = osmpbf::HeaderBlock::default();
+        let mut writer = PbfWriter::new(Vec::new(), &header).unwrap();
+        for (id, lat, lon) in &[(1, 525_000_000, 134_000_000), (3, -1, 1_800_000_000)] {
+            writer
+                .write(Entity::Node(Node {
+                    id: *id,
+                    lat: *lat,
+                    lon: *lon,
+                    tags: tags(&[("name", "n")]),
+                    info: None,
+                }))
+                .unwrap();
+        }
+        writer
+            .write(Entity::Way(Way {
+                id: 10,
+                refs: vec![3, 1, 3],
+                tags: tags(&[("highway", "path"), ("name", "n")]),
+                info: None,
+            }))
+            .unwrap();
+        writer
+            .write(Entity::Relation(Relation {
+                id: 100,
+                members: vec![
+                    Member {
+                        member_type: MemberType::Way,
+                        id: 10,
+                        role: "outer".into(),
+                    },
+                    Member {
+                        member_type: MemberType::Node,
+                        id: 1,
+                        role: "".into(),
+                    },
+                ],
+                tags: Tags::new(),
+                info: None,
+            }))
+            .unwrap();
+        let data = writer.finish().unwrap();
+
+        let (index, skipped) = build_block_index(&data, false).unwrap();
+        assert!(skipped.is_empty());
+        let block_types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(
+            block_types,
+            vec![
+                BlockType::Header,
+                BlockType::DenseNodes,
+                BlockType::Ways,
+                BlockType::Relations
+            ]
+        );
+
+        let header: osmpbf::HeaderBlock = read_block(&data, &index[0]).unwrap();
+        assert_eq!(
+            header.required_features,
+            vec!["OsmSchema-V0.6", "DenseNodes"]
+        );
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[1]).unwrap();
+        let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+        assert_eq!(dense.id, vec![1, 2]);
+        assert_eq!(dense.lat, vec![525_000_000, -525_000_001]);
+        assert_eq!(dense.lon, vec![134_000_000, 1_666_000_000]);
+        assert_eq!(dense.keys_vals, vec![1, 2, 0, 1, 2, 0]);
+        assert_eq!(dense.denseinfo, None);
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[2]).unwrap();
+        let way = &block.primitivegroup[0].ways[0];
+        assert_eq!(way.refs, vec![3, -2, 2]);
+        assert_eq!(block.stringtable.s[way.keys[1] as usize], b"name");
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[3]).unwrap();
+        let relation = &block.primitivegroup[0].relations[0];
+        assert_eq!(relation.memids, vec![10, -9]);
+        assert_eq!(
+            relation.types,
+            vec![MemberType::Way as i32, MemberType::Node as i32]
+        );
+        assert_eq!(
+            block.stringtable.s[relation.roles_sid[0] as usize],
+            b"outer"
+        );
+    }
+
+    #[test]
+    fn test_write_info() {
+        let header = osmpbf::HeaderBlock::default();
+        let mut writer = PbfWriter::new(Vec::new(), &header).unwrap();
+        let info = Info {
+            version: 2,
+            timestamp: 1_600_000_000,
+            changeset: 42,
+            uid: 7,
+            user: "alice".into(),
+            ..Default::default()
+        };
+        for (id, info) in [(1, Some(info.clone())), (2, None)] {
+            writer
+                .write(Entity::Node(Node {
+                    id,
+                    info,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+        writer
+            .write(Entity::Way(Way {
+                id: 10,
+                refs: vec![1, 2],
+                info: Some(info),
+                ..Default::default()
+            }))
+            .unwrap();
+        let data = writer.finish().unwrap();
+        let (index, _) = build_block_index(&data, false).unwrap();
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[1]).unwrap();
+        let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+        let dense_info = dense.denseinfo.as_ref().unwrap();
+        assert_eq!(dense_info.version, vec![2, 0]);
+        assert_eq!(dense_info.timestamp, vec![1_600_000_000, -1_600_000_000]);
+        assert_eq!(dense_info.changeset, vec![42, -42]);
+        assert_eq!(dense_info.uid, vec![7, -7]);
+        assert_eq!(
+            block.stringtable.s[dense_info.user_sid[0] as usize],
+            b"alice"
+        );
+        assert_eq!(dense_info.user_sid[0] + dense_info.user_sid[1], 0);
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[2]).unwrap();
+        let way_info = block.primitivegroup[0].ways[0].info.as_ref().unwrap();
+        assert_eq!(way_info.version, Some(2));
+        assert_eq!(way_info.timestamp, Some(1_600_000_000));
+        assert_eq!(
+            block.stringtable.s[way_info.user_sid.unwrap() as usize],
+            b"alice"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Polygonal regions read from Osmosis `.poly` files or GeoJSON.
+//!
+//! See <https://wiki.openstreetmap.org/wiki/Osmosis/Polygon_Filter_File_Format>
+//! for the `.poly` format.
+
+use crate::filter::BBox;
+use crate::input;
+use crate::osmxml::parse_coordinate;
+
+use serde_json::Value;
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+/// Errors which can occur while reading a polygon.
+#[derive(Debug)]
+pub enum Error {
+    /// The `.poly` file is invalid at the given line.
+    InvalidPoly {
+        path: PathBuf,
+        line: usize,
+        reason: &'static str,
+    },
+    /// The GeoJSON file is invalid or does not contain polygons.
+    InvalidGeoJson { path: PathBuf, reason: String },
+    /// The file could not be read.
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidPoly { path, line, reason } => {
+                write!(f, "invalid polygon {}:{}: {}", path.display(), line, reason)
+            }
+            Error::InvalidGeoJson { path, reason } => {
+                write!(f, "invalid polygon {}: {}", path.display(), reason)
+            }
+            Error::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Closed ring of a polygon with coordinates `(lon, lat)` in nanodegrees.
+#[derive(Debug, Clone, PartialEq)]
+struct Ring {
+    points: Vec<(i64, i64)>,
+    bbox: BBox,
+}
+
+impl Ring {
+    fn new(points: Vec<(i64, i64)>) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let bbox = BBox {
+            min_lon: points.iter().map(|p| p.0).min()?,
+            min_lat: points.iter().map(|p| p.1).min()?,
+            max_lon: points.iter().map(|p| p.0).max()?,
+            max_lat: points.iter().map(|p| p.1).max()?,
+        };
+        Some(Self { points, bbox })
+    }
+
+    /// Even-odd test whether the ring encloses a point.
+    fn contains(&self, lat: i64, lon: i64) -> bool {
+        if !self.bbox.contains(lat, lon) {
+            return false;
+        }
+        let mut inside = false;
+        let mut previous = self.points[self.points.len() - 1];
+        for &current in &self.points {
+            let ((x0, y0), (x1, y1)) = (previous, current);
+            if (y0 > lat) != (y1 > lat) {
+                // longitude at which the edge crosses the latitude of the point
+                let x = i128::from(x0)
+                    + i128::from(lat - y0) * i128::from(x1 - x0) / i128::from(y1 - y0);
+                if i128::from(lon) < x {
+                    inside = !inside;
+                }
+            }
+            previous = current;
+        }
+        inside
+    }
+}
+
+/// Region bounded by one or more rings.
+///
+/// A point is inside the region if it is enclosed by an odd number of rings,
+/// i.e. holes are rings inside of other rings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    rings: Vec<Ring>,
+}
+
+impl Polygon {
+    /// Reads a polygon from a file.
+    ///
+    /// Files with the extension `.geojson` or `.json` are read as GeoJSON, all
+    /// other files as `.poly`. Files compressed with gzip or bzip2 are
+    /// decompressed transparently.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let reader = input::open_decompressed(path).map_err(io_error)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("geojson") | Some("json") => {
+                let value: Value =
+                    serde_json::from_reader(reader).map_err(|e| Error::InvalidGeoJson {
+                        path: path.to_path_buf(),
+                        reason: e.to_string(),
+                    })?;
+                Self::from_geojson(&value).map_err(|reason| Error::InvalidGeoJson {
+                    path: path.to_path_buf(),
+                    reason: reason.to_string(),
+                })
+            }
+            _ => match Self::from_poly(reader).map_err(io_error)? {
+                Ok(polygon) => Ok(polygon),
+                Err((line, reason)) => Err(Error::InvalidPoly {
+                    path: path.to_path_buf(),
+                    line,
+                    reason,
+                }),
+            },
+        }
+    }
+
+    /// Parses a polygon in the Osmosis `.poly` format.
+    ///
+    /// The file starts with a name, followed by sections each containing a
+    /// ring, and ends with `END`. Every section starts with a name (prefixed
+    /// with `!` for holes), followed by one `lon lat` pair per line, and ends
+    /// with `END`. Parse errors are returned with their line number.
+    pub fn from_poly(reader: impl BufRead) -> io::Result<Result<Self, (usize, &'static str)>> {
+        let mut lines = Vec::new();
+        for line in reader.lines() {
+            lines.push(line?);
+        }
+        let mut lines = lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(idx, line)| *idx == 1 || !line.is_empty());
+
+        if lines.next().is_none() {
+            return Ok(Err((1, "missing name")));
+        }
+        let mut rings = Vec::new();
+        loop {
+            let section = match lines.next() {
+                Some((_, "END")) | None => break,
+                Some(section) => section,
+            };
+            let mut points = Vec::new();
+            loop {
+                let (idx, line) = match lines.next() {
+                    Some((_, "END")) => break,
+                    Some(line) => line,
+                    None => return Ok(Err((section.0, "unterminated section"))),
+                };
+                let mut coordinates = line.split_whitespace().map(parse_degrees);
+                match (coordinates.next(), coordinates.next(), coordinates.next()) {
+                    (Some(Some(lon)), Some(Some(lat)), None) => points.push((lon, lat)),
+                    _ => return Ok(Err((idx, "invalid coordinates"))),
+                }
+            }
+            match Ring::new(points) {
+                Some(ring) => rings.push(ring),
+                None => return Ok(Err((section.0, "ring with less than 3 points"))),
+            }
+        }
+        if rings.is_empty() {
+            return Ok(Err((1, "no rings")));
+        }
+        Ok(Ok(Self { rings }))
+    }
+
+    /// Extracts the polygons from a GeoJSON object.
+    ///
+    /// Supported are `Polygon` and `MultiPolygon` geometries, optionally
+    /// wrapped in a `Feature`, `FeatureCollection` or `GeometryCollection`.
+    pub fn from_geojson(value: &Value) -> Result<Self, &'static str> {
+        let mut rings = Vec::new();
+        collect_geojson_rings(value, &mut rings)?;
+        if rings.is_empty() {
+            return Err("no polygons");
+        }
+        Ok(Self { rings })
+    }
+
+    pub fn contains(&self, lat: i64, lon: i64) -> bool {
+        self.rings
+            .iter()
+            .filter(|ring| ring.contains(lat, lon))
+            .count()
+            % 2
+            == 1
+    }
+
+    /// Bounding box of the polygon.
+    pub fn bbox(&self) -> BBox {
+        let bboxes = || self.rings.iter().map(|ring| ring.bbox);
+        BBox {
+            min_lon: bboxes().map(|b| b.min_lon).min().unwrap_or_default(),
+            min_lat: bboxes().map(|b| b.min_lat).min().unwrap_or_default(),
+            max_lon: bboxes().map(|b| b.max_lon).max().unwrap_or_default(),
+            max_lat: bboxes().map(|b| b.max_lat).max().unwrap_or_default(),
+        }
+    }
+}
+
+/// Parses degrees into nanodegrees, also accepting the scientific notation
+/// used by some tools writing `.poly` files.
+fn parse_degrees(value: &str) -> Option<i64> {
+    parse_coordinate(value).or_else(|| {
+        let degrees: f64 = value.parse().ok()?;
+        let nanodegrees = (degrees * osmflat::COORD_SCALE as f64).round();
+        if degrees.is_finite() && nanodegrees.abs() < i64::MAX as f64 {
+            Some(nanodegrees as i64)
+        } else {
+            None
+        }
+    })
+}
+
+fn collect_geojson_rings(value: &Value, rings: &mut Vec<Ring>) -> Result<(), &'static str> {
+    let objects = |key| -> Result<&Vec<Value>, &'static str> {
+        value[key].as_array().ok_or("invalid GeoJSON object")
+    };
+    match value["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in objects("features")? {
+                collect_geojson_rings(feature, rings)?;
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in objects("geometries")? {
+                collect_geojson_rings(geometry, rings)?;
+            }
+        }
+        Some("Feature") => collect_geojson_rings(&value["geometry"], rings)?,
+        Some("Polygon") => {
+            rings.extend(geojson_polygon(&value["coordinates"])?);
+        }
+        Some("MultiPolygon") => {
+            for polygon in objects("coordinates")? {
+                rings.extend(geojson_polygon(polygon)?);
+            }
+        }
+        Some(_) => return Err("unsupported GeoJSON geometry, expected (multi)polygons"),
+        None => return Err("invalid GeoJSON object"),
+    }
+    Ok(())
+}
+
+fn geojson_polygon(coordinates: &Value) -> Result<Vec<Ring>, &'static str> {
+    let invalid = "invalid polygon coordinates";
+    let to_nanodegrees = |value: &Value| {
+        let degrees = value.as_f64()?;
+        Some((degrees * osmflat::COORD_SCALE as f64).round() as i64)
+    };
+    coordinates
+        .as_array()
+        .ok_or(invalid)?
+        .iter()
+        .map(|ring| {
+            let points = ring
+                .as_array()?
+                .iter()
+                .map(|point| match point.as_array()?.as_slice() {
+                    [lon, lat, ..] => Some((to_nanodegrees(lon)?, to_nanodegrees(lat)?)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Ring::new(points)
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEGREE: i64 = osmflat::COORD_SCALE as i64;
+
+    #[test]
+    fn test_read_poly() {
+        let poly = Polygon::from_poly(
+            &b"square with hole
+               1
+                  0.0 0.0
+                  1.0E+01 0.0
+                  10.0 10.0
+                  0 10
+               END
+               !2
+                  4 4
+                  6 4
+                  6 6
+                  4 6
+               END
+               END
+            "[..],
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(poly.rings.len(), 2);
+        assert_eq!(poly.rings[0].points[1], (10 * DEGREE, 0));
+        assert_eq!(
+            poly.bbox(),
+            BBox {
+                min_lon: 0,
+                min_lat: 0,
+                max_lon: 10 * DEGREE,
+                max_lat: 10 * DEGREE,
+            }
+        );
+
+        assert!(poly.contains(DEGREE, 2 * DEGREE));
+        assert!(poly.contains(9 * DEGREE, 9 * DEGREE));
+        assert!(!poly.contains(5 * DEGREE, 5 * DEGREE));
+        assert!(!poly.contains(5 * DEGREE, 11 * DEGREE));
+        assert!(!poly.contains(-DEGREE, 5 * DEGREE));
+    }
+
+    #[test]
+    fn test_read_invalid_poly() {
+        let read = |data: &[u8]| Polygon::from_poly(data).unwrap().unwrap_err();
+        assert_eq!(read(b"name\n1\n0 0\n1 0\n"), (2, "unterminated section"));
+        assert_eq!(
+            read(b"name\n1\n0 0\n1 x\nEND\nEND\n"),
+            (4, "invalid coordinates")
+        );
+        assert_eq!(
+            read(b"name\n1\n0 0\n1 0\nEND\nEND\n"),
+            (2, "ring with less than 3 points")
+        );
+        assert_eq!(read(b"name\nEND\n"), (1, "no rings"));
+    }
+
+    #[test]
+    fn test_read_geojson() {
+        let value = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [[[0, 0], [2, 0], [2, 2], [0, 2], [0, 0]]],
+                        [[[3, 0], [5, 0], [4, 2.5], [3, 0]]]
+                    ]
+                }
+            }]
+        });
+        let poly = Polygon::from_geojson(&value).unwrap();
+        assert_eq!(poly.rings.len(), 2);
+        assert_eq!(poly.bbox().max_lat, 5 * DEGREE / 2);
+        assert!(poly.contains(DEGREE, DEGREE));
+        assert!(poly.contains(DEGREE, 4 * DEGREE));
+        assert!(!poly.contains(DEGREE, 5 * DEGREE / 2));
+
+        let point = serde_json::json!({"type": "Point", "coordinates": [0, 0]});
+        assert!(Polygon::from_geojson(&point).is_err());
+    }
+}
@@ -0,0 +1,563 @@
+//! Reordering of nodes and ways along a Hilbert curve.
+//!
+//! Only the Hilbert value and the position in the input of each node and way
+//! are held in memory, together with the grid cell of each node for the
+//! bounding boxes of ways. The keys are sorted, and the entities are read again
+//! from the input in this order and written back to PBF, followed by the
+//! relations, which are streamed in the input order. The compiler then resolves
+//! all references by id as usual, so that the indexes of nodes and ways follow
+//! the new order.
+
+use crate::entities::{Entity, Info, Member, Node, Relation, Tags, Way};
+use crate::ids::{IdTable, IdTableBuilder};
+use crate::osmpbf::relation::MemberType;
+use crate::osmpbf::{self, build_block_index, read_block, BlockIndex, BlockType};
+use crate::parallel;
+use crate::pbfwriter::PbfWriter;
+use crate::spatial_index::{grid_cell, hilbert, point, union};
+use crate::{decode_dense_info, duplicate_id_error, Error};
+
+use log::{info, warn};
+use rayon::prelude::*;
+
+use std::io::Write;
+use std::str;
+
+/// Optional feature of the header of reordered data.
+///
+/// It is stored in the header of the archive, so that updates of the archive
+/// are reordered as well.
+pub const HILBERT_ORDER_FEATURE: &str = "Sort.Hilbert";
+
+/// Number of consecutive entities of the new order, which are read from the
+/// input together
+const WINDOW_SIZE: usize = 1 << 20;
+
+/// Hilbert value of an entity, and the index of its block and its offset in
+/// the block
+type Key = (u32, u32, u32);
+
+/// Entities of the input, in input order.
+#[derive(Debug, Default)]
+struct Entities {
+    nodes: Vec<Node>,
+    ways: Vec<Way>,
+    relations: Vec<Relation>,
+}
+
+/// Rewrites PBF data with nodes and ways ordered along a Hilbert curve.
+///
+/// Nodes are ordered by their location and ways by the center of the grid
+/// cells of their nodes; ways without any node in the input come last.
+/// Relations keep their order. Besides the keys of nodes and ways, only a
+/// window of entities in the new order is held in memory.
+pub fn write_pbf<W: Write>(
+    input_data: &[u8],
+    skip_corrupt_blocks: bool,
+    writer: W,
+) -> Result<W, Error> {
+    write_pbf_in_windows(input_data, skip_corrupt_blocks, writer, WINDOW_SIZE)
+}
+
+fn write_pbf_in_windows<W: Write>(
+    input_data: &[u8],
+    skip_corrupt_blocks: bool,
+    writer: W,
+    window_size: usize,
+) -> Result<W, Error> {
+    let (block_index, skipped_blocks) = build_block_index(input_data, skip_corrupt_blocks)?;
+    for e in &skipped_blocks {
+        warn!("Skipping corrupt block: {}", e);
+    }
+    let mut header_blocks = Vec::new();
+    let mut node_blocks = Vec::new();
+    let mut way_blocks = Vec::new();
+    let mut relation_blocks = Vec::new();
+    for block in block_index {
+        match block.block_type {
+            BlockType::Header => header_blocks.push(block),
+            BlockType::Nodes | BlockType::DenseNodes => node_blocks.push(block),
+            BlockType::Ways => way_blocks.push(block),
+            BlockType::Relations => relation_blocks.push(block),
+        }
+    }
+    if header_blocks.len() != 1 {
+        return Err(format!(
+            "Require exactly one header block, but found {}",
+            header_blocks.len()
+        )
+        .into());
+    }
+    let mut header: osmpbf::HeaderBlock = read_block(input_data, &header_blocks[0])?;
+    // nodes and ways are not sorted by id anymore
+    header
+        .optional_features
+        .retain(|feature| feature != "Sort.Type_then_ID" && feature != HILBERT_ORDER_FEATURE);
+    header
+        .optional_features
+        .push(HILBERT_ORDER_FEATURE.to_string());
+    // a block containing plain and dense nodes is indexed once for each type
+    node_blocks.sort_by_key(|b| b.blob_start);
+    node_blocks.dedup_by_key(|b| b.blob_start);
+
+    info!("Computing Hilbert values of nodes and ways...");
+    let mut node_ids = IdTableBuilder::new();
+    let mut extent = None;
+    parallel::parallel_process(
+        node_blocks.iter(),
+        |idx| read_block(input_data, idx).map(|block| node_locations(&block)),
+        |nodes| -> Result<(), Error> {
+            for (id, lon, lat) in nodes? {
+                node_ids.insert(id);
+                let location = point(lon, lat);
+                extent = Some(extent.map_or(location, |extent| union(extent, location)));
+            }
+            Ok(())
+        },
+    )?;
+    let node_ids = node_ids
+        .build()
+        .map_err(|e| duplicate_id_error("node", e, false))?;
+    // without nodes, there are no locations to scale
+    let extent = extent.unwrap_or_else(|| point(0, 0));
+
+    // grid cells of the nodes in input order, i.e. by their index in `node_ids`
+    let mut cells: Vec<(u16, u16)> = Vec::new();
+    let mut node_keys: Vec<Key> = Vec::new();
+    parallel::parallel_process(
+        node_blocks.iter().enumerate(),
+        |(block_idx, idx)| {
+            read_block(input_data, idx).map(|block| {
+                let cells: Vec<_> = node_locations(&block)
+                    .into_iter()
+                    .map(|(_, lon, lat)| grid_cell(&point(lon, lat), &extent))
+                    .collect();
+                (block_idx, cells)
+            })
+        },
+        |block_cells| -> Result<(), Error> {
+            let (block_idx, block_cells) = block_cells?;
+            for (offset, (x, y)) in block_cells.into_iter().enumerate() {
+                cells.push((x as u16, y as u16));
+                node_keys.push((hilbert(x, y), block_idx as u32, offset as u32));
+            }
+            Ok(())
+        },
+    )?;
+    node_keys.par_sort_unstable();
+
+    let mut way_keys: Vec<Key> = Vec::new();
+    // ways without any node in the input, in input order
+    let mut unlocated_way_keys: Vec<Key> = Vec::new();
+    parallel::parallel_process(
+        way_blocks.iter().enumerate(),
+        |(block_idx, idx)| {
+            read_block(input_data, idx).map(|block| {
+                let values: Vec<_> = way_refs(&block)
+                    .iter()
+                    .map(|refs| way_hilbert_value(refs, &node_ids, &cells))
+                    .collect();
+                (block_idx, values)
+            })
+        },
+        |values| -> Result<(), Error> {
+            let (block_idx, values) = values?;
+            for (offset, value) in values.into_iter().enumerate() {
+                match value {
+                    Some(value) => way_keys.push((value, block_idx as u32, offset as u32)),
+                    None => unlocated_way_keys.push((0, block_idx as u32, offset as u32)),
+                }
+            }
+            Ok(())
+        },
+    )?;
+    way_keys.par_sort_unstable();
+    drop(node_ids);
+    drop(cells);
+
+    info!("Writing nodes and ways along a Hilbert curve...");
+    let mut writer = PbfWriter::new(writer, &header)?;
+    write_entities(
+        input_data,
+        &node_blocks,
+        &node_keys,
+        window_size,
+        |entities| entities.nodes.into_iter().map(Entity::Node).collect(),
+        &mut writer,
+    )?;
+    drop(node_keys);
+    for keys in &[way_keys, unlocated_way_keys] {
+        write_entities(
+            input_data,
+            &way_blocks,
+            keys,
+            window_size,
+            |entities| entities.ways.into_iter().map(Entity::Way).collect(),
+            &mut writer,
+        )?;
+    }
+    parallel::parallel_process(
+        relation_blocks.into_iter(),
+        |idx| read_block(input_data, &idx),
+        |block: Result<osmpbf::PrimitiveBlock, _>| -> Result<(), Error> {
+            let mut entities = Entities::default();
+            decode_block(&block?, &mut entities)?;
+            for relation in entities.relations {
+                writer.write(Entity::Relation(relation))?;
+            }
+            Ok(())
+        },
+    )?;
+    Ok(writer.finish()?)
+}
+
+/// Writes the entities at the positions of `keys` in the order of the keys.
+///
+/// The entities are read from `blocks` in windows of `window_size` keys, and
+/// each block is decoded at most once per window. `entities_of_block` returns
+/// the entities of a decoded block, to which the offsets of the keys refer.
+fn write_entities<W: Write>(
+    input_data: &[u8],
+    blocks: &[BlockIndex],
+    keys: &[Key],
+    window_size: usize,
+    entities_of_block: impl Fn(Entities) -> Vec<Entity> + Sync,
+    writer: &mut PbfWriter<W>,
+) -> Result<(), Error> {
+    for window in keys.chunks(window_size) {
+        // block and offset of each key of the window, with its position in the window
+        let mut positions: Vec<(u32, u32, usize)> = window
+            .iter()
+            .enumerate()
+            .map(|(pos, &(_, block_idx, offset))| (block_idx, offset, pos))
+            .collect();
+        positions.sort_unstable();
+        let mut entities: Vec<Option<Entity>> = vec![None; window.len()];
+        parallel::parallel_process(
+            positions.chunk_by(|a, b| a.0 == b.0),
+            |positions| {
+                read_block(input_data, &blocks[positions[0].0 as usize])
+                    .map(|block| (positions, block))
+            },
+            |block| -> Result<(), Error> {
+                let (positions, block) = block?;
+                let mut decoded = Entities::default();
+                decode_block(&block, &mut decoded)?;
+                let mut block_entities: Vec<_> =
+                    entities_of_block(decoded).into_iter().map(Some).collect();
+                for &(_, offset, pos) in positions {
+                    entities[pos] = block_entities[offset as usize].take();
+                }
+                Ok(())
+            },
+        )?;
+        for entity in entities {
+            writer.write(entity.expect("entity of a key is not decoded"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Hilbert value of the center of the grid cells of the nodes with ids `refs`,
+/// which are present in the input.
+fn way_hilbert_value(refs: &[i64], node_ids: &IdTable, cells: &[(u16, u16)]) -> Option<u32> {
+    let bbox = refs
+        .iter()
+        .filter_map(|&id| node_ids.get(id))
+        .map(|idx| {
+            let (x, y) = cells[idx as usize];
+            point(i64::from(x), i64::from(y))
+        })
+        .reduce(union)?;
+    let center = |min: i64, max: i64| ((min + max) / 2) as u32;
+    Some(hilbert(
+        center(bbox.min_lon, bbox.max_lon),
+        center(bbox.min_lat, bbox.max_lat),
+    ))
+}
+
+/// Ids and locations `(id, lon, lat)` of the nodes of a block, in the order of
+/// `decode_block`.
+fn node_locations(block: &osmpbf::PrimitiveBlock) -> Vec<(i64, i64, i64)> {
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    let location = |id, lon, lat| {
+        (
+            id,
+            lon_offset + granularity * lon,
+            lat_offset + granularity * lat,
+        )
+    };
+
+    let mut nodes = Vec::new();
+    for group in &block.primitivegroup {
+        if let Some(dense_nodes) = &group.dense {
+            let (mut id, mut lat, mut lon) = (0, 0, 0);
+            for i in 0..dense_nodes.id.len() {
+                id += dense_nodes.id[i];
+                lat += dense_nodes.lat[i];
+                lon += dense_nodes.lon[i];
+                nodes.push(location(id, lon, lat));
+            }
+        }
+        nodes.extend(
+            group
+                .nodes
+                .iter()
+                .map(|node| location(node.id, node.lon, node.lat)),
+        );
+    }
+    nodes
+}
+
+/// Node ids referenced by the ways of a block, in the order of `decode_block`.
+fn way_refs(block: &osmpbf::PrimitiveBlock) -> Vec<Vec<i64>> {
+    let ways = block.primitivegroup.iter().flat_map(|group| &group.ways);
+    ways.map(|way| {
+        way.refs
+            .iter()
+            .scan(0, |id, delta| {
+                *id += delta;
+                Some(*id)
+            })
+            .collect()
+    })
+    .collect()
+}
+
+/// Decodes all entities of a block and appends them to `entities`.
+fn decode_block(block: &osmpbf::PrimitiveBlock, entities: &mut Entities) -> Result<(), Error> {
+    let strings = block
+        .stringtable
+        .s
+        .iter()
+        .map(|s| Ok(str::from_utf8(s)?.to_string()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let string = |sid: usize| strings[sid].clone();
+    let tags = |keys: &[u32], vals: &[u32]| -> Tags {
+        debug_assert_eq!(keys.len(), vals.len(), "invalid input data");
+        keys.iter()
+            .zip(vals)
+            .map(|(&key, &val)| (string(key as usize), string(val as usize)))
+            .collect()
+    };
+    let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+    let info = |info: &osmpbf::Info| Info {
+        version: info.version.unwrap_or(0),
+        timestamp: info.timestamp.unwrap_or(0) * date_granularity / 1000,
+        changeset: info.changeset.unwrap_or(0),
+        uid: info.uid.unwrap_or(0),
+        // string 0 is the empty string, which denotes an unknown user
+        user: info
+            .user_sid
+            .filter(|&sid| sid != 0)
+            .map_or_else(String::new, |sid| string(sid as usize)),
+        visible: info.visible,
+    };
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+
+    for group in &block.primitivegroup {
+        if let Some(dense_nodes) = &group.dense {
+            let (mut id, mut lat, mut lon) = (0, 0, 0);
+            let mut pbf_info = osmpbf::Info::default();
+            let mut keys_vals = dense_nodes.keys_vals.iter().map(|&sid| sid as usize);
+            for i in 0..dense_nodes.id.len() {
+                id += dense_nodes.id[i];
+                lat += dense_nodes.lat[i];
+                lon += dense_nodes.lon[i];
+                let mut node_tags = Tags::new();
+                // the tags of all nodes are omitted, if no node has tags
+                while let Some(key) = keys_vals.next().filter(|&key| key != 0) {
+                    let value = keys_vals.next().ok_or("invalid dense node tags")?;
+                    node_tags.push((string(key), string(value)));
+                }
+                entities.nodes.push(Node {
+                    id,
+                    lat: lat_offset + granularity * lat,
+                    lon: lon_offset + granularity * lon,
+                    tags: node_tags,
+                    info: dense_nodes.denseinfo.as_ref().map(|dense_info| {
+                        pbf_info = decode_dense_info(dense_info, i, &pbf_info);
+                        info(&pbf_info)
+                    }),
+                });
+            }
+        }
+        entities.nodes.extend(group.nodes.iter().map(|node| Node {
+            id: node.id,
+            lat: lat_offset + granularity * node.lat,
+            lon: lon_offset + granularity * node.lon,
+            tags: tags(&node.keys, &node.vals),
+            info: node.info.as_ref().map(info),
+        }));
+        entities.ways.extend(group.ways.iter().map(|way| {
+            Way {
+                id: way.id,
+                refs: way
+                    .refs
+                    .iter()
+                    .scan(0, |id, delta| {
+                        *id += delta;
+                        Some(*id)
+                    })
+                    .collect(),
+                tags: tags(&way.keys, &way.vals),
+                info: way.info.as_ref().map(info),
+            }
+        }));
+        for relation in &group.relations {
+            debug_assert!(
+                relation.roles_sid.len() == relation.memids.len()
+                    && relation.memids.len() == relation.types.len(),
+                "invalid input data"
+            );
+            let mut id = 0;
+            let mut members = Vec::with_capacity(relation.memids.len());
+            for i in 0..relation.memids.len() {
+                id += relation.memids[i];
+                members.push(Member {
+                    member_type: MemberType::from_i32(relation.types[i])
+                        .ok_or("invalid member type")?,
+                    id,
+                    role: string(relation.roles_sid[i] as usize),
+                });
+            }
+            entities.relations.push(Relation {
+                id: relation.id,
+                members,
+                tags: tags(&relation.keys, &relation.vals),
+                info: relation.info.as_ref().map(info),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{
+        compile_entities, node, pbf, relation, relation_members, way, way_ids, way_refs, with_info,
+        with_tags,
+    };
+
+    fn tagged_node(id: i64, lon: i64, lat: i64) -> Entity {
+        let info = Info {
+            version: 2,
+            timestamp: 1_600_000_000,
+            changeset: 7,
+            uid: 3,
+            user: "osm".to_string(),
+            visible: None,
+        };
+        let node = with_tags(node(id, lon, lat), &[("name", &id.to_string())]);
+        with_info(node, info)
+    }
+
+    fn select(entities: &[Entity], order: &[usize]) -> Vec<Entity> {
+        order.iter().map(|&i| entities[i].clone()).collect()
+    }
+
+    fn decode(data: &[u8]) -> Vec<Entity> {
+        let (block_index, _) = build_block_index(data, false).unwrap();
+        let mut entities = Entities::default();
+        for idx in block_index {
+            if idx.block_type != BlockType::Header {
+                decode_block(&read_block(data, &idx).unwrap(), &mut entities).unwrap();
+            }
+        }
+        let nodes = entities.nodes.into_iter().map(Entity::Node);
+        let ways = entities.ways.into_iter().map(Entity::Way);
+        let relations = entities.relations.into_iter().map(Entity::Relation);
+        nodes.chain(ways).chain(relations).collect()
+    }
+
+    #[test]
+    fn test_hilbert_order() {
+        let nodes = vec![
+            tagged_node(1, 0, 0),
+            tagged_node(2, 1000, 1000),
+            tagged_node(3, 0, 1000),
+            tagged_node(4, 1000, 0),
+        ];
+        let ways = vec![
+            with_tags(way(10, &[2]), &[("highway", "path")]),
+            way(11, &[42]),
+            way(12, &[1, 3]),
+            way(13, &[4, 42]),
+        ];
+        let relation = relation(100, &[(MemberType::Way, 10, "outer")]);
+
+        let mut entities = nodes.clone();
+        entities.extend(ways.iter().cloned());
+        entities.push(relation.clone());
+        let input = pbf(entities);
+
+        let mut expected = select(&nodes, &[0, 2, 1, 3]);
+        // the way without any node in the input comes last
+        expected.extend(select(&ways, &[2, 0, 3, 1]));
+        expected.push(relation);
+        for &window_size in &[1, 3, WINDOW_SIZE] {
+            let output = write_pbf_in_windows(&input, false, Vec::new(), window_size).unwrap();
+            assert_eq!(decode(&output), expected);
+        }
+    }
+
+    #[test]
+    fn test_compile_hilbert_order() {
+        let ways: [(i64, &[i64]); 3] = [(10, &[2]), (11, &[1, 3]), (12, &[4, 42])];
+        let mut entities = vec![
+            node(1, 0, 0),
+            node(2, 1000, 1000),
+            node(3, 0, 1000),
+            node(4, 1000, 0),
+        ];
+        entities.extend(ways.iter().map(|&(id, refs)| way(id, refs)));
+        entities.push(relation(
+            100,
+            &[
+                (MemberType::Node, 4, "a"),
+                (MemberType::Way, 10, "b"),
+                (MemberType::Relation, 101, "c"),
+            ],
+        ));
+        entities.push(relation(101, &[(MemberType::Way, 12, "")]));
+        let args = ["--hilbert-order", "--with-id-index"];
+        let (_dir, archive) = compile_entities(entities, &args);
+
+        let node_ids: Vec<_> = archive.nodes().iter().map(|node| node.id()).collect();
+        assert_eq!(node_ids, vec![1, 3, 2, 4]);
+        assert_eq!(way_ids(&archive), vec![11, 10, 12]);
+        for &(id, refs) in &ways {
+            let idx = way_ids(&archive).iter().position(|&i| i == id).unwrap();
+            let refs = refs.iter().map(|&id| Some(id).filter(|&id| id != 42));
+            assert_eq!(way_refs(&archive, idx), refs.collect::<Vec<_>>());
+        }
+        let member = |member_type, id, role: &str| (member_type, Some(id), role.to_string());
+        assert_eq!(
+            relation_members(&archive, 0),
+            vec![
+                member(MemberType::Node, 4, "a"),
+                member(MemberType::Way, 10, "b"),
+                member(MemberType::Relation, 101, "c"),
+            ]
+        );
+        assert_eq!(
+            relation_members(&archive, 1),
+            vec![member(MemberType::Way, 12, "")]
+        );
+
+        for id in 1..=4 {
+            assert_eq!(archive.node_by_id(id).map(|node| node.id()), Some(id));
+        }
+        for id in 10..=12 {
+            assert_eq!(archive.way_by_id(id).map(|way| way.id()), Some(id));
+        }
+        for id in 100..=101 {
+            assert_eq!(archive.relation_by_id(id).map(|r| r.id()), Some(id));
+        }
+    }
+}
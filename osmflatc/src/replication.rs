@@ -0,0 +1,285 @@
+//! Incremental updates of an archive from a local replication directory.
+//!
+//! The directory is laid out like an OSM replication server: `state.txt`
+//! contains the latest sequence number, and the diff with sequence number
+//! `123456789` is stored as `123/456/789.osc.gz`.
+
+use crate::args::{CompileOptions, UpdateArgs};
+use crate::changes::{compile_with_changes, read_header, Changes};
+use crate::{input, osmxml};
+
+use flatdata::FileResourceStorage;
+use log::info;
+use osmflat::Osm;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Errors which can occur while updating an archive from a replication
+/// directory.
+#[derive(Debug)]
+pub enum Error {
+    /// The header of the archive has no replication sequence number.
+    MissingSequenceNumber,
+    /// A state file is invalid.
+    InvalidState { path: PathBuf, reason: &'static str },
+    /// A diff required for the update is missing in the replication directory.
+    MissingDiff { path: PathBuf },
+    /// A diff could not be read.
+    InvalidDiff {
+        path: PathBuf,
+        source: osmxml::Error,
+    },
+    /// A file of the replication directory could not be read.
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingSequenceNumber => write!(
+                f,
+                "archive has no replication sequence number, \
+                 it can be set with `apply-changes --sequence-number`"
+            ),
+            Error::InvalidState { path, reason } => {
+                write!(f, "invalid state file {}: {}", path.display(), reason)
+            }
+            Error::MissingDiff { path } => write!(f, "missing diff {}", path.display()),
+            Error::InvalidDiff { path, source } => {
+                write!(f, "invalid diff {}: {}", path.display(), source)
+            }
+            Error::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidDiff { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Replication state as stored in `state.txt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    pub sequence_number: i64,
+    /// Timestamp in seconds since epoch
+    pub timestamp: Option<i64>,
+}
+
+impl State {
+    /// Reads a state file.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let file = File::open(path).map_err(io_error)?;
+        Self::read(BufReader::new(file))
+            .map_err(io_error)?
+            .map_err(|reason| Error::InvalidState {
+                path: path.to_path_buf(),
+                reason,
+            })
+    }
+
+    /// Reads a state file in Java properties format, e.g.
+    ///
+    /// ```text
+    /// #Sat Oct 17 20:21:04 UTC 2020
+    /// sequenceNumber=4235123
+    /// timestamp=2020-10-17T20\:21\:02Z
+    /// ```
+    fn read<R: BufRead>(input: R) -> io::Result<Result<Self, &'static str>> {
+        let mut sequence_number = None;
+        let mut timestamp = None;
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(pos) => (line[..pos].trim(), unescape(line[pos + 1..].trim())),
+                None => continue,
+            };
+            match key {
+                "sequenceNumber" => match value.parse() {
+                    Ok(number) => sequence_number = Some(number),
+                    Err(_) => return Ok(Err("invalid sequenceNumber")),
+                },
+                "timestamp" => match osmxml::parse_timestamp(&value) {
+                    Some(value) => timestamp = Some(value),
+                    None => return Ok(Err("invalid timestamp")),
+                },
+                _ => (),
+            }
+        }
+        Ok(match sequence_number {
+            Some(sequence_number) => Ok(Self {
+                sequence_number,
+                timestamp,
+            }),
+            None => Err("missing sequenceNumber"),
+        })
+    }
+}
+
+/// Removes the escaping backslashes of a properties value.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Path of a file of the replication directory without extension, e.g.
+/// `123/456/789` for the sequence number `123456789`.
+fn sequence_path(dir: &Path, sequence_number: i64) -> PathBuf {
+    let digits = format!("{:09}", sequence_number);
+    let (prefix, last) = digits.split_at(digits.len() - 3);
+    let (first, second) = prefix.split_at(prefix.len() - 3);
+    dir.join(first).join(second).join(last)
+}
+
+/// Path of the diff with the given sequence number.
+pub fn diff_path(dir: &Path, sequence_number: i64) -> PathBuf {
+    sequence_path(dir, sequence_number).with_extension("osc.gz")
+}
+
+/// Path of the state file belonging to the diff with the given sequence
+/// number.
+pub fn state_path(dir: &Path, sequence_number: i64) -> PathBuf {
+    sequence_path(dir, sequence_number).with_extension("state.txt")
+}
+
+/// Applies all diffs of a replication directory, which are newer than the
+/// archive, and compiles a new archive.
+pub fn run(args: &UpdateArgs, options: &CompileOptions) -> Result<(), crate::Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.archive.clone()))?;
+    let mut header = read_header(&archive);
+    let current = header
+        .osmosis_replication_sequence_number
+        .ok_or(Error::MissingSequenceNumber)?;
+
+    let mut state = State::open(&args.replication_dir.join("state.txt"))?;
+    if state.sequence_number <= current {
+        info!(
+            "Archive is up to date at sequence number {}, nothing to do",
+            current
+        );
+        return Ok(());
+    }
+    if let Some(max_diffs) = args.max_diffs.map(i64::from) {
+        if state.sequence_number - current > max_diffs {
+            let sequence_number = current + max_diffs;
+            let path = state_path(&args.replication_dir, sequence_number);
+            state = if path.is_file() {
+                State::open(&path)?
+            } else {
+                State {
+                    sequence_number,
+                    timestamp: None,
+                }
+            };
+        }
+    }
+
+    // check that all diffs exist before spending time on reading them
+    let diffs: Vec<_> = (current + 1..=state.sequence_number)
+        .map(|sequence_number| diff_path(&args.replication_dir, sequence_number))
+        .collect();
+    if let Some(path) = diffs.iter().find(|path| !path.is_file()) {
+        return Err(Error::MissingDiff { path: path.clone() }.into());
+    }
+
+    info!(
+        "Reading {} diffs from sequence number {} to {}...",
+        diffs.len(),
+        current + 1,
+        state.sequence_number
+    );
+    let mut changes = Changes::default();
+    for path in diffs {
+        let input = input::open_decompressed(&path).map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let diff = Changes::read(input).map_err(|source| Error::InvalidDiff { path, source })?;
+        changes.append(diff);
+    }
+
+    header.osmosis_replication_sequence_number = Some(state.sequence_number);
+    if let Some(timestamp) = state.timestamp.or_else(|| changes.latest_timestamp()) {
+        header.osmosis_replication_timestamp = Some(timestamp);
+    }
+
+    compile_with_changes(&archive, changes, &header, &args.output, options)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_state() {
+        let state = State::read(
+            &b"#Sat Oct 17 20:21:04 UTC 2020\n\
+               sequenceNumber=4235123\n\
+               timestamp=2020-10-17T20\\:21\\:02Z\n"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            state,
+            Ok(State {
+                sequence_number: 4_235_123,
+                timestamp: Some(1_602_966_062),
+            })
+        );
+
+        let state = State::read(&b"sequenceNumber = 12\n"[..]).unwrap();
+        assert_eq!(
+            state,
+            Ok(State {
+                sequence_number: 12,
+                timestamp: None,
+            })
+        );
+
+        let state = State::read(&b"timestamp=2020-10-17T20\\:21\\:02Z\n"[..]).unwrap();
+        assert_eq!(state, Err("missing sequenceNumber"));
+        let state = State::read(&b"sequenceNumber=x\n"[..]).unwrap();
+        assert_eq!(state, Err("invalid sequenceNumber"));
+    }
+
+    #[test]
+    fn test_paths() {
+        let dir = Path::new("replication");
+        assert_eq!(
+            diff_path(dir, 4_235_123),
+            Path::new("replication/004/235/123.osc.gz")
+        );
+        assert_eq!(
+            state_path(dir, 12),
+            Path::new("replication/000/000/012.state.txt")
+        );
+        assert_eq!(
+            diff_path(dir, 1_234_567_890),
+            Path::new("replication/1234/567/890.osc.gz")
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Report of references to entities, which are missing in the input.
+
+use crate::osmpbf::relation::MemberType;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writer of unresolved references as tab-separated values.
+///
+/// Every line contains the type and id of the referencing entity, the type
+/// and id of the missing entity, and the role of the member (empty for nodes
+/// of ways).
+pub struct UnresolvedReport<W: Write = BufWriter<File>> {
+    writer: W,
+}
+
+impl UnresolvedReport {
+    /// Creates a report file at `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> UnresolvedReport<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "referrer_type\treferrer_id\ttype\tid\trole")?;
+        Ok(Self { writer })
+    }
+
+    /// Writes a reference from the entity `referrer_id` to the missing entity
+    /// `id`.
+    pub fn write(
+        &mut self,
+        referrer_type: MemberType,
+        referrer_id: i64,
+        member_type: MemberType,
+        id: i64,
+        role: &[u8],
+    ) -> io::Result<()> {
+        // tabs and line breaks would break the format
+        let role = String::from_utf8_lossy(role).replace(&['\t', '\n', '\r'][..], " ");
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t{}",
+            type_name(referrer_type),
+            referrer_id,
+            type_name(member_type),
+            id,
+            role
+        )
+    }
+
+    /// Flushes the report and returns the underlying writer.
+    pub fn close(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn type_name(member_type: MemberType) -> &'static str {
+    match member_type {
+        MemberType::Node => "node",
+        MemberType::Way => "way",
+        MemberType::Relation => "relation",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_report() {
+        let mut report = UnresolvedReport::new(Vec::new()).unwrap();
+        report
+            .write(MemberType::Way, 10, MemberType::Node, 3, b"")
+            .unwrap();
+        report
+            .write(MemberType::Relation, 20, MemberType::Way, 11, b"outer\tway")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(report.close().unwrap()).unwrap(),
+            "referrer_type\treferrer_id\ttype\tid\trole\n\
+             way\t10\tnode\t3\t\n\
+             relation\t20\tway\t11\touter way\n"
+        );
+    }
+}
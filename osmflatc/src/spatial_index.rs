@@ -0,0 +1,280 @@
+//! Packed Hilbert R-trees over the nodes and the bounding boxes of ways.
+
+use crate::filter::BBox;
+use crate::Error;
+
+use osmflat::{Osm, OsmBuilder, RTREE_NODE_SIZE};
+use rayon::prelude::*;
+
+/// Builds the R-trees of `archive` and writes them with `builder`.
+///
+/// The bounding boxes of all nodes resp. ways are sorted in memory, which
+/// needs 40 bytes per node and way.
+pub fn build(archive: &Osm, builder: &OsmBuilder) -> Result<(), Error> {
+    let nodes = archive.nodes();
+    let node_boxes = nodes
+        .iter()
+        .enumerate()
+        .map(|(idx, node)| (point(node.lon(), node.lat()), idx as u64))
+        .collect();
+    let node_indexes = serialize_rtree(node_boxes, builder.start_node_rtree()?)?;
+    let mut node_index = builder.start_node_rtree_index()?;
+    for idx in node_indexes {
+        node_index.grow()?.set_value(Some(idx));
+    }
+    node_index.close()?;
+
+    let nodes_index = archive.nodes_index();
+    let way_boxes = archive
+        .ways()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, way)| {
+            // ways without any resolved node have no location
+            nodes_index[way.refs().start as usize..way.refs().end as usize]
+                .iter()
+                .filter_map(|node_idx| node_idx.value())
+                .map(|node_idx| {
+                    let node = &nodes[node_idx as usize];
+                    point(node.lon(), node.lat())
+                })
+                .reduce(union)
+                .map(|bbox| (bbox, idx as u64))
+        })
+        .collect();
+    let way_indexes = serialize_rtree(way_boxes, builder.start_way_rtree()?)?;
+    let mut way_index = builder.start_way_rtree_index()?;
+    for idx in way_indexes {
+        way_index.grow()?.set_value(idx);
+    }
+    way_index.close()?;
+    Ok(())
+}
+
+/// Sorts `items` by the Hilbert value of the centers of their boxes and writes
+/// the levels of the R-tree over them to `tree`, starting with the leaves.
+///
+/// Returns the indexes of the items in the order of the leaves.
+fn serialize_rtree(
+    mut items: Vec<(BBox, u64)>,
+    mut tree: flatdata::ExternalVector<osmflat::BBox>,
+) -> Result<Vec<u64>, Error> {
+    if let Some(extent) = items.iter().map(|(bbox, _)| *bbox).reduce(union) {
+        items.par_sort_unstable_by_key(|(bbox, _)| hilbert_value(bbox, &extent));
+    }
+
+    let mut write = |bbox: &BBox| -> Result<(), Error> {
+        let node = tree.grow()?;
+        node.set_min_lon(bbox.min_lon);
+        node.set_min_lat(bbox.min_lat);
+        node.set_max_lon(bbox.max_lon);
+        node.set_max_lat(bbox.max_lat);
+        Ok(())
+    };
+    // the leaves are written directly, so that only the upper levels, which
+    // are smaller by a factor of the node size, are held in memory
+    for (bbox, _) in &items {
+        write(bbox)?;
+    }
+    let node_size = RTREE_NODE_SIZE as usize;
+    let mut level: Vec<BBox> = if items.len() > 1 {
+        items
+            .chunks(node_size)
+            .map(|children| {
+                children
+                    .iter()
+                    .map(|(bbox, _)| *bbox)
+                    .reduce(union)
+                    .unwrap()
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    while !level.is_empty() {
+        for bbox in &level {
+            write(bbox)?;
+        }
+        if level.len() == 1 {
+            break;
+        }
+        level = level
+            .chunks(node_size)
+            .map(|children| children.iter().copied().reduce(union).unwrap())
+            .collect();
+    }
+    tree.close()?;
+    Ok(items.into_iter().map(|(_, idx)| idx).collect())
+}
+
+pub fn point(lon: i64, lat: i64) -> BBox {
+    BBox {
+        min_lon: lon,
+        min_lat: lat,
+        max_lon: lon,
+        max_lat: lat,
+    }
+}
+
+pub fn union(a: BBox, b: BBox) -> BBox {
+    BBox {
+        min_lon: a.min_lon.min(b.min_lon),
+        min_lat: a.min_lat.min(b.min_lat),
+        max_lon: a.max_lon.max(b.max_lon),
+        max_lat: a.max_lat.max(b.max_lat),
+    }
+}
+
+/// Hilbert value of the center of `bbox` on a 2^16 x 2^16 grid over `extent`.
+pub fn hilbert_value(bbox: &BBox, extent: &BBox) -> u32 {
+    let (x, y) = grid_cell(bbox, extent);
+    hilbert(x, y)
+}
+
+/// Cell of the center of `bbox` on a 2^16 x 2^16 grid over `extent`.
+pub fn grid_cell(bbox: &BBox, extent: &BBox) -> (u32, u32) {
+    let scale = |min: i64, max: i64, extent_min: i64, extent_max: i64| {
+        let center = i128::from(min) + i128::from(max) - 2 * i128::from(extent_min);
+        let size = 2 * (i128::from(extent_max) - i128::from(extent_min));
+        (center * 0xFFFF / size.max(1)) as u32
+    };
+    (
+        scale(bbox.min_lon, bbox.max_lon, extent.min_lon, extent.max_lon),
+        scale(bbox.min_lat, bbox.max_lat, extent.min_lat, extent.max_lat),
+    )
+}
+
+/// Position of `(x, y)` on the Hilbert curve filling a 2^16 x 2^16 grid.
+///
+/// Based on the branch-free algorithm described in "Fast Hilbert curve generation,
+/// sorting, and range queries" by rawrunprotected.
+pub fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    for shift in [2, 4].iter() {
+        a = aa;
+        b = bb;
+        c = cc;
+        d = dd;
+        aa = (a & (a >> shift)) ^ (b & (b >> shift));
+        bb = (a & (b >> shift)) ^ (b & ((a ^ b) >> shift));
+        cc ^= (a & (c >> shift)) ^ (b & (d >> shift));
+        dd ^= (b & (c >> shift)) ^ ((a ^ b) & (d >> shift));
+    }
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let interleave = |mut i: u32| {
+        i = (i | (i << 8)) & 0x00FF_00FF;
+        i = (i | (i << 4)) & 0x0F0F_0F0F;
+        i = (i | (i << 2)) & 0x3333_3333;
+        (i | (i << 1)) & 0x5555_5555
+    };
+    let i0 = x ^ y;
+    let i1 = b | (0xFFFF ^ (i0 | a));
+    (interleave(i1) << 1) | interleave(i0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn test_hilbert_curve_is_continuous() {
+        // the lower left 256 x 256 cells are the first part of the curve
+        let mut cells = vec![None; 256 * 256];
+        for x in 0..256 {
+            for y in 0..256 {
+                let value = hilbert(x, y) as usize;
+                assert!(value < cells.len());
+                assert!(cells[value].is_none());
+                cells[value] = Some((x as i32, y as i32));
+            }
+        }
+        for pair in cells.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0].unwrap(), pair[1].unwrap());
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        // pseudo random locations, so that the trees have several levels
+        let mut state = 17u64;
+        let mut random = || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            (state >> 33) as i64 % 1000
+        };
+        let mut entities: Vec<_> = (0..1000).map(|id| node(id, random(), random())).collect();
+        for id in 0..300 {
+            let refs = [random(), random(), random()];
+            entities.push(way(id, &refs));
+        }
+        // ways without resolved nodes are not in the index
+        entities.push(way(300, &[5000]));
+        let (_dir, archive) = compile_entities(entities, &["--with-spatial-index"]);
+
+        let nodes = archive.nodes();
+        let node_bbox = |idx: u64| {
+            let node = &nodes[idx as usize];
+            osmflat::BBox::from_coords(node.lon(), node.lat(), node.lon(), node.lat())
+        };
+        let way_bbox = |idx: usize| {
+            let refs = archive.ways()[idx].refs();
+            archive.nodes_index()[refs.start as usize..refs.end as usize]
+                .iter()
+                .filter_map(|node_idx| node_idx.value())
+                .map(|node_idx| {
+                    let node = &nodes[node_idx as usize];
+                    point(node.lon(), node.lat())
+                })
+                .reduce(union)
+                .map(|b| osmflat::BBox::from_coords(b.min_lon, b.min_lat, b.max_lon, b.max_lat))
+        };
+
+        let queries = [
+            (0, 0, 999, 999),
+            (100, 200, 300, 250),
+            (500, 500, 500, 500),
+            (-10, -10, -1, -1),
+            (990, 0, 2000, 10),
+        ];
+        for &(min_lon, min_lat, max_lon, max_lat) in &queries {
+            let bbox = osmflat::BBox::from_coords(min_lon, min_lat, max_lon, max_lat);
+
+            let mut found: Vec<_> = archive.nodes_in_bbox(&bbox).unwrap().collect();
+            found.sort_unstable();
+            let expected: Vec<_> = (0..nodes.len() as u64)
+                .filter(|&idx| node_bbox(idx).intersects(&bbox))
+                .collect();
+            assert_eq!(found, expected);
+
+            let mut found: Vec<_> = archive.ways_in_bbox(&bbox).unwrap().collect();
+            found.sort_unstable();
+            let expected: Vec<_> = (0..archive.ways().len())
+                .filter(|&idx| way_bbox(idx).is_some_and(|way| way.intersects(&bbox)))
+                .map(|idx| idx as u64)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+}
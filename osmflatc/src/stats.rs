@@ -9,6 +9,23 @@ pub struct Stats {
     pub num_unresolved_node_ids: usize,
     pub num_unresolved_way_ids: usize,
     pub num_unresolved_rel_ids: usize,
+    /// Number of ways dropped because of dangling references
+    pub num_dropped_ways: usize,
+    /// Number of relations dropped because of dangling references
+    pub num_dropped_relations: usize,
+    /// Number of references to ways dropped because of dangling references
+    pub num_refs_to_dropped_ways: usize,
+    /// Number of references to relations dropped because of dangling
+    /// references
+    pub num_refs_to_dropped_relations: usize,
+    pub num_skipped_blocks: usize,
+    pub num_evicted_strings: usize,
+    /// Number of references to tags
+    pub num_tag_refs: usize,
+    /// Number of tags stored after deduplication
+    pub num_tags: usize,
+    /// Description of the tag deduplication policy
+    pub tag_dedup: Option<String>,
 }
 
 impl AddAssign for Stats {
@@ -20,6 +37,17 @@ impl AddAssign for Stats {
         self.num_unresolved_node_ids += other.num_unresolved_node_ids;
         self.num_unresolved_way_ids += other.num_unresolved_way_ids;
         self.num_unresolved_rel_ids += other.num_unresolved_rel_ids;
+        self.num_dropped_ways += other.num_dropped_ways;
+        self.num_dropped_relations += other.num_dropped_relations;
+        self.num_refs_to_dropped_ways += other.num_refs_to_dropped_ways;
+        self.num_refs_to_dropped_relations += other.num_refs_to_dropped_relations;
+        self.num_skipped_blocks += other.num_skipped_blocks;
+        self.num_evicted_strings += other.num_evicted_strings;
+        self.num_tag_refs += other.num_tag_refs;
+        self.num_tags += other.num_tags;
+        if other.tag_dedup.is_some() {
+            self.tag_dedup = other.tag_dedup;
+        }
     }
 }
 
@@ -41,6 +69,44 @@ Unresolved ids:
             self.num_unresolved_node_ids,
             self.num_unresolved_way_ids,
             self.num_unresolved_rel_ids
-        )
+        )?;
+        if self.num_dropped_ways > 0 || self.num_dropped_relations > 0 {
+            write!(
+                f,
+                r#"
+Dropped because of dangling references:
+  ways:         {}
+  relations:    {}
+References to dropped entities:
+  ways:         {}
+  relations:    {}"#,
+                self.num_dropped_ways,
+                self.num_dropped_relations,
+                self.num_refs_to_dropped_ways,
+                self.num_refs_to_dropped_relations
+            )?;
+        }
+        if let Some(ref tag_dedup) = self.tag_dedup {
+            write!(
+                f,
+                r#"
+Tags:
+  dedup policy: {}
+  references:   {}
+  stored:       {}"#,
+                tag_dedup, self.num_tag_refs, self.num_tags
+            )?;
+        }
+        if self.num_skipped_blocks > 0 {
+            write!(f, "\nSkipped corrupt blocks: {}", self.num_skipped_blocks)?;
+        }
+        if self.num_evicted_strings > 0 {
+            write!(
+                f,
+                "\nStrings evicted from deduplication: {}",
+                self.num_evicted_strings
+            )?;
+        }
+        Ok(())
     }
 }
@@ -1,50 +1,187 @@
-use inlinable_string::{InlinableString, StringExt};
-use std::collections::HashMap;
+use memmap::Mmap;
 
-#[derive(Debug, Default)]
+use std::collections::hash_map::{DefaultHasher, HashMap};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+/// Size of the buffer of inserted strings, which are not yet written to disk
+const BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Approximate memory used by a single entry of the deduplication index
+const INDEX_ENTRY_SIZE: usize = 32;
+
+/// Minimum number of entries of the deduplication index
+const MIN_INDEX_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    idx: u64,
+    /// Whether the string was found since the last eviction
+    referenced: bool,
+}
+
+/// String table which streams the inserted strings to a temporary file.
+///
+/// Strings are deduplicated by an index from the hash of a string to its
+/// position. The bytes of a string are only stored on disk, and a hash match is
+/// verified against them. The number of entries of the index can be bounded by
+/// a memory budget: if the index is full, entries which were not found since
+/// the last eviction are removed from it. Strings which are evicted from the
+/// index are not deduplicated anymore, which makes the string table larger but
+/// keeps it correct.
+#[derive(Debug)]
 pub struct StringTable {
-    indexed_data: HashMap<InlinableString, u64>,
-    size_in_bytes: u64,
+    file: File,
+    /// Inserted strings which are not yet written to `file`
+    buffer: Vec<u8>,
+    /// Number of bytes written to `file`
+    flushed_size: u64,
+    /// Memory map of `file`, remapped on demand
+    mmap: Option<Mmap>,
+    index: HashMap<u64, IndexEntry>,
+    max_index_entries: usize,
+    num_evicted: usize,
 }
 
 impl StringTable {
-    pub fn new() -> Self {
-        Default::default()
+    /// Creates a new string table with a temporary file in `temp_dir`.
+    ///
+    /// `memory_budget` bounds the memory in bytes used for deduplicating
+    /// strings; if it is `None`, all strings are deduplicated.
+    pub fn new(temp_dir: &Path, memory_budget: Option<usize>) -> io::Result<Self> {
+        let max_index_entries = memory_budget
+            .map(|budget| budget.saturating_sub(BUFFER_SIZE) / INDEX_ENTRY_SIZE)
+            .map_or(usize::MAX, |entries| entries.max(MIN_INDEX_ENTRIES));
+        Ok(Self {
+            file: tempfile::tempfile_in(temp_dir)?,
+            buffer: Vec::new(),
+            flushed_size: 0,
+            mmap: None,
+            index: HashMap::new(),
+            max_index_entries,
+            num_evicted: 0,
+        })
     }
 
     pub fn next_index(&self) -> u64 {
-        self.size_in_bytes
+        self.flushed_size + self.buffer.len() as u64
+    }
+
+    /// Number of strings evicted from the deduplication index.
+    pub fn num_evicted(&self) -> usize {
+        self.num_evicted
     }
 
     /// Inserts a string into string table and returns its index.
     ///
-    /// If the string was already inserted before, the string is deduplicated
-    /// and the index to the previous string is returned.
-    pub fn insert(&mut self, s: &str) -> u64 {
-        // Horrible news, we cannot use entry API since it does not support Borrow
-        // See: https://github.com/rust-lang/rust/issues/56167
-        if let Some(&idx) = self.indexed_data.get(s) {
-            return idx;
+    /// If the string was already inserted before and is still in the
+    /// deduplication index, the index to the previous string is returned.
+    pub fn insert(&mut self, s: &str) -> io::Result<u64> {
+        let hash = hash(s);
+        if let Some(entry) = self.index.get(&hash).copied() {
+            if self.is_stored_at(entry.idx, s.as_bytes())? {
+                self.index.get_mut(&hash).unwrap().referenced = true;
+                return Ok(entry.idx);
+            }
+            // hash collision: the string is inserted again, the index entry is
+            // kept for the previous string
         }
 
-        let idx = self.size_in_bytes;
-        self.indexed_data.insert(s.into(), idx);
-        self.size_in_bytes += s.len() as u64 + 1;
-        idx
+        let idx = self.next_index();
+        self.buffer.extend(s.as_bytes());
+        self.buffer.push(b'\0');
+        if !self.index.contains_key(&hash) {
+            if self.index.len() >= self.max_index_entries {
+                self.evict();
+            }
+            self.index.insert(
+                hash,
+                IndexEntry {
+                    idx,
+                    referenced: false,
+                },
+            );
+        }
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.flush()?;
+        }
+        Ok(idx)
+    }
+
+    /// Returns the data of the string table.
+    pub fn into_bytes(mut self) -> io::Result<StringTableData> {
+        self.flush()?;
+        if self.flushed_size == 0 {
+            return Ok(StringTableData(None));
+        }
+        Ok(StringTableData(Some(unsafe { Mmap::map(&self.file)? })))
+    }
+
+    /// Checks if the string `s` is stored at `idx`.
+    fn is_stored_at(&mut self, idx: u64, s: &[u8]) -> io::Result<bool> {
+        let end = idx + s.len() as u64 + 1;
+        if end > self.next_index() {
+            return Ok(false);
+        }
+        // strings are flushed as a whole, so they are either completely in
+        // the file or completely in the buffer
+        let data = if idx >= self.flushed_size {
+            &self.buffer[(idx - self.flushed_size) as usize..(end - self.flushed_size) as usize]
+        } else {
+            if end > self.flushed_size {
+                return Ok(false);
+            }
+            if self.mmap.as_ref().map_or(0, |mmap| mmap.len() as u64) < end {
+                self.mmap = Some(unsafe { Mmap::map(&self.file)? });
+            }
+            &self.mmap.as_ref().unwrap()[idx as usize..end as usize]
+        };
+        Ok(&data[..s.len()] == s && data[s.len()] == b'\0')
     }
 
-    pub fn into_bytes(self) -> Vec<u8> {
-        let mut index: Vec<(&InlinableString, &u64)> = self.indexed_data.iter().collect();
-        index.sort_by_key(|(_, &idx)| idx);
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.write_all(&self.buffer)?;
+        self.flushed_size += self.buffer.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
 
-        let mut data = Vec::new();
-        data.reserve(self.size_in_bytes as usize);
-        for (s, &idx) in index {
-            assert!(data.len() as u64 == idx);
-            data.extend(s.as_bytes());
-            data.push(b'\0');
+    /// Halves the number of entries in the deduplication index.
+    ///
+    /// Entries which were not found since the last eviction are removed first.
+    /// If this is not enough, entries are removed by the bits of their hash,
+    /// which selects them at random.
+    fn evict(&mut self) {
+        let len = self.index.len();
+        self.index
+            .retain(|_, entry| std::mem::replace(&mut entry.referenced, false));
+        let target_len = self.max_index_entries / 2;
+        let mut bit = 0;
+        while self.index.len() > target_len && bit < 64 {
+            self.index.retain(|hash, _| (hash >> bit) & 1 == 0);
+            bit += 1;
         }
-        data
+        self.num_evicted += len - self.index.len();
+    }
+}
+
+fn hash(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Data of a string table, which is mapped from disk.
+pub struct StringTableData(Option<Mmap>);
+
+impl Deref for StringTableData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0.as_deref().unwrap_or(&[])
     }
 }
 
@@ -54,19 +191,52 @@ mod test {
     use proptest::prelude::*;
     use std::collections::HashSet;
 
+    fn string_table(memory_budget: Option<usize>) -> StringTable {
+        StringTable::new(&std::env::temp_dir(), memory_budget).unwrap()
+    }
+
     #[test]
     fn test_simple_insert() {
-        let mut st = StringTable::new();
-        assert_eq!(st.insert("hello"), 0);
-        assert_eq!(st.insert("world"), 6);
-        assert_eq!(st.insert("world"), 6);
-        assert_eq!(st.insert("!"), 6 + 6);
-        assert_eq!(st.insert("!"), 6 + 6);
-        assert_eq!(st.insert("!"), 6 + 6);
-
-        let bytes = st.into_bytes();
+        let mut st = string_table(None);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert("world").unwrap(), 6);
+        assert_eq!(st.insert("world").unwrap(), 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
+
+        let bytes = st.into_bytes().unwrap();
         println!("{}", ::std::str::from_utf8(&bytes).unwrap());
-        assert_eq!(bytes, b"hello\0world\0!\0");
+        assert_eq!(&bytes[..], b"hello\0world\0!\0");
+    }
+
+    #[test]
+    fn test_empty() {
+        let st = string_table(None);
+        assert!(st.into_bytes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bounded_memory() {
+        let mut st = string_table(Some(0));
+        let strings: Vec<String> = (0..5000).map(|i| format!("s{}", i % 3000)).collect();
+        let indices: Vec<u64> = strings.iter().map(|s| st.insert(s).unwrap()).collect();
+        // frequently used string survives the eviction
+        let frequent = st.insert("frequent").unwrap();
+        for s in &strings {
+            st.insert(s).unwrap();
+            assert_eq!(st.insert("frequent").unwrap(), frequent);
+        }
+        assert!(st.num_evicted() > 0);
+
+        let bytes = st.into_bytes().unwrap();
+        for (s, idx) in strings.iter().zip(indices) {
+            let idx = idx as usize;
+            assert_eq!(
+                &bytes[idx..idx + s.len() + 1],
+                format!("{}\0", s).as_bytes()
+            );
+        }
     }
 
     #[derive(Debug, Default)]
@@ -89,13 +259,13 @@ mod test {
         #[test]
         fn sequence_of_insert(ref seq in prop::collection::vec(".*", 1..100))
         {
-            let mut st = StringTable::new();
+            let mut st = string_table(None);
             let mut reference_st = ReferenceStringTable::default();
             for input in seq {
-                st.insert(input);
+                st.insert(input).unwrap();
                 reference_st.insert(input.into());
             }
-            assert_eq!(st.into_bytes(), reference_st.data);
+            assert_eq!(&st.into_bytes().unwrap()[..], &reference_st.data[..]);
         }
     }
 }
@@ -0,0 +1,231 @@
+//! Inverted index from tags and keys to the entities carrying them.
+
+use crate::osmpbf::relation::MemberType;
+use crate::Error;
+
+use osmflat::{Osm, OsmBuilder};
+use rayon::prelude::*;
+
+use std::convert::TryFrom;
+use std::iter;
+use std::ops::Range;
+
+/// A string together with its index in the string table.
+type IndexedString<'a> = (u64, &'a [u8]);
+
+/// Entities carrying a tag or a key, sorted by their index.
+#[derive(Debug, Default, PartialEq)]
+struct Entities {
+    key_idx: u64,
+    value_idx: Option<u64>,
+    nodes: Vec<u64>,
+    ways: Vec<u64>,
+    relations: Vec<u64>,
+}
+
+/// Builder of the inverted index.
+///
+/// Tags are identified by their strings, since equal strings may be stored at
+/// different positions of the string table. The distinct tags and keys are
+/// numbered in the order of their strings; the index is built by sorting the
+/// pairs of these numbers and the entities, like the node ways index.
+#[derive(Debug, Default)]
+struct TagIndexBuilder {
+    /// Key and value of each distinct tag and key, sorted by their strings
+    entries: Vec<(u64, Option<u64>)>,
+    /// Numbers of the key and of the tag for each tag of the archive
+    tag_entries: Vec<(u32, u32)>,
+    refs: Vec<(u32, MemberType, u64)>,
+}
+
+impl TagIndexBuilder {
+    /// Numbers the distinct tags and keys of the tags of an archive, given as
+    /// pairs of keys and values.
+    ///
+    /// Fails if there are more distinct tags and keys than fit into 32 bits.
+    fn new<'a>(
+        tags: impl Iterator<Item = (IndexedString<'a>, IndexedString<'a>)>,
+    ) -> Result<Self, Error> {
+        let mut tags: Vec<_> = tags.enumerate().collect();
+        tags.par_sort_unstable_by_key(|&(_, ((key_idx, key), (value_idx, value)))| {
+            (key, value, key_idx, value_idx)
+        });
+
+        let mut entries = Vec::new();
+        let mut tag_entries = vec![(0, 0); tags.len()];
+        let mut key_entry = 0;
+        let mut previous = None;
+        for (tag, ((key_idx, key), (value_idx, value))) in tags {
+            let is_new_key = previous.is_none_or(|(previous_key, _)| previous_key != key);
+            if is_new_key {
+                key_entry = entry_number(entries.len())?;
+                entries.push((key_idx, None));
+            }
+            if is_new_key || previous != Some((key, value)) {
+                entries.push((entries[key_entry as usize].0, Some(value_idx)));
+            }
+            tag_entries[tag] = (key_entry, entry_number(entries.len() - 1)?);
+            previous = Some((key, value));
+        }
+        Ok(Self {
+            entries,
+            tag_entries,
+            refs: Vec::new(),
+        })
+    }
+
+    /// Adds the tag at index `tag_idx` in the `tags` vector of an entity.
+    fn add(&mut self, entity_type: MemberType, idx: u64, tag_idx: u64) {
+        let (key_entry, tag_entry) = self.tag_entries[tag_idx as usize];
+        self.refs.push((key_entry, entity_type, idx));
+        self.refs.push((tag_entry, entity_type, idx));
+    }
+
+    /// Returns the entries sorted by key and value.
+    fn build(mut self) -> impl Iterator<Item = Entities> {
+        self.refs.par_sort_unstable();
+        // an entity can carry the same key several times
+        self.refs.dedup();
+
+        let entries = self.entries;
+        let mut refs = self.refs.into_iter().peekable();
+        iter::from_fn(move || {
+            let &(entry, ..) = refs.peek()?;
+            let (key_idx, value_idx) = entries[entry as usize];
+            let mut entities = Entities {
+                key_idx,
+                value_idx,
+                ..Default::default()
+            };
+            while let Some((_, entity_type, idx)) = refs.next_if(|&(e, ..)| e == entry) {
+                match entity_type {
+                    MemberType::Node => entities.nodes.push(idx),
+                    MemberType::Way => entities.ways.push(idx),
+                    MemberType::Relation => entities.relations.push(idx),
+                }
+            }
+            Some(entities)
+        })
+    }
+}
+
+/// Number of the entry at position `pos`, which is stored in 32 bits.
+fn entry_number(pos: usize) -> Result<u32, Error> {
+    u32::try_from(pos).map_err(|_| "too many distinct tags and keys for the tag index".into())
+}
+
+/// Builds the inverted tag index of `archive` and writes it with `builder`.
+///
+/// Returns the number of entries of the index.
+pub fn build(archive: &Osm, builder: &OsmBuilder) -> Result<usize, Error> {
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+    let strings = archive.stringtable();
+
+    let mut index = TagIndexBuilder::new(tags.iter().map(|tag| {
+        let string = |idx: u64| (idx, strings.substring_raw(idx as usize));
+        (string(tag.key_idx()), string(tag.value_idx()))
+    }))?;
+    let mut add = |entity_type, idx: usize, range: Range<u64>| {
+        for tag_idx in range {
+            index.add(
+                entity_type,
+                idx as u64,
+                tags_index[tag_idx as usize].value(),
+            );
+        }
+    };
+    for (idx, node) in archive.nodes().iter().enumerate() {
+        add(MemberType::Node, idx, node.tags());
+    }
+    for (idx, way) in archive.ways().iter().enumerate() {
+        add(MemberType::Way, idx, way.tags());
+    }
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        add(MemberType::Relation, idx, relation.tags());
+    }
+    let mut tag_entities = builder.start_tag_entities()?;
+    let mut nodes = builder.start_tag_entities_nodes()?;
+    let mut ways = builder.start_tag_entities_ways()?;
+    let mut relations = builder.start_tag_entities_relations()?;
+    let mut num_entries = 0;
+    for entry in index.build() {
+        let tag_entity = tag_entities.grow()?;
+        tag_entity.set_key_idx(entry.key_idx);
+        tag_entity.set_value_idx(entry.value_idx);
+        tag_entity.set_node_first_idx(nodes.len() as u64);
+        tag_entity.set_way_first_idx(ways.len() as u64);
+        tag_entity.set_relation_first_idx(relations.len() as u64);
+        for &idx in &entry.nodes {
+            nodes.grow()?.set_value(Some(idx));
+        }
+        for &idx in &entry.ways {
+            ways.grow()?.set_value(idx);
+        }
+        for &idx in &entry.relations {
+            relations.grow()?.set_value(idx);
+        }
+        num_entries += 1;
+    }
+    {
+        let sentinel = tag_entities.grow()?;
+        sentinel.set_value_idx(None);
+        sentinel.set_node_first_idx(nodes.len() as u64);
+        sentinel.set_way_first_idx(ways.len() as u64);
+        sentinel.set_relation_first_idx(relations.len() as u64);
+    }
+    tag_entities.close()?;
+    nodes.close()?;
+    ways.close()?;
+    relations.close()?;
+    Ok(num_entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_tag_index() {
+        let tags: [(IndexedString, IndexedString); 5] = [
+            ((0, b"amenity"), (8, b"pub")),
+            ((0, b"amenity"), (12, b"bar")),
+            ((16, b"name"), (21, b"Bar")),
+            // same string stored twice in the string table
+            ((25, b"amenity"), (8, b"pub")),
+            ((25, b"amenity"), (12, b"bar")),
+        ];
+        let mut index = TagIndexBuilder::new(tags.iter().copied()).unwrap();
+        index.add(MemberType::Node, 0, 0);
+        index.add(MemberType::Node, 2, 1);
+        index.add(MemberType::Node, 2, 2);
+        index.add(MemberType::Way, 1, 3);
+        index.add(MemberType::Way, 1, 4);
+
+        let entries: Vec<_> = index.build().collect();
+        let entry = |key_idx, value_idx, nodes: &[u64], ways: &[u64]| Entities {
+            key_idx,
+            value_idx,
+            nodes: nodes.to_vec(),
+            ways: ways.to_vec(),
+            relations: Vec::new(),
+        };
+        assert_eq!(
+            entries,
+            vec![
+                entry(0, None, &[0, 2], &[1]),
+                entry(0, Some(12), &[2], &[1]),
+                entry(0, Some(8), &[0], &[1]),
+                entry(16, None, &[2], &[]),
+                entry(16, Some(21), &[2], &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_number() {
+        assert_eq!(entry_number(0).unwrap(), 0);
+        assert_eq!(entry_number(u32::MAX as usize).unwrap(), u32::MAX);
+        assert!(entry_number(u32::MAX as usize + 1).is_err());
+    }
+}
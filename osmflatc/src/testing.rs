@@ -0,0 +1,210 @@
+//! Helpers for tests compiling small archives.
+
+use crate::args::CompileOptions;
+use crate::entities::{Entity, Info, Member, Node, Relation, Tags, Way};
+use crate::osmpbf::{self, relation::MemberType};
+use crate::pbfwriter::PbfWriter;
+use crate::Error;
+
+use flatdata::FileResourceStorage;
+use osmflat::{iter_tags, Osm, RelationMembersRef};
+use prost::Message;
+use structopt::StructOpt;
+use tempfile::TempDir;
+
+use std::ops::Range;
+
+pub fn tags(tags: &[(&str, &str)]) -> Tags {
+    tags.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+pub fn node(id: i64, lon: i64, lat: i64) -> Entity {
+    Entity::Node(Node {
+        id,
+        lat,
+        lon,
+        ..Default::default()
+    })
+}
+
+pub fn way(id: i64, refs: &[i64]) -> Entity {
+    Entity::Way(Way {
+        id,
+        refs: refs.to_vec(),
+        ..Default::default()
+    })
+}
+
+pub fn relation(id: i64, members: &[(MemberType, i64, &str)]) -> Entity {
+    Entity::Relation(Relation {
+        id,
+        members: members
+            .iter()
+            .map(|&(member_type, id, role)| Member {
+                member_type,
+                id,
+                role: role.to_string(),
+            })
+            .collect(),
+        tags: Tags::new(),
+        info: None,
+    })
+}
+
+/// Sets the tags of an entity.
+pub fn with_tags(mut entity: Entity, entity_tags: &[(&str, &str)]) -> Entity {
+    *match &mut entity {
+        Entity::Node(node) => &mut node.tags,
+        Entity::Way(way) => &mut way.tags,
+        Entity::Relation(relation) => &mut relation.tags,
+    } = tags(entity_tags);
+    entity
+}
+
+/// Sets the metadata of an entity.
+pub fn with_info(mut entity: Entity, info: Info) -> Entity {
+    *match &mut entity {
+        Entity::Node(node) => &mut node.info,
+        Entity::Way(way) => &mut way.info,
+        Entity::Relation(relation) => &mut relation.info,
+    } = Some(info);
+    entity
+}
+
+/// Writes entities as PBF data.
+pub fn pbf(entities: Vec<Entity>) -> Vec<u8> {
+    let mut writer = PbfWriter::new(Vec::new(), &Default::default()).unwrap();
+    for entity in entities {
+        writer.write(entity).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+/// Writes PBF data consisting of the given blocks.
+pub fn pbf_from_blocks(header: &osmpbf::HeaderBlock, blocks: &[osmpbf::PrimitiveBlock]) -> Vec<u8> {
+    let mut data = encode_blob("OSMHeader", header);
+    for block in blocks {
+        data.extend(encode_blob("OSMData", block));
+    }
+    data
+}
+
+fn encode_blob<M: Message>(blob_type: &str, message: &M) -> Vec<u8> {
+    let mut data = Vec::new();
+    message.encode(&mut data).unwrap();
+    let mut blob = Vec::new();
+    osmpbf::Blob {
+        raw: Some(data),
+        ..Default::default()
+    }
+    .encode(&mut blob)
+    .unwrap();
+    let mut header = Vec::new();
+    osmpbf::BlobHeader {
+        r#type: blob_type.into(),
+        indexdata: None,
+        datasize: blob.len() as i32,
+    }
+    .encode(&mut header)
+    .unwrap();
+
+    let mut result = (header.len() as u32).to_be_bytes().to_vec();
+    result.extend(header);
+    result.extend(blob);
+    result
+}
+
+/// Compiles PBF data with the given command line options into a temporary
+/// archive.
+///
+/// The archive is removed when the returned directory is dropped.
+pub fn compile_pbf(data: &[u8], args: &[&str]) -> (TempDir, Osm) {
+    try_compile_pbf(data, args).unwrap()
+}
+
+pub fn try_compile_pbf(data: &[u8], args: &[&str]) -> Result<(TempDir, Osm), Error> {
+    let options = CompileOptions::from_iter_safe(Some("osmflatc").iter().chain(args))?;
+    let dir = tempfile::tempdir()?;
+    let output = dir.path().join("archive");
+    crate::compile(data, &output, &options)?;
+    let archive = Osm::open(FileResourceStorage::new(output))?;
+    Ok((dir, archive))
+}
+
+/// Compiles entities with the given command line options into a temporary
+/// archive.
+pub fn compile_entities(entities: Vec<Entity>, args: &[&str]) -> (TempDir, Osm) {
+    compile_pbf(&pbf(entities), args)
+}
+
+pub fn read_tags(archive: &Osm, range: Range<u64>) -> Tags {
+    iter_tags(archive, range)
+        .map(|(key, value)| {
+            (
+                String::from_utf8(key.to_vec()).unwrap(),
+                String::from_utf8(value.to_vec()).unwrap(),
+            )
+        })
+        .collect()
+}
+
+pub fn node_ids(archive: &Osm) -> Vec<i64> {
+    archive.nodes().iter().map(|node| node.id()).collect()
+}
+
+pub fn way_ids(archive: &Osm) -> Vec<i64> {
+    archive.ways().iter().map(|way| way.id()).collect()
+}
+
+pub fn relation_ids(archive: &Osm) -> Vec<i64> {
+    archive
+        .relations()
+        .iter()
+        .map(|relation| relation.id())
+        .collect()
+}
+
+/// Ids of the nodes of a way, `None` for unresolved references.
+pub fn way_refs(archive: &Osm, idx: usize) -> Vec<Option<i64>> {
+    let refs = archive.ways()[idx].refs();
+    archive.nodes_index()[refs.start as usize..refs.end as usize]
+        .iter()
+        .map(|node_idx| {
+            node_idx
+                .value()
+                .map(|idx| archive.nodes()[idx as usize].id())
+        })
+        .collect()
+}
+
+/// Types, ids and roles of the members of a relation, `None` for unresolved
+/// references.
+pub fn relation_members(archive: &Osm, idx: usize) -> Vec<(MemberType, Option<i64>, String)> {
+    let role = |idx: u64| {
+        String::from_utf8(archive.stringtable().substring_raw(idx as usize).to_vec()).unwrap()
+    };
+    archive
+        .relation_members()
+        .at(idx)
+        .map(|member| match member {
+            RelationMembersRef::NodeMember(m) => (
+                MemberType::Node,
+                m.node_idx().map(|idx| archive.nodes()[idx as usize].id()),
+                role(m.role_idx()),
+            ),
+            RelationMembersRef::WayMember(m) => (
+                MemberType::Way,
+                m.way_idx().map(|idx| archive.ways()[idx as usize].id()),
+                role(m.role_idx()),
+            ),
+            RelationMembersRef::RelationMember(m) => (
+                MemberType::Relation,
+                m.relation_idx()
+                    .map(|idx| archive.relations()[idx as usize].id()),
+                role(m.role_idx()),
+            ),
+        })
+        .collect()
+}
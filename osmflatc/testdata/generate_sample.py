@@ -0,0 +1,296 @@
+#!/usr/bin/env python3
+"""Writes sample.o5m and sample.osm.pbf containing the same small dataset.
+
+Both files are encoded directly from the o5m and PBF specifications, without
+using osmflatc, so that tests can compare the compiled archives of both
+formats:
+
+* https://wiki.openstreetmap.org/wiki/O5m
+* https://wiki.openstreetmap.org/wiki/PBF_Format
+
+The o5m file is laid out like the output of osmconvert: it starts with a reset
+and writes another reset before the ways and before the relations, so that
+string references and delta coded values never cross entity types. The PBF
+file stores the nodes as dense nodes in one zlib compressed block and the
+ways and relations in another one.
+"""
+
+import os
+import struct
+import zlib
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+# bbox (left, bottom, right, top) and coordinates in 100 nanodegrees
+BBOX = (133_900_000, 525_100_000, 134_100_000, 525_300_000)
+TIMESTAMP = 1_600_000_000
+
+
+def info(version, timestamp, changeset, uid, user):
+    return dict(version=version, timestamp=timestamp, changeset=changeset, uid=uid, user=user)
+
+
+ALICE = (1, "alice")
+BOB = (2, "bob")
+CAROL = (3, "carol")
+
+NODES = [
+    # id, lon, lat, tags, info
+    (100, 134_050_000, 525_200_000, [("name", "Alexanderplatz"), ("highway", "bus_stop")],
+     info(1, 1_500_000_000, 1000, *ALICE)),
+    (101, 134_049_000, 525_201_000, [], info(2, 1_500_000_100, 1001, *BOB)),
+    (105, 134_060_000, 525_199_000, [("highway", "bus_stop")],
+     info(1, 1_500_000_200, 1000, *ALICE)),
+    (106, 133_990_000, 525_150_000, [("amenity", "cafe"), ("name", "Café Ümlaut")],
+     info(3, 1_500_000_050, 998, *CAROL)),
+    (110, 134_000_000, 525_160_000, [], info(1, 1_400_000_000, 5, *ALICE)),
+]
+
+WAYS = [
+    # id, refs, tags, info
+    (200, [100, 101, 105, 100], [("highway", "footway"), ("name", "Alexanderplatz")],
+     info(1, 1_500_000_300, 1002, *ALICE)),
+    # node 999 is not contained in the data
+    (201, [105, 106, 999], [("highway", "footway")], info(2, 1_500_000_400, 1003, *BOB)),
+]
+
+RELATIONS = [
+    # id, members (type, id, role), tags, info
+    (300, [("n", 100, "stop"), ("w", 200, "platform"), ("w", 201, "")],
+     [("type", "route"), ("route", "bus")], info(1, 1_500_000_500, 1004, *ALICE)),
+    (301, [("r", 300, ""), ("n", 106, "stop")],
+     [("type", "route_master"), ("route", "bus")], info(4, 1_500_000_600, 1005, *CAROL)),
+]
+
+
+def varint(value):
+    result = bytearray()
+    while value >= 0x80:
+        result.append((value & 0x7F) | 0x80)
+        value >>= 7
+    result.append(value)
+    return bytes(result)
+
+
+def zigzag(value):
+    return (value << 1) ^ (value >> 63)
+
+
+def zvarint(value):
+    return varint(zigzag(value))
+
+
+# o5m
+
+
+class O5mWriter:
+    def __init__(self):
+        self.data = bytearray()
+        self.reset()
+
+    def reset(self):
+        self.data.append(0xFF)
+        self.strings = []
+        self.deltas = {}
+
+    def delta(self, name, value):
+        previous = self.deltas.get(name, 0)
+        self.deltas[name] = value
+        return zvarint(value - previous)
+
+    def string(self, entry):
+        """Writes a string pair inline or as a reference to the string table."""
+        if entry in self.strings:
+            # references count from the most recently added entry
+            return varint(len(self.strings) - self.strings.index(entry))
+        if len(entry) <= 252:
+            self.strings.append(entry)
+        return b"\x00" + entry
+
+    def dataset(self, dataset_type, payload):
+        self.data.append(dataset_type)
+        self.data += varint(len(payload)) + payload
+
+    def info(self, info):
+        user = varint(info["uid"]) + b"\x00" + info["user"].encode() + b"\x00"
+        return (
+            varint(info["version"])
+            + self.delta("timestamp", info["timestamp"])
+            + self.delta("changeset", info["changeset"])
+            + self.string(user)
+        )
+
+    def tags(self, tags):
+        return b"".join(
+            self.string(k.encode() + b"\x00" + v.encode() + b"\x00") for k, v in tags
+        )
+
+
+def write_o5m(path):
+    o5m = O5mWriter()
+    o5m.data += b"\xe0\x04o5m2"
+    o5m.dataset(0xDC, zvarint(TIMESTAMP))
+    o5m.dataset(0xDB, b"".join(zvarint(x) for x in BBOX))
+    for node_id, lon, lat, tags, node_info in NODES:
+        payload = o5m.delta("id", node_id) + o5m.info(node_info)
+        payload += o5m.delta("lon", lon) + o5m.delta("lat", lat)
+        o5m.dataset(0x10, payload + o5m.tags(tags))
+    o5m.reset()
+    for way_id, refs, tags, way_info in WAYS:
+        refs = b"".join(o5m.delta("ref", ref) for ref in refs)
+        payload = o5m.delta("id", way_id) + o5m.info(way_info)
+        payload += varint(len(refs)) + refs
+        o5m.dataset(0x11, payload + o5m.tags(tags))
+    o5m.reset()
+    for relation_id, members, tags, relation_info in RELATIONS:
+        encoded = b""
+        for member_type, member_id, role in members:
+            type_char = {"n": b"0", "w": b"1", "r": b"2"}[member_type]
+            encoded += o5m.delta("member_" + member_type, member_id)
+            encoded += o5m.string(type_char + role.encode() + b"\x00")
+        payload = o5m.delta("id", relation_id) + o5m.info(relation_info)
+        payload += varint(len(encoded)) + encoded
+        o5m.dataset(0x12, payload + o5m.tags(tags))
+    o5m.data.append(0xFE)
+    with open(path, "wb") as f:
+        f.write(o5m.data)
+
+
+# PBF
+
+
+def key(field, wire_type):
+    return varint((field << 3) | wire_type)
+
+
+def pb_varint(field, value):
+    return key(field, 0) + varint(value & 0xFFFFFFFFFFFFFFFF)
+
+
+def pb_sint(field, value):
+    return key(field, 0) + zvarint(value)
+
+
+def pb_bytes(field, value):
+    return key(field, 2) + varint(len(value)) + value
+
+
+def pb_packed(field, values, encode=varint):
+    return pb_bytes(field, b"".join(encode(v & 0xFFFFFFFFFFFFFFFF) for v in values))
+
+
+def pb_packed_sint(field, values):
+    return pb_bytes(field, b"".join(zvarint(v) for v in values))
+
+
+def deltas(values):
+    return [v - p for v, p in zip(values, [0] + values[:-1])]
+
+
+class StringTable:
+    def __init__(self):
+        # index 0 is reserved as delimiter
+        self.strings = [b""]
+
+    def get(self, s):
+        s = s.encode()
+        if s not in self.strings:
+            self.strings.append(s)
+        return self.strings.index(s)
+
+    def encode(self):
+        return b"".join(pb_bytes(1, s) for s in self.strings)
+
+
+def primitive_block(strings, groups):
+    block = pb_bytes(1, strings.encode())
+    for group in groups:
+        block += pb_bytes(2, group)
+    return block + pb_varint(17, 100) + pb_varint(18, 1000)
+
+
+def pb_info(strings, info):
+    return (
+        pb_varint(1, info["version"])
+        + pb_varint(2, info["timestamp"])
+        + pb_varint(3, info["changeset"])
+        + pb_varint(4, info["uid"])
+        + pb_varint(5, strings.get(info["user"]))
+    )
+
+
+def tag_fields(strings, tags):
+    return pb_packed(2, [strings.get(k) for k, _ in tags]) + pb_packed(
+        3, [strings.get(v) for _, v in tags]
+    )
+
+
+def nodes_block():
+    strings = StringTable()
+    keys_vals = []
+    for _, _, _, tags, _ in NODES:
+        for k, v in tags:
+            keys_vals += [strings.get(k), strings.get(v)]
+        keys_vals.append(0)
+    infos = [node[4] for node in NODES]
+    dense_info = (
+        pb_packed(1, [i["version"] for i in infos])
+        + pb_packed_sint(2, deltas([i["timestamp"] for i in infos]))
+        + pb_packed_sint(3, deltas([i["changeset"] for i in infos]))
+        + pb_packed_sint(4, deltas([i["uid"] for i in infos]))
+        + pb_packed_sint(5, deltas([strings.get(i["user"]) for i in infos]))
+    )
+    dense = (
+        pb_packed_sint(1, deltas([node[0] for node in NODES]))
+        + pb_bytes(5, dense_info)
+        + pb_packed_sint(8, deltas([node[2] for node in NODES]))
+        + pb_packed_sint(9, deltas([node[1] for node in NODES]))
+        + pb_packed(10, keys_vals)
+    )
+    return primitive_block(strings, [pb_bytes(2, dense)])
+
+
+def ways_and_relations_block():
+    strings = StringTable()
+    ways = b""
+    for way_id, refs, tags, way_info in WAYS:
+        way = pb_varint(1, way_id) + tag_fields(strings, tags)
+        way += pb_bytes(4, pb_info(strings, way_info))
+        way += pb_packed_sint(8, deltas(refs))
+        ways += pb_bytes(3, way)
+    relations = b""
+    for relation_id, members, tags, relation_info in RELATIONS:
+        relation = pb_varint(1, relation_id) + tag_fields(strings, tags)
+        relation += pb_bytes(4, pb_info(strings, relation_info))
+        relation += pb_packed(8, [strings.get(role) for _, _, role in members])
+        relation += pb_packed_sint(9, deltas([member_id for _, member_id, _ in members]))
+        relation += pb_packed(10, ["nwr".index(t) for t, _, _ in members])
+        relations += pb_bytes(4, relation)
+    return primitive_block(strings, [ways, relations])
+
+
+def header_block():
+    left, bottom, right, top = (x * 100 for x in BBOX)
+    bbox = pb_sint(1, left) + pb_sint(2, right) + pb_sint(3, top) + pb_sint(4, bottom)
+    header = pb_bytes(1, bbox)
+    for feature in ("OsmSchema-V0.6", "DenseNodes"):
+        header += pb_bytes(4, feature.encode())
+    return header + pb_varint(32, TIMESTAMP)
+
+
+def blob(blob_type, data):
+    blob = pb_varint(2, len(data)) + pb_bytes(3, zlib.compress(data))
+    header = pb_bytes(1, blob_type.encode()) + pb_varint(3, len(blob))
+    return struct.pack(">I", len(header)) + header + blob
+
+
+def write_pbf(path):
+    with open(path, "wb") as f:
+        f.write(blob("OSMHeader", header_block()))
+        f.write(blob("OSMData", nodes_block()))
+        f.write(blob("OSMData", ways_and_relations_block()))
+
+
+if __name__ == "__main__":
+    write_o5m(os.path.join(HERE, "sample.o5m"))
+    write_pbf(os.path.join(HERE, "sample.osm.pbf"))